@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+use super::error::{Error, Retries};
+
+/// Delay between successive attempts
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    initial: Duration,
+    multiplier: f64,
+    max: Duration,
+}
+
+impl Backoff {
+    /// Wait the same `interval` between attempts
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            initial: interval,
+            multiplier: 1.0,
+            max: interval,
+        }
+    }
+
+    /// Double the delay after every attempt,
+    /// starting at `initial` and capped at `max`
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            multiplier: 2.0,
+            max,
+        }
+    }
+
+    /// Grow the delay by `multiplier` per attempt
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Delay after the given zero-based attempt
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.min(i32::MAX as u32) as i32);
+
+        Duration::try_from_secs_f64(self.initial.as_secs_f64() * factor)
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+
+    /// Delay after the given zero-based attempt, or
+    /// `retry_after` if the server asked for one, at
+    /// most the maximum delay either way
+    pub(crate) fn retry_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(retry_after) => retry_after.min(self.max),
+            None => self.delay(attempt),
+        }
+    }
+}
+
+impl Default for Backoff {
+    /// Exponential from 500ms up to 30s
+    fn default() -> Self {
+        Self::exponential(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+/// Failed attempts of an operation, attached
+/// to its error when giving up
+pub(crate) struct Attempts {
+    start: Instant,
+    outcomes: Vec<Error>,
+    total_delay: Duration,
+}
+
+impl Attempts {
+    pub(crate) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            outcomes: Vec::new(),
+            total_delay: Duration::ZERO,
+        }
+    }
+
+    /// Record `error` of an attempt
+    /// retried after `delay`
+    pub(crate) fn retry(&mut self, error: Error, delay: Duration) {
+        self.outcomes.push(error);
+        self.total_delay += delay;
+    }
+
+    /// `error` of the last attempt, with
+    /// the earlier ones if there were any
+    pub(crate) fn give_up(self, error: Error) -> Error {
+        if self.outcomes.is_empty() {
+            return error;
+        }
+
+        Error::Retried {
+            retries: Box::new(Retries {
+                attempts: self.outcomes.len() as u32 + 1,
+                outcomes: self.outcomes,
+                total_delay: self.total_delay,
+                elapsed: self.start.elapsed(),
+            }),
+            source: Box::new(error),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{Request, Response};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    failover::{rebase, retry_after, strip_base_url},
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// How a `Balancer` picks a base URL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Take turns
+    #[default]
+    RoundRobin,
+    /// The one with the fewest requests in flight
+    LeastOutstanding,
+}
+
+/// Middleware balancing load between
+/// equivalent base URLs
+///
+/// Requests sent to any of the base URLs are
+/// spread across all of them, e.g. mirrors or
+/// read replicas.
+///
+/// Every base URL has a circuit breaker: after
+/// a number of consecutive connection errors or
+/// `5xx` responses it is left out until a cooldown
+/// has elapsed, then tried again. `429` and `503`
+/// responses with `Retry-After` open it right away,
+/// for as long as asked. If every circuit is open,
+/// all base URLs are used.
+///
+/// Clones share their state
+#[derive(Clone, Debug)]
+pub struct Balancer {
+    base_urls: Vec<String>,
+    strategy: Strategy,
+    threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    turn: usize,
+    hosts: Vec<Host>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Host {
+    outstanding: usize,
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Balancer {
+    /// Balance round-robin between `base_urls`
+    ///
+    /// Circuits open after 5 consecutive
+    /// failures for 30 seconds by default
+    pub fn new<I, S>(base_urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let base_urls: Vec<String> = base_urls
+            .into_iter()
+            .map(|base_url| base_url.into().trim_end_matches('/').to_string())
+            .collect();
+
+        Self {
+            state: Arc::new(Mutex::new(State {
+                turn: 0,
+                hosts: vec![Host::default(); base_urls.len()],
+            })),
+            base_urls,
+            strategy: Strategy::default(),
+            threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    /// How to pick a base URL
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Open a circuit after `threshold` consecutive
+    /// failures, for the duration of `cooldown`
+    pub fn circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.threshold = threshold.max(1);
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Pick a base URL and count
+    /// the request as outstanding
+    fn acquire(&self) -> Option<Permit<'_>> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        let mut available: Vec<usize> = (0..state.hosts.len())
+            .filter(|&index| {
+                state.hosts[index]
+                    .open_until
+                    .is_none_or(|until| until <= now)
+            })
+            .collect();
+
+        if available.is_empty() {
+            available = (0..state.hosts.len()).collect();
+        }
+
+        let index = match self.strategy {
+            Strategy::RoundRobin => {
+                let turn = state.turn;
+                state.turn = turn.wrapping_add(1);
+                *available.get(turn % available.len().max(1))?
+            }
+            Strategy::LeastOutstanding => available
+                .into_iter()
+                .min_by_key(|&index| state.hosts[index].outstanding)?,
+        };
+
+        state.hosts[index].outstanding += 1;
+
+        Some(Permit {
+            balancer: self,
+            index,
+        })
+    }
+}
+
+/// An outstanding request to a base URL
+struct Permit<'a> {
+    balancer: &'a Balancer,
+    index: usize,
+}
+
+impl Permit<'_> {
+    fn record(&self, healthy: bool, retry_after: Option<Duration>) {
+        let mut state = self.balancer.state.lock().unwrap();
+        let host = &mut state.hosts[self.index];
+
+        if let Some(retry_after) = retry_after {
+            host.failures = host.failures.saturating_add(1);
+            host.open_until = Instant::now().checked_add(retry_after);
+        } else if healthy {
+            host.failures = 0;
+            host.open_until = None;
+        } else {
+            host.failures = host.failures.saturating_add(1);
+
+            if host.failures >= self.balancer.threshold {
+                host.open_until = Instant::now().checked_add(self.balancer.cooldown);
+            }
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.balancer.state.lock().unwrap().hosts[self.index].outstanding -= 1;
+    }
+}
+
+impl Middleware for Balancer {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let url = request.url().to_string();
+
+            let rest = self
+                .base_urls
+                .iter()
+                .find_map(|base_url| strip_base_url(&url, base_url));
+
+            let Some(permit) = rest.and_then(|_| self.acquire()) else {
+                return next.run(request).await;
+            };
+
+            if let Some(url) = rest.and_then(|rest| rebase(&self.base_urls[permit.index], rest)) {
+                *request.url_mut() = url;
+            }
+
+            let result = next.run(request).await;
+
+            let healthy = match &result {
+                Ok(response) => !response.status().is_server_error(),
+                Err(err) => !err.is_transient(),
+            };
+
+            permit.record(healthy, retry_after(&result));
+
+            result
+        })
+    }
+}
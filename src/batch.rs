@@ -0,0 +1,455 @@
+use std::marker::PhantomData;
+
+use bytes::Bytes;
+use http::{
+    header::{ACCEPT_ENCODING, CONTENT_TYPE},
+    HeaderMap, HeaderName, HeaderValue, StatusCode,
+};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use super::{
+    built::{BodyFormat, BuiltRequest},
+    decode,
+    error::Error,
+    failover::strip_base_url,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+    request_id::request_id,
+    transport::{builder_client, Transport},
+    url::join,
+};
+
+/// Wire format of a batch call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchFormat {
+    /// `{"requests": [{"id", "method", "url",
+    /// "headers", "body"}]}` answered with
+    /// `{"responses": [{"id", "status", "headers",
+    /// "body"}]}`, e.g. Microsoft Graph `$batch`.
+    /// URLs are relative to the base URL
+    Json,
+    /// `multipart/mixed` with one `application/http`
+    /// part per operation, e.g. Google batch endpoints
+    /// and OData `$batch`. URLs are absolute paths
+    Multipart,
+}
+
+/// Many requests sent in a single HTTP call
+///
+/// Requests are prepared against the base URL as
+/// usual and serialized into the envelope. Each
+/// `add` returns a `BatchItem` to pick its typed
+/// result from the `BatchResponse`, so operations
+/// of different response types can share a batch
+pub struct BatchEnvelope {
+    format: BatchFormat,
+    base_url: String,
+    endpoint: String,
+    operations: Vec<BuiltRequest>,
+}
+
+/// Handle of an operation in a batch
+pub struct BatchItem<T> {
+    index: usize,
+    strict: bool,
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> BatchItem<T> {
+    /// Position of the operation in the batch
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T> Clone for BatchItem<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BatchItem<T> {}
+
+/// Response to a single operation of a batch
+#[derive(Clone, Debug)]
+pub struct OperationResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// Demultiplexed responses of a batch
+#[derive(Clone, Debug, Default)]
+pub struct BatchResponse {
+    /// By position of the operation,
+    /// `None` if the server left it out
+    pub responses: Vec<Option<OperationResponse>>,
+}
+
+impl BatchEnvelope {
+    /// Batch sent to `endpoint` below `base_url`,
+    /// which operations are prepared against too
+    pub fn new<B, E>(format: BatchFormat, base_url: B, endpoint: E) -> Self
+    where
+        B: Into<String>,
+        E: Into<String>,
+    {
+        Self {
+            format,
+            base_url: base_url.into(),
+            endpoint: endpoint.into(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Add `request` to the batch
+    pub fn add<R, T>(&mut self, request: &R) -> Result<BatchItem<T>, Error>
+    where
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        let mut built = request.prepare(&self.base_url)?;
+
+        // Bodies of operations are not decompressed
+        built.headers.remove(ACCEPT_ENCODING);
+
+        self.operations.push(built);
+
+        Ok(BatchItem {
+            index: self.operations.len() - 1,
+            strict: request.strict(),
+            response: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Send the batch through `client`
+    ///
+    /// Fails if the batch call itself fails,
+    /// results of individual operations are
+    /// checked by `BatchResponse::get`
+    pub async fn send<C>(&self, client: &C) -> Result<BatchResponse, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let url = join(&self.base_url, &self.endpoint)?;
+
+        let (content_type, body) = match self.format {
+            BatchFormat::Json => ("application/json".to_string(), self.json_envelope()),
+            BatchFormat::Multipart => {
+                let boundary = format!("batch_{}", Uuid::new_v4().simple());
+
+                (
+                    format!("multipart/mixed; boundary={}", boundary),
+                    self.multipart_envelope(&boundary),
+                )
+            }
+        };
+
+        let request = builder_client()
+            .post(url)
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .build()?;
+
+        let response = client.execute(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let request_id = request_id(&response);
+
+            return Err(Error::from_status(
+                status,
+                &headers,
+                response.json().await.ok(),
+                request_id,
+            ));
+        }
+
+        let responses = match self.format {
+            BatchFormat::Json => parse_json(response).await?,
+            BatchFormat::Multipart => parse_multipart(response).await?,
+        };
+
+        let mut ordered = vec![None; self.operations.len()];
+
+        for (index, response) in responses {
+            if let Some(slot) = ordered.get_mut(index) {
+                *slot = Some(response);
+            }
+        }
+
+        Ok(BatchResponse { responses: ordered })
+    }
+
+    fn json_envelope(&self) -> Vec<u8> {
+        let requests: Vec<Value> = self
+            .operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                let full_url = operation.full_url();
+                let base_url = self.base_url.trim_end_matches('/');
+
+                let url = match strip_base_url(full_url.as_str(), base_url) {
+                    Some(rest) => rest.to_string(),
+                    None => full_url.to_string(),
+                };
+
+                let headers: Map<String, Value> = operation
+                    .headers
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), Value::from(value.to_str().ok()?)))
+                    })
+                    .collect();
+
+                let mut entry = json!({
+                    "id": index.to_string(),
+                    "method": operation.method.as_str(),
+                    "url": url,
+                    "headers": headers,
+                });
+
+                if let Some(body) = &operation.body {
+                    entry["body"] = match body.format {
+                        BodyFormat::Json => operation.json().unwrap_or_default(),
+                        _ => Value::from(String::from_utf8_lossy(&body.bytes)),
+                    };
+                }
+
+                entry
+            })
+            .collect();
+
+        serde_json::to_vec(&json!({ "requests": requests })).unwrap_or_default()
+    }
+
+    fn multipart_envelope(&self, boundary: &str) -> Vec<u8> {
+        let mut envelope = Vec::new();
+
+        for (index, operation) in self.operations.iter().enumerate() {
+            let full_url = operation.full_url();
+
+            let target = match full_url.query() {
+                Some(query) => format!("{}?{}", full_url.path(), query),
+                None => full_url.path().to_string(),
+            };
+
+            envelope.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Type: application/http\r\nContent-ID: <{}>\r\n\r\n{} {} HTTP/1.1\r\n",
+                    boundary, index, operation.method, target
+                )
+                .as_bytes(),
+            );
+
+            for (name, value) in &operation.headers {
+                envelope.extend_from_slice(name.as_str().as_bytes());
+                envelope.extend_from_slice(b": ");
+                envelope.extend_from_slice(value.as_bytes());
+                envelope.extend_from_slice(b"\r\n");
+            }
+
+            envelope.extend_from_slice(b"\r\n");
+
+            if let Some(body) = &operation.body {
+                envelope.extend_from_slice(&body.bytes);
+                envelope.extend_from_slice(b"\r\n");
+            }
+        }
+
+        envelope.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        envelope
+    }
+}
+
+impl BatchResponse {
+    /// Typed result of an operation
+    ///
+    /// Fails like `Request::send` would if the
+    /// operation failed, or with `ClientDecodeError`
+    /// if the server didn't answer it
+    pub fn get<T: DeserializeOwned>(&self, item: &BatchItem<T>) -> Result<T, Error> {
+        let response = self
+            .responses
+            .get(item.index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| {
+                Error::ClientDecodeError(format!("missing response to operation {}", item.index))
+            })?;
+
+        if !response.status.is_success() {
+            let body = serde_json::from_slice(&response.body).ok();
+
+            return Err(Error::from_status(
+                response.status,
+                &response.headers,
+                body,
+                None,
+            ));
+        }
+
+        // Empty bodies, e.g. of `204 No Content`, as `null`
+        match response.body.is_empty() {
+            true => decode::json(b"null", item.strict),
+            false => decode::owned(&response.body, item.strict),
+        }
+    }
+}
+
+/// Responses of a JSON batch by operation index
+async fn parse_json(response: Response) -> Result<Vec<(usize, OperationResponse)>, Error> {
+    let envelope: Value = response
+        .json()
+        .await
+        .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+    let responses = envelope
+        .get("responses")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::ClientDecodeError("missing field `responses`".to_string()))?;
+
+    let parsed = responses
+        .iter()
+        .enumerate()
+        .filter_map(|(position, response)| {
+            let index = match response.get("id") {
+                Some(Value::String(id)) => id.parse().ok()?,
+                Some(id) => id.as_u64()? as usize,
+                None => position,
+            };
+
+            let status = response
+                .get("status")
+                .and_then(Value::as_u64)
+                .and_then(|status| StatusCode::from_u16(status as u16).ok())?;
+
+            let headers = response
+                .get("headers")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .filter_map(|(name, value)| {
+                    Some((
+                        HeaderName::try_from(name.as_str()).ok()?,
+                        HeaderValue::try_from(value.as_str()?).ok()?,
+                    ))
+                })
+                .collect();
+
+            let body = match response.get("body") {
+                None | Some(Value::Null) => Bytes::new(),
+                Some(body) => serde_json::to_vec(body).ok()?.into(),
+            };
+
+            Some((
+                index,
+                OperationResponse {
+                    status,
+                    headers,
+                    body,
+                },
+            ))
+        })
+        .collect();
+
+    Ok(parsed)
+}
+
+/// Responses of a multipart batch by operation index
+async fn parse_multipart(response: Response) -> Result<Vec<(usize, OperationResponse)>, Error> {
+    let invalid = |message: &str| Error::ClientDecodeError(message.to_string());
+
+    let boundary = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| {
+            content_type
+                .split(';')
+                .find_map(|parameter| parameter.trim().strip_prefix("boundary="))
+        })
+        .map(|boundary| boundary.trim_matches('"').to_string())
+        .ok_or_else(|| invalid("missing multipart boundary"))?;
+
+    let body = response.bytes().await?;
+    let body = String::from_utf8_lossy(&body);
+
+    let delimiter = format!("--{}", boundary);
+    let mut parsed = Vec::new();
+
+    // Skip the preamble, stop at the closing delimiter
+    for (position, part) in body.split(delimiter.as_str()).skip(1).enumerate() {
+        if part.starts_with("--") {
+            break;
+        }
+
+        let (part_headers, http) = split_head(part.trim_start_matches(['\r', '\n']))
+            .ok_or_else(|| invalid("invalid multipart part"))?;
+
+        let (head, body) = split_head(http).ok_or_else(|| invalid("invalid multipart part"))?;
+        let mut lines = head.lines();
+
+        // `HTTP/1.1 200 OK`
+        let status = lines
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|status| status.parse::<StatusCode>().ok())
+            .ok_or_else(|| invalid("invalid status line in multipart part"))?;
+
+        let index = header_lines(part_headers)
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-id"))
+            .and_then(|(_, id)| {
+                let id = id.trim_start_matches('<').trim_end_matches('>');
+                id.strip_prefix("response-").unwrap_or(id).parse().ok()
+            })
+            .unwrap_or(position);
+
+        let headers = header_lines(head)
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::try_from(name).ok()?,
+                    HeaderValue::try_from(value).ok()?,
+                ))
+            })
+            .collect();
+
+        parsed.push((
+            index,
+            OperationResponse {
+                status,
+                headers,
+                body: Bytes::copy_from_slice(body.trim_end_matches(['\r', '\n']).as_bytes()),
+            },
+        ));
+    }
+
+    Ok(parsed)
+}
+
+/// Split a head of header lines from the body
+fn split_head(text: &str) -> Option<(&str, &str)> {
+    text.split_once("\r\n\r\n")
+        .or_else(|| text.split_once("\n\n"))
+}
+
+/// `name: value` lines of a head,
+/// skipping e.g. a status line
+fn header_lines(head: &str) -> impl Iterator<Item = (&str, &str)> {
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .filter(|(name, _)| !name.contains(' '))
+}
@@ -0,0 +1,106 @@
+//! Request bodies sent from a stream
+//!
+//! `Request::body_stream` sends large files or
+//! generated content without buffering it in memory,
+//! with a `Content-Length` if known and chunked
+//! otherwise. Streams can only be read once, so
+//! middleware retrying or hedging such requests send
+//! them once or fail with `Error::NotReplayable`.
+//!
+//! Not available on `wasm32`, where reqwest
+//! can't send streaming bodies
+
+use std::{error::Error as StdError, fmt};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{io::AsyncReadExt, stream, AsyncRead};
+use http::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderMap, HeaderValue,
+};
+
+/// Size of the chunks read by `BodyStream::from_reader`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Body of a request read from a stream,
+/// see `Request::body_stream`
+pub struct BodyStream {
+    body: reqwest::Body,
+    length: Option<u64>,
+    content_type: Option<HeaderValue>,
+}
+
+impl BodyStream {
+    /// Send the chunks of `stream`
+    pub fn new<S, B, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<B, E>> + Send + 'static,
+        Bytes: From<B>,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        Self {
+            body: reqwest::Body::wrap_stream(stream),
+            length: None,
+            content_type: None,
+        }
+    }
+
+    /// Send what's read from `reader`, e.g. a file
+    pub fn from_reader<R: AsyncRead + Send + 'static>(reader: R) -> Self {
+        let chunks = stream::try_unfold(Box::pin(reader), |mut reader| async move {
+            let mut buffer = vec![0; CHUNK_SIZE];
+            let read = reader.read(&mut buffer).await?;
+
+            if read == 0 {
+                return Ok::<_, std::io::Error>(None);
+            }
+
+            buffer.truncate(read);
+            Ok(Some((Bytes::from(buffer), reader)))
+        });
+
+        Self::new(chunks)
+    }
+
+    /// Length of the body in bytes, sent as
+    /// `Content-Length`. Chunked if unknown
+    pub fn length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// `Content-Type` of the body,
+    /// `application/octet-stream` by default
+    pub fn content_type(mut self, content_type: HeaderValue) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// Set the headers of the body,
+    /// replacing any of a serialized one
+    pub(crate) fn apply(self, headers: &mut HeaderMap) -> reqwest::Body {
+        headers.remove(CONTENT_ENCODING);
+        headers.insert(
+            CONTENT_TYPE,
+            self.content_type
+                .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+        );
+
+        match self.length {
+            Some(length) => headers.insert(CONTENT_LENGTH, HeaderValue::from(length)),
+            None => headers.remove(CONTENT_LENGTH),
+        };
+
+        self.body
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream")
+            .field("length", &self.length)
+            .field("content_type", &self.content_type)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,434 @@
+//! Configuring the HTTP client of a `WrapiClient`
+//!
+//! `WrapiClient::builder` sets up the underlying reqwest
+//! client, so wrappers can document a single code path
+//! for their users, e.g. to authenticate with a client
+//! certificate to APIs mandating mutual TLS, or to go
+//! through a corporate proxy. Use `configure` for
+//! anything else reqwest offers
+
+use std::{fmt, sync::Arc};
+
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+};
+
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+use async_lock::{OnceCell, Semaphore};
+
+use reqwest::{Client, ClientBuilder};
+
+#[cfg(not(target_arch = "wasm32"))]
+use http::Extensions;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    Certificate, Identity, NoProxy, Proxy, Request, Response,
+};
+
+use super::{
+    client::WrapiClient,
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{middleware::BoxFuture, transport::Transport};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Setting = dyn Fn(ClientBuilder) -> ClientBuilder + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Setting = dyn Fn(ClientBuilder) -> ClientBuilder;
+
+/// Builder of a `WrapiClient` and its reqwest client
+///
+/// Invalid certificates, keys or proxies fail `build`
+#[derive(Clone, Default)]
+pub struct WrapiClientBuilder {
+    /// Applied to the reqwest builder in order
+    settings: Vec<Arc<Setting>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy_auth: Option<(String, String)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy_bypass: Option<String>,
+    /// First error configuring the client
+    error: Option<Error>,
+}
+
+impl WrapiClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configure the reqwest client directly,
+    /// e.g. for timeouts or the user agent
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(ClientBuilder) -> ClientBuilder + MaybeSend + MaybeSync + 'static,
+    {
+        self.settings.push(Arc::new(configure));
+        self
+    }
+
+    /// Build the client, sending calls marked
+    /// as `Direct` without the proxy if any
+    pub fn build(self) -> Result<WrapiClient, Error> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(proxy) = self.build_proxy()? {
+            return Ok(WrapiClient::with_transport(Proxied {
+                proxied: self.client().proxy(proxy).build()?,
+                direct: self.client().no_proxy().build()?,
+            }));
+        }
+
+        Ok(WrapiClient::new(self.client().build()?))
+    }
+
+    /// A reqwest builder with the settings applied
+    fn client(&self) -> ClientBuilder {
+        self.settings
+            .iter()
+            .fold(Client::builder(), |builder, setting| setting(builder))
+    }
+
+    /// Keep the first error, reported by `build`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fail(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+}
+
+/// TLS, HTTP versions, DNS and proxies, not available
+/// on `wasm32` where the browser handles them
+#[cfg(not(target_arch = "wasm32"))]
+impl WrapiClientBuilder {
+    /// Authenticate with a client certificate for
+    /// mutual TLS, from PEM encoded certificate
+    /// chain and PKCS#8 private key
+    pub fn identity_pem(mut self, certificate: &[u8], key: &[u8]) -> Self {
+        match Identity::from_pkcs8_pem(certificate, key) {
+            Ok(identity) => self.identity(identity),
+            Err(err) => {
+                self.fail(err.into());
+                self
+            }
+        }
+    }
+
+    /// Authenticate with a client certificate for
+    /// mutual TLS, from a PKCS#12 archive, e.g. a
+    /// `.p12` or `.pfx` file
+    pub fn identity_pkcs12(mut self, archive: &[u8], password: &str) -> Self {
+        match Identity::from_pkcs12_der(archive, password) {
+            Ok(identity) => self.identity(identity),
+            Err(err) => {
+                self.fail(err.into());
+                self
+            }
+        }
+    }
+
+    fn identity(self, identity: Identity) -> Self {
+        self.configure(move |builder| builder.identity(identity.clone()))
+    }
+
+    /// Trust the CA certificates of a PEM bundle,
+    /// e.g. of a private or enterprise CA, in
+    /// addition to the built-in ones
+    pub fn root_certificates_pem(mut self, bundle: &[u8]) -> Self {
+        match Certificate::from_pem_bundle(bundle) {
+            Ok(certificates) => certificates.into_iter().fold(self, Self::root_certificate),
+            Err(err) => {
+                self.fail(err.into());
+                self
+            }
+        }
+    }
+
+    /// Trust a DER encoded CA certificate in
+    /// addition to the built-in ones
+    pub fn root_certificate_der(mut self, certificate: &[u8]) -> Self {
+        match Certificate::from_der(certificate) {
+            Ok(certificate) => self.root_certificate(certificate),
+            Err(err) => {
+                self.fail(err.into());
+                self
+            }
+        }
+    }
+
+    fn root_certificate(self, certificate: Certificate) -> Self {
+        self.configure(move |builder| builder.add_root_certificate(certificate.clone()))
+    }
+
+    /// Whether to trust the built-in CA certificates,
+    /// `true` by default. Disable to trust only the
+    /// ones added, e.g. to pin a private CA
+    pub fn built_in_roots(self, enabled: bool) -> Self {
+        self.configure(move |builder| builder.tls_built_in_root_certs(enabled))
+    }
+
+    /// HTTP versions to speak, negotiating HTTP/2
+    /// over TLS by default. HTTP/3 needs reqwest's
+    /// unstable `http3` support, enabled on reqwest
+    /// directly and set with `configure`
+    pub fn http_version(self, version: HttpVersion) -> Self {
+        match version {
+            HttpVersion::Negotiate => self,
+            HttpVersion::Http1Only => self.configure(ClientBuilder::http1_only),
+            HttpVersion::Http2Only => self.configure(ClientBuilder::http2_prior_knowledge),
+        }
+    }
+
+    /// Which IP family to connect over, e.g. to
+    /// prefer IPv4 where IPv6 routes are broken.
+    /// Both families are tried by default, in the
+    /// order the system resolver returns them
+    pub fn ip_family(self, family: IpFamily) -> Self {
+        match family {
+            IpFamily::Any => self,
+            family => self.configure(move |builder| {
+                builder.dns_resolver(Arc::new(FamilyResolver { family }))
+            }),
+        }
+    }
+
+    /// Resolve `host` to `addrs`, bypassing DNS and
+    /// `ip_family`, e.g. to reach a staging cluster
+    /// under the production hostname. Ports are the
+    /// ones of the URLs. Replaces earlier
+    /// addresses of `host`
+    pub fn resolve<S: Into<String>>(self, host: S, addrs: &[IpAddr]) -> Self {
+        let host = host.into();
+        let addrs: Vec<SocketAddr> = addrs.iter().map(|addr| SocketAddr::new(*addr, 0)).collect();
+
+        self.configure(move |builder| builder.resolve_to_addrs(&host, &addrs))
+    }
+
+    /// Send all requests through the proxy at `url`,
+    /// e.g. `http://proxy.corp:3128`, instead of the
+    /// ones of the environment. `socks5://` proxies
+    /// need the `socks` feature
+    pub fn proxy<S: Into<String>>(mut self, url: S) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Authenticate to the proxy with basic auth
+    pub fn proxy_auth<U, P>(mut self, username: U, password: P) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.proxy_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Hosts reached without the proxy, comma-separated
+    /// as in `NO_PROXY`, e.g. `localhost,.corp,10.0.0.0/8`
+    pub fn proxy_bypass<S: Into<String>>(mut self, hosts: S) -> Self {
+        self.proxy_bypass = Some(hosts.into());
+        self
+    }
+
+    /// Ignore the proxies of the environment,
+    /// e.g. `HTTPS_PROXY`, unless one is set
+    pub fn no_system_proxy(self) -> Self {
+        self.configure(ClientBuilder::no_proxy)
+    }
+
+    /// The proxy, if one is set
+    fn build_proxy(&self) -> Result<Option<Proxy>, Error> {
+        let Some(url) = &self.proxy else {
+            return Ok(None);
+        };
+
+        let mut proxy = Proxy::all(url.as_str())?;
+
+        if let Some((username, password)) = &self.proxy_auth {
+            proxy = proxy.basic_auth(username, password);
+        }
+
+        let bypass = self.proxy_bypass.as_deref().and_then(NoProxy::from_string);
+
+        Ok(Some(proxy.no_proxy(bypass)))
+    }
+}
+
+impl fmt::Debug for WrapiClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("WrapiClientBuilder");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        debug
+            .field("proxy", &self.proxy)
+            .field("proxy_bypass", &self.proxy_bypass);
+
+        debug.field("error", &self.error).finish_non_exhaustive()
+    }
+}
+
+impl WrapiClient {
+    /// Configure the underlying client,
+    /// see `WrapiClientBuilder`
+    pub fn builder() -> WrapiClientBuilder {
+        WrapiClientBuilder::new()
+    }
+}
+
+/// HTTP versions a client speaks, see
+/// `WrapiClientBuilder::http_version`. The one
+/// serving a response is in its `Metadata`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HttpVersion {
+    /// HTTP/2 when the server offers it in the TLS
+    /// handshake, HTTP/1.1 otherwise
+    #[default]
+    Negotiate,
+    /// HTTP/1.1 only, e.g. for servers
+    /// mishandling HTTP/2
+    Http1Only,
+    /// HTTP/2 only, also over plain HTTP, failing
+    /// with servers that don't speak it
+    Http2Only,
+}
+
+/// IP family a client connects over, see
+/// `WrapiClientBuilder::ip_family`
+///
+/// Preferring a family tries its addresses
+/// first, falling back to the other one shortly
+/// after as in "Happy Eyeballs", RFC 8305
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpFamily {
+    /// In the order of the system resolver
+    #[default]
+    Any,
+    PreferIpv4,
+    PreferIpv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl IpFamily {
+    /// `addrs` ordered and filtered by preference
+    fn apply(self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        match self {
+            IpFamily::Any => {}
+            IpFamily::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            IpFamily::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+            IpFamily::Ipv4Only => addrs.retain(SocketAddr::is_ipv4),
+            IpFamily::Ipv6Only => addrs.retain(SocketAddr::is_ipv6),
+        }
+
+        addrs
+    }
+}
+
+/// System resolver ordering addresses by
+/// family, as connections try them in order
+#[cfg(not(target_arch = "wasm32"))]
+struct FamilyResolver {
+    family: IpFamily,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.family;
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let addrs = family.apply(lookup(host.clone()).await?);
+
+            if addrs.is_empty() {
+                return Err(format!("no address of {} matching {:?}", host, family).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Addresses of `host` from the system resolver,
+/// which blocks, on the blocking pool of tokio
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+async fn lookup(host: String) -> io::Result<Vec<SocketAddr>> {
+    tokio::task::spawn_blocking(move || (host.as_str(), 0).to_socket_addrs().map(Iterator::collect))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Addresses of `host` from the system resolver,
+/// which blocks, on a thread of its own. At most
+/// 16 lookups run at once
+#[cfg(all(not(feature = "tokio"), not(target_arch = "wasm32")))]
+async fn lookup(host: String) -> io::Result<Vec<SocketAddr>> {
+    static LOOKUPS: Semaphore = Semaphore::new(16);
+
+    let _permit = LOOKUPS.acquire().await;
+    let resolved = Arc::new(OnceCell::new());
+    let sender = resolved.clone();
+
+    thread::spawn(move || {
+        let addrs = (host.as_str(), 0)
+            .to_socket_addrs()
+            .map(Iterator::collect::<Vec<_>>);
+        let _ = sender.set_blocking(addrs);
+    });
+
+    match resolved.wait().await {
+        Ok(addrs) => Ok(addrs.clone()),
+        Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+    }
+}
+
+/// Sends a call without the proxy of its client
+///
+/// Insert it into the extensions of
+/// `Request::send_with_context`, e.g. for internal
+/// hosts the proxy can't reach. Not available
+/// on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Direct;
+
+/// Transport of a client with a proxy
+#[cfg(not(target_arch = "wasm32"))]
+struct Proxied {
+    proxied: Client,
+    direct: Client,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for Proxied {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Transport::execute(&self.proxied, request)
+    }
+
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        match extensions.get::<Direct>() {
+            Some(_) => Transport::execute(&self.direct, request),
+            None => Transport::execute(&self.proxied, request),
+        }
+    }
+}
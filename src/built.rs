@@ -0,0 +1,328 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, PROXY_AUTHORIZATION},
+    HeaderMap, HeaderName, HeaderValue, Method,
+};
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Serialize;
+use serde_json::{ser::Formatter, Serializer, Value};
+
+use super::{headers::media_type_matches, parameters::QueryEncoding};
+
+/// Format of a request body
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyFormat {
+    Json,
+    /// URL-encoded form parameters
+    Form,
+    /// Anything else
+    Raw,
+}
+
+impl BodyFormat {
+    /// Default `Content-Type` of the format
+    pub fn content_type(&self) -> HeaderValue {
+        match self {
+            BodyFormat::Json => HeaderValue::from_static("application/json"),
+            BodyFormat::Form => HeaderValue::from_static("application/x-www-form-urlencoded"),
+            BodyFormat::Raw => HeaderValue::from_static("application/octet-stream"),
+        }
+    }
+
+    /// Format of a body by its `Content-Type`
+    pub fn from_content_type(content_type: &str) -> Self {
+        if media_type_matches("application/json", content_type) {
+            BodyFormat::Json
+        } else if media_type_matches("application/x-www-form-urlencoded", content_type) {
+            BodyFormat::Form
+        } else {
+            BodyFormat::Raw
+        }
+    }
+}
+
+/// Serialized body of a request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Body {
+    /// Bytes as sent, i.e. after compression
+    pub bytes: Bytes,
+    pub format: BodyFormat,
+}
+
+impl Body {
+    /// Serialize `value` as JSON
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            bytes: serde_json::to_vec(value)?.into(),
+            format: BodyFormat::Json,
+        })
+    }
+
+    /// Serialize `value` as JSON with `formatter`,
+    /// e.g. to control indentation or number output
+    pub fn json_with<T, F>(value: &T, formatter: F) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+        F: Formatter,
+    {
+        let mut bytes = Vec::new();
+        value.serialize(&mut Serializer::with_formatter(&mut bytes, formatter))?;
+
+        Ok(Self {
+            bytes: bytes.into(),
+            format: BodyFormat::Json,
+        })
+    }
+
+    /// URL-encode form parameters
+    pub fn form<K: AsRef<str>, V: AsRef<str>>(pairs: &[(K, V)]) -> Self {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+        for (key, value) in pairs {
+            serializer.append_pair(key.as_ref(), value.as_ref());
+        }
+
+        Self {
+            bytes: serializer.finish().into(),
+            format: BodyFormat::Form,
+        }
+    }
+
+    /// Bytes as is
+    pub fn raw<B: Into<Bytes>>(bytes: B) -> Self {
+        Self {
+            bytes: bytes.into(),
+            format: BodyFormat::Raw,
+        }
+    }
+}
+
+/// A fully built request, as it would be sent
+///
+/// Produced by `Request::prepare` and turned into a
+/// `reqwest::RequestBuilder` by `Request::build`, so
+/// it can be inspected or altered independently of
+/// the client, e.g. to sign it. Also returned by
+/// `Request::dry_run` so tests can assert exactly
+/// what would be sent, or tools can preview mutations.
+///
+/// Its `Debug` output redacts secrets
+#[derive(Clone)]
+pub struct BuiltRequest {
+    pub method: Method,
+    /// URL, without `query`
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// Query parameters, in order
+    pub query: Vec<(String, String)>,
+    pub query_encoding: QueryEncoding,
+    pub body: Option<Body>,
+}
+
+impl BuiltRequest {
+    /// Capture `request`, whose query
+    /// was encoded with `query_encoding`
+    pub(crate) fn from_request(request: &reqwest::Request, query_encoding: QueryEncoding) -> Self {
+        let mut url = request.url().clone();
+
+        // Pre-encoded pairs are kept as is, as they may not decode
+        let query = match query_encoding.is_preencoded() {
+            true => url
+                .query()
+                .into_iter()
+                .flat_map(|query| query.split('&'))
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (pair.to_string(), String::new()),
+                })
+                .collect(),
+            false => url.query_pairs().into_owned().collect(),
+        };
+
+        url.set_query(None);
+
+        let format = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map_or(BodyFormat::Raw, BodyFormat::from_content_type);
+
+        Self {
+            method: request.method().clone(),
+            url,
+            headers: request.headers().clone(),
+            query,
+            query_encoding,
+            body: request
+                .body()
+                .and_then(reqwest::Body::as_bytes)
+                .map(|bytes| Body {
+                    bytes: Bytes::copy_from_slice(bytes),
+                    format,
+                }),
+        }
+    }
+
+    /// Turn into a builder of `client`
+    pub fn into_builder(self, client: &Client) -> RequestBuilder {
+        let url = self.full_url();
+        let mut builder = client.request(self.method, url).headers(self.headers);
+
+        if let Some(body) = self.body {
+            builder = builder.body(body.bytes);
+        }
+
+        builder
+    }
+
+    /// URL including query parameters
+    pub fn full_url(&self) -> Url {
+        let mut url = self.url.clone();
+
+        if !self.query.is_empty() {
+            let encoded = self.query_encoding.encode(&self.query);
+
+            match url.query() {
+                Some(existing) if !existing.is_empty() => {
+                    let query = format!("{}&{}", existing, encoded);
+                    url.set_query(Some(&query));
+                }
+                _ => url.set_query(Some(&encoded)),
+            }
+        }
+
+        url
+    }
+
+    /// `full_url()` without credentials, and with the
+    /// values of secret query parameters, such as
+    /// `api_key` or `token`, replaced by `[redacted]`
+    pub fn redacted_url(&self) -> Url {
+        redact_url(self.full_url())
+    }
+
+    /// Whether header `name` carries a secret
+    ///
+    /// True for values marked as sensitive, such as
+    /// bearer tokens, and for well-known credential
+    /// headers like `Authorization` and `Cookie`
+    pub fn is_secret(&self, name: &HeaderName) -> bool {
+        is_secret(name, self.headers.get_all(name).iter())
+    }
+
+    /// Headers with secret values
+    /// replaced by `[redacted]`
+    pub fn redacted_headers(&self) -> HeaderMap {
+        let mut headers = self.headers.clone();
+
+        for name in self.headers.keys() {
+            if self.is_secret(name) {
+                headers.insert(name.clone(), HeaderValue::from_static("[redacted]"));
+            }
+        }
+
+        headers
+    }
+
+    /// Body deserialized as JSON
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_slice(&self.body.as_ref()?.bytes).ok()
+    }
+}
+
+impl fmt::Debug for BuiltRequest {
+    /// With the URL and query redacted, and only
+    /// the length of the body, e.g. of a form
+    /// carrying a client secret
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query: Vec<(&str, &str)> = self
+            .query
+            .iter()
+            .map(|(key, value)| match is_secret_parameter(key) {
+                true => (key.as_str(), "[redacted]"),
+                false => (key.as_str(), value.as_str()),
+            })
+            .collect();
+
+        f.debug_struct("BuiltRequest")
+            .field("method", &self.method)
+            .field("url", &self.redacted_url().as_str())
+            .field("headers", &self.redacted_headers())
+            .field("query", &query)
+            .field("query_encoding", &self.query_encoding)
+            .field(
+                "body",
+                &self
+                    .body
+                    .as_ref()
+                    .map(|body| format!("{} bytes", body.bytes.len())),
+            )
+            .finish()
+    }
+}
+
+/// `Authorization` header value for basic auth,
+/// marked as sensitive
+pub(crate) fn basic_auth(username: &str, password: Option<&str>) -> HeaderValue {
+    let credentials = STANDARD.encode(format!("{}:{}", username, password.unwrap_or_default()));
+
+    let mut value = HeaderValue::try_from(format!("Basic {}", credentials))
+        .expect("base64 is a valid header value");
+    value.set_sensitive(true);
+    value
+}
+
+fn is_secret<'a, I>(name: &HeaderName, mut values: I) -> bool
+where
+    I: Iterator<Item = &'a HeaderValue>,
+{
+    [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE].contains(name)
+        || name.as_str().contains("api-key")
+        || name.as_str().contains("token")
+        || values.any(HeaderValue::is_sensitive)
+}
+
+/// `url` without credentials, and with
+/// secret query values replaced
+pub(crate) fn redact_url(mut url: Url) -> Url {
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+
+    let Some(query) = url.query() else {
+        return url;
+    };
+
+    let pairs: Vec<String> = query
+        .split('&')
+        .map(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+
+            match is_secret_parameter(key) {
+                true => format!("{}=[redacted]", key),
+                false => pair.to_string(),
+            }
+        })
+        .collect();
+
+    url.set_query(Some(&pairs.join("&")));
+    url
+}
+
+fn is_secret_parameter(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+
+    [
+        "key",
+        "token",
+        "secret",
+        "password",
+        "signature",
+        "credential",
+    ]
+    .iter()
+    .any(|secret| key.contains(secret))
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures_util::future::{select, Either};
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Token to cancel operations from elsewhere,
+/// e.g. when the user aborts
+///
+/// Operations run with `run` stop at their next await
+/// point once cancelled, including retry and backoff
+/// sleeps and body reads, failing with `Error::Cancelled`.
+/// Pass it to `Download::cancellation` or
+/// `Poller::cancellation`, or add it to a `WrapiClient`
+/// as middleware to cancel all of its requests still
+/// awaiting a response.
+///
+/// Dropping a future of wrapi cancels it as well,
+/// so it can also be raced in `tokio::select!`.
+/// Clones share the cancellation
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: bool,
+    next_waiter: u64,
+    waiters: HashMap<u64, Waker>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every operation using the token,
+    /// now and in the future
+    pub fn cancel(&self) {
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            state.cancelled = true;
+
+            std::mem::take(&mut state.waiters)
+        };
+
+        waiters.into_values().for_each(Waker::wake);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    /// Wait until the token is cancelled
+    pub async fn cancelled(&self) {
+        let mut waiter = Waiter {
+            token: self,
+            id: None,
+        };
+
+        poll_fn(|cx| waiter.poll(cx)).await
+    }
+
+    /// Run `future` until it completes or the
+    /// token is cancelled, failing with
+    /// `Error::Cancelled` in that case
+    pub async fn run<F, T>(&self, future: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        match select(pin!(future), pin!(self.cancelled())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Cancelled),
+        }
+    }
+}
+
+/// Registration of a task waiting
+/// for the cancellation
+struct Waiter<'a> {
+    token: &'a CancellationToken,
+    id: Option<u64>,
+}
+
+impl Waiter<'_> {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.token.state.lock().unwrap();
+
+        if state.cancelled {
+            return Poll::Ready(());
+        }
+
+        let id = *self.id.get_or_insert_with(|| {
+            state.next_waiter += 1;
+            state.next_waiter
+        });
+
+        state.waiters.insert(id, cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.token.state.lock().unwrap().waiters.remove(&id);
+        }
+    }
+}
+
+impl Middleware for CancellationToken {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(self.run(next.run(request)))
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use futures_util::future;
+use http::{Extensions, Method};
+use reqwest::{Client, Request, Response, Url};
+
+use super::{
+    error::Error,
+    middleware::{no_extensions, BoxFuture, Middleware, Next},
+    transport::Transport,
+};
+
+/// Wrapper around a `Transport` carrying
+/// a middleware stack
+///
+/// Cloning is cheap, the transport and
+/// middleware are shared between clones
+#[derive(Clone)]
+pub struct WrapiClient {
+    transport: Arc<dyn Transport>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl WrapiClient {
+    pub fn new(inner: Client) -> Self {
+        Self::with_transport(inner)
+    }
+
+    /// Create a client executing requests
+    /// through a custom transport
+    pub fn with_transport<X>(transport: X) -> Self
+    where
+        X: Transport + 'static,
+    {
+        Self {
+            transport: Arc::new(transport),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Add middleware to the end of the stack
+    pub fn with<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Open connections to the origins of `base_urls`
+    /// ahead of the first request
+    ///
+    /// Sends a `HEAD` request to each origin at once,
+    /// resolving DNS and completing the TLS handshake
+    /// so the pooled connections are reused by later
+    /// requests. Any response counts, and the middleware
+    /// stack is skipped. Fails with the first origin
+    /// which can't be reached
+    pub async fn warm_up<I, S>(&self, base_urls: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut origins = Vec::new();
+
+        for base_url in base_urls {
+            let mut url = Url::parse(base_url.as_ref())?;
+            url.set_path("/");
+            url.set_query(None);
+            url.set_fragment(None);
+
+            if !origins.contains(&url) {
+                origins.push(url);
+            }
+        }
+
+        let responses = future::join_all(
+            origins
+                .into_iter()
+                .map(|url| self.transport.execute(Request::new(Method::HEAD, url))),
+        )
+        .await;
+
+        responses
+            .into_iter()
+            .try_for_each(|response| response.map(drop))
+    }
+}
+
+impl Default for WrapiClient {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+impl Transport for WrapiClient {
+    /// Execute the request through the middleware stack
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        self.execute_with(request, no_extensions())
+    }
+
+    /// Execute the request through the middleware
+    /// stack, exposing `extensions` to it
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Next::new(self.transport.as_ref(), &self.middleware, extensions).run(request)
+    }
+}
+
+impl From<Client> for WrapiClient {
+    fn from(inner: Client) -> Self {
+        Self::new(inner)
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{self, Read};
+
+/// Content coding of a request or response body
+///
+/// Variants are available with the `gzip` and
+/// `zstd` features, except on `wasm32`, where
+/// the browser handles response decompression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+    Gzip,
+    #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+    Zstd,
+}
+
+impl Encoding {
+    /// All supported encodings
+    pub const ALL: &'static [Encoding] = &[
+        #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+        Encoding::Gzip,
+        #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+        Encoding::Zstd,
+    ];
+
+    /// Name used in `Content-Encoding`
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+            Encoding::Gzip => "gzip",
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    /// Parse a `Content-Encoding` value
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|encoding| encoding.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Compress `bytes`
+    #[cfg_attr(
+        any(not(any(feature = "gzip", feature = "zstd")), target_arch = "wasm32"),
+        allow(unused_variables)
+    )]
+    pub fn encode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+            Encoding::Gzip => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Encoding::Zstd => zstd::encode_all(bytes, 0),
+        }
+    }
+
+    /// Decompress `bytes`
+    pub fn decode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        self.decoder(bytes)?.read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    /// Decompress at most `limit` bytes plus one of
+    /// `bytes`, enough to tell they exceed `limit`
+    /// without inflating all of them
+    pub(crate) fn decode_limited(&self, bytes: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        self.decoder(bytes)?
+            .take(limit as u64 + 1)
+            .read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[cfg_attr(
+        any(not(any(feature = "gzip", feature = "zstd")), target_arch = "wasm32"),
+        allow(unused_variables)
+    )]
+    fn decoder<'a>(&self, bytes: &'a [u8]) -> io::Result<Box<dyn Read + 'a>> {
+        match *self {
+            #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+            Encoding::Gzip => Ok(Box::new(flate2::read::GzDecoder::new(bytes))),
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            Encoding::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(bytes)?)),
+        }
+    }
+}
+
+/// Encodings a request accepts for its
+/// response, see `Request::accept_encoding`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AcceptEncoding {
+    /// All supported encodings, if decompressing
+    #[default]
+    Supported,
+    /// Only these encodings, by preference,
+    /// e.g. `zstd` from APIs supporting it
+    Only(Vec<Encoding>),
+    /// `identity`, an uncompressed body,
+    /// e.g. for byte-exact downloads
+    Identity,
+}
+
+impl AcceptEncoding {
+    /// `Accept-Encoding` value, if any
+    pub fn value(&self) -> Option<String> {
+        let names: Vec<&str> = match self {
+            AcceptEncoding::Supported => Encoding::ALL.iter().map(Encoding::as_str).collect(),
+            AcceptEncoding::Only(encodings) => encodings.iter().map(Encoding::as_str).collect(),
+            AcceptEncoding::Identity => vec!["identity"],
+        };
+
+        (!names.is_empty()).then(|| names.join(", "))
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderMap, HeaderValue,
+};
+use reqwest::{Request, Response, Url};
+use url::Host;
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+    runtime,
+};
+
+/// Format name/value pairs as a `Cookie` header value
+pub fn cookie_header<I, K, V>(cookies: I) -> String
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    cookies
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name.as_ref(), value.as_ref()))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Append cookies to the `Cookie` header of `headers`,
+/// keeping a single header as required by RFC 6265
+pub(crate) fn append_cookies(headers: &mut HeaderMap, cookies: &str) {
+    if cookies.is_empty() {
+        return;
+    }
+
+    let combined = match headers.get(COOKIE).and_then(|value| value.to_str().ok()) {
+        Some(existing) if !existing.is_empty() => format!("{}; {}", existing, cookies),
+        _ => cookies.to_string(),
+    };
+
+    if let Ok(value) = HeaderValue::try_from(combined) {
+        headers.insert(COOKIE, value);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl StoredCookie {
+    /// Parse a `Set-Cookie` header received from `url`
+    ///
+    /// Rejected if its `Domain` doesn't cover the host
+    /// of `url`, is a public suffix other than the host,
+    /// or is set at all by an IP address host
+    fn parse(header: &str, url: &Url) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: url.host_str()?.to_ascii_lowercase(),
+            host_only: true,
+            path: default_path(url),
+            secure: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        let mut domain = None;
+
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            match key.to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "expires" => cookie.expires = httpdate::parse_http_date(value).ok(),
+                _ => {}
+            }
+        }
+
+        // The last `Domain` applies, RFC 6265 section 5.3
+        if let Some(domain) = domain {
+            if !matches!(url.host()?, Host::Domain(_)) {
+                return None;
+            }
+
+            match is_public_suffix(&domain) {
+                // Left host-only, as hosts may set
+                // cookies for themselves
+                true if domain == cookie.domain => {}
+                false if domain_match(&cookie.domain, &domain) => {
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                _ => return None,
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        if let Some(seconds) = max_age {
+            cookie.expires = match u64::try_from(seconds) {
+                // A session cookie if out of range
+                Ok(seconds) if seconds > 0 => {
+                    runtime::now().checked_add(Duration::from_secs(seconds))
+                }
+                _ => Some(SystemTime::UNIX_EPOCH),
+            };
+        }
+
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        let domain_match = match (self.host_only, url.host()) {
+            (false, Some(Host::Domain(_))) => {
+                domain_match(&host.to_ascii_lowercase(), &self.domain)
+            }
+            _ => host.eq_ignore_ascii_case(&self.domain),
+        };
+
+        let path = url.path();
+        let path_match = path == self.path
+            || (path.starts_with(&self.path)
+                && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+
+        domain_match && path_match && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// Whether the lowercase `host` is `domain` or
+/// a subdomain of it, per RFC 6265
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Whether `domain` is on the public suffix
+/// list, e.g. `com` or `github.io`
+fn is_public_suffix(domain: &str) -> bool {
+    psl::suffix(domain.as_bytes())
+        .is_some_and(|suffix| suffix.is_known() && suffix.as_bytes() == domain.as_bytes())
+}
+
+/// Directory of the request path, per RFC 6265
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => url.path()[..index].to_string(),
+    }
+}
+
+/// Middleware keeping cookies for a logical session
+///
+/// Captures `Set-Cookie` headers from responses and
+/// sends matching cookies on subsequent requests,
+/// independently of reqwest's cookie store.
+/// Clones share the same session, create a new
+/// `CookieSession` to start a separate one
+#[derive(Clone, Debug, Default)]
+pub struct CookieSession {
+    jar: Arc<Mutex<Vec<StoredCookie>>>,
+}
+
+impl CookieSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value of a stored cookie by name
+    pub fn get(&self, name: &str) -> Option<String> {
+        let now = runtime::now();
+
+        self.jar
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|cookie| cookie.name == name && !cookie.is_expired(now))
+            .map(|cookie| cookie.value.clone())
+    }
+
+    /// Store the cookies from the `Set-Cookie`
+    /// headers of a response received from `url`
+    ///
+    /// Cookies whose `Domain` doesn't cover the host,
+    /// names a public suffix or an IP address are dropped
+    ///
+    /// ```
+    /// # use wrapi::{cookie::CookieSession, http::{header::SET_COOKIE, HeaderMap}, reqwest::Url};
+    /// let stored = |url: &str, set_cookie: &str| {
+    ///     let session = CookieSession::new();
+    ///     let mut headers = HeaderMap::new();
+    ///     headers.insert(SET_COOKIE, set_cookie.parse().unwrap());
+    ///     session.store(&Url::parse(url).unwrap(), &headers);
+    ///     session.get("a").is_some()
+    /// };
+    ///
+    /// assert!(stored("https://api.example.com/", "a=1; Domain=example.com"));
+    /// assert!(!stored("https://api.example.com/", "a=1; Domain=evil.com"));
+    /// assert!(!stored("https://example.com/", "a=1; Domain=api.example.com"));
+    /// assert!(!stored("https://xample.com/", "a=1; Domain=ample.com"));
+    ///
+    /// // Public suffixes, unless the host itself
+    /// assert!(!stored("https://example.com/", "a=1; Domain=com"));
+    /// assert!(!stored("https://foo.github.io/", "a=1; Domain=github.io"));
+    /// assert!(stored("https://github.io/", "a=1; Domain=github.io"));
+    ///
+    /// assert!(!stored("https://127.0.0.1/", "a=1; Domain=127.0.0.1"));
+    /// assert!(stored("https://127.0.0.1/", "a=1"));
+    ///
+    /// // A session cookie if Max-Age is out of range
+    /// assert!(stored("https://example.com/", "a=1; Max-Age=9223372036854775807"));
+    /// ```
+    pub fn store(&self, url: &Url, headers: &HeaderMap) {
+        let now = runtime::now();
+        let mut jar = self.jar.lock().unwrap();
+
+        for header in headers.get_all(SET_COOKIE) {
+            let Some(cookie) = header
+                .to_str()
+                .ok()
+                .and_then(|header| StoredCookie::parse(header, url))
+            else {
+                continue;
+            };
+
+            jar.retain(|stored| {
+                !(stored.name == cookie.name
+                    && stored.domain == cookie.domain
+                    && stored.path == cookie.path)
+            });
+
+            if !cookie.is_expired(now) {
+                jar.push(cookie);
+            }
+        }
+    }
+
+    /// `Cookie` header value for a request to `url`
+    pub fn header_for(&self, url: &Url) -> String {
+        let now = runtime::now();
+        let mut jar = self.jar.lock().unwrap();
+
+        jar.retain(|cookie| !cookie.is_expired(now));
+
+        cookie_header(
+            jar.iter()
+                .filter(|cookie| cookie.matches(url))
+                .map(|cookie| (&cookie.name, &cookie.value)),
+        )
+    }
+
+    /// Forget all cookies
+    pub fn clear(&self) {
+        self.jar.lock().unwrap().clear();
+    }
+}
+
+impl Middleware for CookieSession {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let url = request.url().clone();
+
+            append_cookies(request.headers_mut(), &self.header_for(&url));
+
+            let response = next.run(request).await?;
+
+            self.store(response.url(), response.headers());
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,298 @@
+//! Requests for standard CRUD idioms
+//!
+//! `Get`, `Create`, `Update` and `Delete` pre-wire the
+//! method, expected statuses and body handling of typical
+//! REST endpoints, so simple resources need no `Request`
+//! implementation of their own
+
+use std::{collections::HashMap, fmt, marker::PhantomData};
+
+use http::{Method, StatusCode};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Deserializer, Serialize,
+};
+
+use super::{
+    headers::ETag,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+};
+
+/// Response of endpoints returning nothing
+///
+/// Deserializes from an empty body, and
+/// ignores the body if there is one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoContent;
+
+impl<'de> Deserialize<'de> for NoContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(NoContent)
+    }
+}
+
+/// `GET` of a resource or collection,
+/// returning `T`
+#[derive(Serialize)]
+pub struct Get<T> {
+    #[serde(skip)]
+    endpoint: String,
+    #[serde(skip)]
+    query: HashMap<String, String>,
+    #[serde(skip)]
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> Get<T> {
+    pub fn new<S: Into<String>>(endpoint: S) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            query: HashMap::new(),
+            response: PhantomData,
+        }
+    }
+
+    /// Add the query parameter `name`
+    pub fn query<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.query.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl<T> Clone for Get<T> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: self.endpoint.clone(),
+            query: self.query.clone(),
+            response: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Get<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Get")
+            .field("endpoint", &self.endpoint)
+            .field("query", &self.query)
+            .finish()
+    }
+}
+
+impl<T> Request<T> for Get<T>
+where
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn query(&self) -> Option<HashMap<String, String>> {
+        match self.query.is_empty() {
+            true => None,
+            false => Some(self.query.clone()),
+        }
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
+
+/// `POST` of `body` to a collection,
+/// returning the created `T`
+///
+/// Expects `201 Created`, or `200 OK`
+/// from APIs not distinguishing them
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Create<B, T> {
+    #[serde(skip)]
+    endpoint: String,
+    body: B,
+    #[serde(skip)]
+    response: PhantomData<fn() -> T>,
+}
+
+impl<B, T> Create<B, T> {
+    pub fn new<S: Into<String>>(endpoint: S, body: B) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            body,
+            response: PhantomData,
+        }
+    }
+}
+
+impl<B: Clone, T> Clone for Create<B, T> {
+    fn clone(&self) -> Self {
+        Self::new(self.endpoint.clone(), self.body.clone())
+    }
+}
+
+impl<B: fmt::Debug, T> fmt::Debug for Create<B, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Create")
+            .field("endpoint", &self.endpoint)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+impl<B, T> Request<T> for Create<B, T>
+where
+    B: Serialize + MaybeSend + MaybeSync,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn expected_statuses(&self) -> Option<Vec<StatusCode>> {
+        Some(vec![StatusCode::CREATED, StatusCode::OK])
+    }
+}
+
+/// `PUT` of `body` to a resource,
+/// returning its new representation `T`
+///
+/// Expects `200 OK`
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Update<B, T> {
+    #[serde(skip)]
+    endpoint: String,
+    body: B,
+    #[serde(skip)]
+    if_match: Option<ETag>,
+    #[serde(skip)]
+    response: PhantomData<fn() -> T>,
+}
+
+impl<B, T> Update<B, T> {
+    pub fn new<S: Into<String>>(endpoint: S, body: B) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            body,
+            if_match: None,
+            response: PhantomData,
+        }
+    }
+
+    /// Only update the resource if
+    /// it still matches `etag`
+    pub fn if_match(mut self, etag: ETag) -> Self {
+        self.if_match = Some(etag);
+        self
+    }
+}
+
+impl<B: Clone, T> Clone for Update<B, T> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: self.endpoint.clone(),
+            body: self.body.clone(),
+            if_match: self.if_match.clone(),
+            response: PhantomData,
+        }
+    }
+}
+
+impl<B: fmt::Debug, T> fmt::Debug for Update<B, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Update")
+            .field("endpoint", &self.endpoint)
+            .field("body", &self.body)
+            .field("if_match", &self.if_match)
+            .finish()
+    }
+}
+
+impl<B, T> Request<T> for Update<B, T>
+where
+    B: Serialize + MaybeSend + MaybeSync,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+
+    fn if_match(&self) -> Option<ETag> {
+        self.if_match.clone()
+    }
+
+    fn expected_statuses(&self) -> Option<Vec<StatusCode>> {
+        Some(vec![StatusCode::OK])
+    }
+}
+
+/// `DELETE` of a resource, returning `NoContent`
+///
+/// Expects `204 No Content`, `200 OK`
+/// or `202 Accepted`, ignoring any body
+#[derive(Clone, Debug, Serialize)]
+pub struct Delete {
+    #[serde(skip)]
+    endpoint: String,
+    #[serde(skip)]
+    if_match: Option<ETag>,
+}
+
+impl Delete {
+    pub fn new<S: Into<String>>(endpoint: S) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            if_match: None,
+        }
+    }
+
+    /// Only delete the resource if
+    /// it still matches `etag`
+    pub fn if_match(mut self, etag: ETag) -> Self {
+        self.if_match = Some(etag);
+        self
+    }
+}
+
+impl Request<NoContent> for Delete {
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn accept(&self) -> Option<String> {
+        None
+    }
+
+    fn if_match(&self) -> Option<ETag> {
+        self.if_match.clone()
+    }
+
+    fn expected_statuses(&self) -> Option<Vec<StatusCode>> {
+        Some(vec![
+            StatusCode::NO_CONTENT,
+            StatusCode::OK,
+            StatusCode::ACCEPTED,
+        ])
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
@@ -0,0 +1,259 @@
+use ::csv::{ReaderBuilder, StringRecord};
+use serde::{
+    de::{
+        self,
+        value::{Error as ValueError, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+
+use super::{decode, error::Error};
+
+/// Options of CSV responses, see `Format::Csv`
+///
+/// Rows are deserialized by header name into structs
+/// or maps, or by position into tuples and when there
+/// are no headers. Fields are parsed into the types
+/// of `T`, with empty fields as `None`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Csv {
+    delimiter: u8,
+    has_headers: bool,
+}
+
+impl Csv {
+    /// Comma-separated, with a header row
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+        }
+    }
+
+    /// Tab-separated, with a header row
+    pub fn tsv() -> Self {
+        Self::new().delimiter(b'\t')
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first row holds the
+    /// column names, `true` by default
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Media type of the format
+    pub fn media_type(&self) -> &'static str {
+        match self.delimiter {
+            b'\t' => "text/tab-separated-values",
+            _ => "text/csv",
+        }
+    }
+
+    /// Deserialize the rows of `bytes` into `T`,
+    /// e.g. a `Vec` of structs
+    pub(crate) fn decode<T: DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+        strict: bool,
+    ) -> Result<T, Error> {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .from_reader(bytes);
+
+        let headers = match self.has_headers {
+            true => Some(
+                reader
+                    .headers()
+                    .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?
+                    .clone(),
+            ),
+            false => None,
+        };
+
+        let records = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+        let rows = SeqDeserializer::<_, ValueError>::new(records.iter().map(|record| Row {
+            headers: headers.as_ref(),
+            record,
+        }));
+
+        match strict {
+            true => decode::ignoring(rows),
+            false => {
+                T::deserialize(rows).map_err(|inner| Error::ClientDecodeError(inner.to_string()))
+            }
+        }
+    }
+}
+
+impl Default for Csv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A row, as a map by header or
+/// a sequence of fields
+struct Row<'a> {
+    headers: Option<&'a StringRecord>,
+    record: &'a StringRecord,
+}
+
+impl<'a> Row<'a> {
+    fn fields(&self) -> SeqDeserializer<impl Iterator<Item = Field<'a>>, ValueError> {
+        SeqDeserializer::new(self.record.iter().map(Field))
+    }
+}
+
+impl<'de> Deserializer<'de> for Row<'de> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(headers) = self.headers else {
+            return self.fields().deserialize_any(visitor);
+        };
+
+        let fields = headers.iter().zip(self.record.iter().map(Field));
+        let mut map = MapDeserializer::new(fields);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.fields().deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for Row<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// A field, parsed into the type requested
+struct Field<'a>(&'a str);
+
+impl Field<'_> {
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, ValueError> {
+        self.0
+            .trim()
+            .parse()
+            .map_err(|_| de::Error::custom(format!("invalid {} `{}`", expected, self.0)))
+    }
+}
+
+macro_rules! parse_field {
+    ($($method:ident => $visit:ident($expected:literal)),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Field<'de> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_field! {
+        deserialize_bool => visit_bool("boolean"),
+        deserialize_i8 => visit_i8("integer"),
+        deserialize_i16 => visit_i16("integer"),
+        deserialize_i32 => visit_i32("integer"),
+        deserialize_i64 => visit_i64("integer"),
+        deserialize_i128 => visit_i128("integer"),
+        deserialize_u8 => visit_u8("integer"),
+        deserialize_u16 => visit_u16("integer"),
+        deserialize_u32 => visit_u32("integer"),
+        deserialize_u64 => visit_u64("integer"),
+        deserialize_u128 => visit_u128("integer"),
+        deserialize_f32 => visit_f32("number"),
+        deserialize_f64 => visit_f64("number"),
+        deserialize_char => visit_char("character")
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0.is_empty() {
+            true => visitor.visit_none(),
+            false => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for Field<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
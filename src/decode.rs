@@ -0,0 +1,128 @@
+use std::fmt;
+
+use serde::{
+    de::{
+        value::{Error as ValueError, StrDeserializer},
+        DeserializeOwned,
+    },
+    Deserialize, Deserializer,
+};
+
+use super::error::Error;
+
+/// Byte order mark some servers prefix JSON with
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Deserialize JSON into `T`, strictly or not
+///
+/// Skips a leading byte order mark, and explains
+/// failures caused by compressed or HTML bodies
+/// with a targeted error, see `diagnose`
+pub(crate) fn json<'a, T: Deserialize<'a>>(bytes: &'a [u8], strict: bool) -> Result<T, Error> {
+    let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+
+    let result = match strict {
+        true => self::strict(bytes),
+        false => serde_json::from_slice(bytes)
+            .map_err(|inner| Error::ClientDecodeError(inner.to_string())),
+    };
+
+    result.map_err(|err| diagnose(bytes).unwrap_or(err))
+}
+
+/// Deserialize JSON into an owned `T`, like `json`
+///
+/// With the `simd-json` feature, parses with simd-json
+/// instead, which needs a mutable copy of the body but
+/// is much faster on large bodies
+pub(crate) fn owned<T: DeserializeOwned>(bytes: &[u8], strict: bool) -> Result<T, Error> {
+    #[cfg(feature = "simd-json")]
+    {
+        let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+        let mut buffer = bytes.to_vec();
+
+        let result = simd_json::Deserializer::from_slice(&mut buffer)
+            .map_err(|inner| Error::ClientDecodeError(inner.to_string()))
+            .and_then(|mut deserializer| match strict {
+                true => ignoring(&mut deserializer),
+                false => T::deserialize(&mut deserializer)
+                    .map_err(|inner| Error::ClientDecodeError(inner.to_string())),
+            });
+
+        result.map_err(|err| diagnose(bytes).unwrap_or(err))
+    }
+
+    #[cfg(not(feature = "simd-json"))]
+    json(bytes, strict)
+}
+
+/// Deserialize `T` from a text body, as a string
+pub(crate) fn text<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+
+    let text =
+        std::str::from_utf8(bytes).map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+    T::deserialize(StrDeserializer::<ValueError>::new(text))
+        .map_err(|inner| Error::ClientDecodeError(inner.to_string()))
+}
+
+/// Deserialize JSON into `T`, failing with
+/// `Error::ClientDecodeError` on fields
+/// unknown to `T` instead of ignoring them
+pub(crate) fn strict<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, Error> {
+    ignoring(&mut serde_json::Deserializer::from_slice(bytes))
+}
+
+/// Deserialize `T` from `deserializer`,
+/// rejecting fields unknown to `T`
+pub(crate) fn ignoring<'de, D, T>(deserializer: D) -> Result<T, Error>
+where
+    D: Deserializer<'de>,
+    D::Error: fmt::Display,
+    T: Deserialize<'de>,
+{
+    let mut unknown = Vec::new();
+
+    let value = serde_ignored::deserialize(deserializer, |path| unknown.push(path.to_string()))
+        .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+    if !unknown.is_empty() {
+        return Err(Error::ClientDecodeError(format!(
+            "unknown fields: {}",
+            unknown.join(", ")
+        )));
+    }
+
+    Ok(value)
+}
+
+/// Common causes of bodies failing to decode
+///
+/// `Error::CompressedBody` for gzip or zstd magic
+/// bytes, `Error::HtmlBody` for HTML pages
+fn diagnose(bytes: &[u8]) -> Option<Error> {
+    if bytes.starts_with(b"\x1F\x8B") {
+        return Some(Error::CompressedBody("gzip".to_string()));
+    }
+
+    if bytes.starts_with(b"\x28\xB5\x2F\xFD") {
+        return Some(Error::CompressedBody("zstd".to_string()));
+    }
+
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    let lower = text.trim_start().to_ascii_lowercase();
+
+    if !(lower.starts_with("<!doctype html") || lower.starts_with("<html")) {
+        return None;
+    }
+
+    let title = lower
+        .find("<title")
+        .and_then(|start| Some(start + lower[start..].find('>')? + 1))
+        .and_then(|start| Some((start, start + lower[start..].find("</title")?)))
+        .map(|(start, end)| text.trim_start()[start..end].trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    Some(Error::HtmlBody(title))
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use http::Method;
+use reqwest::{Request, Response};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Middleware rejecting accidental double submits
+///
+/// Mutating requests identical to one still in
+/// flight or successfully sent within the window,
+/// by method, URL and body, fail with
+/// `Error::Duplicate` instead of being sent again,
+/// e.g. when a button is clicked twice. Failed
+/// requests can be sent again right away.
+///
+/// `GET`, `HEAD`, `OPTIONS` and `TRACE` requests and
+/// streaming bodies pass through. Clones share the
+/// requests seen
+#[derive(Clone, Debug)]
+pub struct Dedup {
+    window: Duration,
+    seen: Arc<Mutex<HashMap<Key, Entry>>>,
+}
+
+/// Method, URL and hash of the body
+type Key = (Method, String, u64);
+
+#[derive(Debug)]
+struct Entry {
+    sent: Instant,
+    in_flight: bool,
+}
+
+impl Dedup {
+    /// Reject duplicates sent within `window`
+    /// of the first one
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Record `key` as sent, unless it's a duplicate
+    fn start(&self, key: &Key) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        let now = Instant::now();
+
+        seen.retain(|_, entry| entry.in_flight || now.duration_since(entry.sent) < self.window);
+
+        if seen.contains_key(key) {
+            return false;
+        }
+
+        seen.insert(
+            key.clone(),
+            Entry {
+                sent: now,
+                in_flight: true,
+            },
+        );
+
+        true
+    }
+}
+
+impl Middleware for Dedup {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let Some(key) = key(&request) else {
+                return next.run(request).await;
+            };
+
+            if !self.start(&key) {
+                return Err(Error::Duplicate);
+            }
+
+            let mut pending = Pending {
+                dedup: self,
+                key: Some(key),
+            };
+
+            let result = next.run(request).await;
+
+            if matches!(&result, Ok(response) if response.status().is_success()) {
+                pending.complete();
+            }
+
+            result
+        })
+    }
+}
+
+/// Forgets its request when dropped before completing,
+/// i.e. on failure or cancellation
+struct Pending<'a> {
+    dedup: &'a Dedup,
+    key: Option<Key>,
+}
+
+impl Pending<'_> {
+    fn complete(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+
+        if let Some(entry) = self.dedup.seen.lock().unwrap().get_mut(&key) {
+            entry.in_flight = false;
+        }
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.dedup.seen.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// Key of a mutating request,
+/// None for ones passed through
+fn key(request: &Request) -> Option<Key> {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    ) {
+        return None;
+    }
+
+    let body = match request.body() {
+        Some(body) => body.as_bytes()?,
+        None => &[],
+    };
+
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+
+    Some((
+        request.method().clone(),
+        request.url().to_string(),
+        hasher.finish(),
+    ))
+}
@@ -0,0 +1,246 @@
+//! Describing the endpoints of a wrapper
+//!
+//! Requests implementing `Describe`, by hand or with
+//! `#[derive(Describe)]` and the `derive` feature, can
+//! be collected into a `Description` and emitted as an
+//! OpenAPI document, to document which endpoints of the
+//! upstream API a wrapper covers and to diff them
+//! against the vendor's spec
+
+use http::Method;
+use serde_json::{json, Map, Value};
+
+#[cfg(feature = "derive")]
+pub use wrapi_derive::Describe;
+
+/// Where a parameter is sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Location {
+    Path,
+    Query,
+    Header,
+}
+
+impl Location {
+    /// The OpenAPI `in` of the location
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Location::Path => "path",
+            Location::Query => "query",
+            Location::Header => "header",
+        }
+    }
+}
+
+/// A parameter of an endpoint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    pub location: Location,
+    pub required: bool,
+}
+
+/// An endpoint implemented by a request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    /// Name of the operation, e.g. the request type
+    pub operation: String,
+    pub method: Method,
+    /// Path template, e.g. `/users/{id}`
+    pub path: String,
+    pub summary: Option<String>,
+    pub parameters: Vec<Parameter>,
+    /// Whether the request sends a body
+    pub body: bool,
+}
+
+/// A request which can describe its endpoint
+///
+/// Derive it with the `derive` feature:
+/// `#[describe(method = "GET", path = "/users/{id}")]`
+/// on the request and `#[describe(path)]`, `#[describe(query)]`,
+/// `#[describe(header = "X-Name")]` or `#[describe(body)]`
+/// on its fields. Parameters are named after their field
+/// unless named explicitly, required unless an `Option`,
+/// and the summary is taken from the doc comment
+pub trait Describe {
+    fn describe() -> Endpoint;
+}
+
+/// Differences between a `Description`
+/// and an OpenAPI spec
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Operations of the spec not implemented
+    pub missing: Vec<(Method, String)>,
+    /// Endpoints implemented but not in the spec,
+    /// e.g. removed or renamed upstream
+    pub unknown: Vec<(Method, String)>,
+}
+
+/// Description of the endpoints of a wrapper
+#[derive(Clone, Debug)]
+pub struct Description {
+    title: String,
+    version: String,
+    endpoints: Vec<Endpoint>,
+}
+
+impl Description {
+    /// Describe the wrapper `title` at `version`,
+    /// e.g. `env!("CARGO_PKG_NAME")` and
+    /// `env!("CARGO_PKG_VERSION")`
+    pub fn new<T: Into<String>, V: Into<String>>(title: T, version: V) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            endpoints: Vec::new(),
+        }
+    }
+
+    /// Add the endpoint of request `D`
+    pub fn add<D: Describe>(mut self) -> Self {
+        self.endpoints.push(D::describe());
+        self
+    }
+
+    /// Add an endpoint described by hand
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// An OpenAPI 3.1 document of the endpoints
+    ///
+    /// Bodies and responses are listed without
+    /// schemas, as their types aren't described
+    pub fn to_openapi(&self) -> Value {
+        let mut paths = Map::new();
+
+        for endpoint in &self.endpoints {
+            let parameters: Vec<Value> = endpoint
+                .parameters
+                .iter()
+                .map(|parameter| {
+                    json!({
+                        "name": parameter.name,
+                        "in": parameter.location.as_str(),
+                        "required": parameter.required,
+                    })
+                })
+                .collect();
+
+            let mut operation = json!({
+                "operationId": endpoint.operation,
+                "responses": { "default": { "description": "Response" } },
+            });
+
+            if let Some(summary) = &endpoint.summary {
+                operation["summary"] = json!(summary);
+            }
+
+            if !parameters.is_empty() {
+                operation["parameters"] = Value::Array(parameters);
+            }
+
+            if endpoint.body {
+                operation["requestBody"] = json!({
+                    "content": { "application/json": {} },
+                });
+            }
+
+            let item = paths
+                .entry(endpoint.path.clone())
+                .or_insert_with(|| json!({}));
+
+            item[endpoint.method.as_str().to_lowercase()] = operation;
+        }
+
+        json!({
+            "openapi": "3.1.0",
+            "info": { "title": self.title, "version": self.version },
+            "paths": paths,
+        })
+    }
+
+    /// Compare with the operations of an OpenAPI `spec`
+    ///
+    /// Paths match regardless of the names
+    /// of their parameters
+    pub fn diff(&self, spec: &Value) -> Diff {
+        let mut operations = Vec::new();
+
+        for (path, item) in spec["paths"].as_object().into_iter().flatten() {
+            for method in item.as_object().into_iter().flat_map(|item| item.keys()) {
+                if let Ok(method) = Method::from_bytes(method.to_uppercase().as_bytes()) {
+                    if is_operation(&method) {
+                        operations.push((method, path.clone()));
+                    }
+                }
+            }
+        }
+
+        let implemented = |method: &Method, path: &str| {
+            self.endpoints
+                .iter()
+                .any(|endpoint| endpoint.method == method && same_path(&endpoint.path, path))
+        };
+
+        let missing = operations
+            .iter()
+            .filter(|(method, path)| !implemented(method, path))
+            .cloned()
+            .collect();
+
+        let unknown = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| {
+                !operations.iter().any(|(method, path)| {
+                    *method == endpoint.method && same_path(&endpoint.path, path)
+                })
+            })
+            .map(|endpoint| (endpoint.method.clone(), endpoint.path.clone()))
+            .collect();
+
+        Diff { missing, unknown }
+    }
+}
+
+/// Whether `method` is an operation
+/// of an OpenAPI path item
+fn is_operation(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::PUT,
+        Method::POST,
+        Method::DELETE,
+        Method::OPTIONS,
+        Method::HEAD,
+        Method::PATCH,
+        Method::TRACE,
+    ]
+    .contains(method)
+}
+
+/// Compare path templates, ignoring
+/// parameter names and trailing slashes
+fn same_path(a: &str, b: &str) -> bool {
+    let segments = |path: &str| -> Vec<String> {
+        path.trim_matches('/')
+            .split('/')
+            .map(
+                |segment| match segment.starts_with('{') && segment.ends_with('}') {
+                    true => "{}".to_string(),
+                    false => segment.to_string(),
+                },
+            )
+            .collect()
+    };
+
+    segments(a) == segments(b)
+}
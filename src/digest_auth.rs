@@ -0,0 +1,461 @@
+//! HTTP Digest authentication, RFC 7616
+//!
+//! Some appliance and IoT APIs only offer Digest
+//! instead of basic auth. `DigestAuth` answers their
+//! `401` challenges and reuses the nonce for later
+//! requests, counting its uses. With the
+//! `digest-auth` feature
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use http::{
+    header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use md5::Md5;
+use reqwest::{Request, Response};
+use sha2::{Digest, Sha256};
+use url::Origin;
+use uuid::Uuid;
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Middleware authenticating with HTTP Digest
+///
+/// A request rejected with a Digest challenge is
+/// answered and retried once. Later requests are
+/// authenticated right away with the same nonce until
+/// the server marks it as stale. `SHA-256` is preferred
+/// over `MD5` when offered, and `auth` over `auth-int`
+/// protection.
+///
+/// Requests to other origins than the one of the
+/// challenge are sent without credentials.
+///
+/// Requests whose body cannot be cloned are sent once,
+/// passing the challenge through. Clones share the nonce
+#[derive(Clone)]
+pub struct DigestAuth {
+    username: String,
+    password: String,
+    cnonce: Option<String>,
+    session: Arc<Mutex<Option<Session>>>,
+}
+
+/// Challenge being answered
+struct Session {
+    challenge: Challenge,
+    /// Where the challenge came from
+    origin: Origin,
+    /// Requests sent with its nonce
+    count: u32,
+}
+
+impl DigestAuth {
+    pub fn new<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            cnonce: None,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Send `cnonce` as client nonce instead
+    /// of fresh random ones
+    ///
+    /// Repeating it weakens Digest, so this is meant
+    /// for known-answer tests, such as the example of
+    /// RFC 7616, section 3.9.1:
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use wrapi::{
+    /// #     client::WrapiClient, digest_auth::DigestAuth, error::Error, http,
+    /// #     middleware::BoxFuture, reqwest, transport::Transport,
+    /// # };
+    /// #
+    /// /// Challenges requests without `Authorization`,
+    /// /// keeping the last one received
+    /// struct Server(Arc<Mutex<Option<String>>>);
+    ///
+    /// impl Transport for Server {
+    ///     fn execute(
+    ///         &self,
+    ///         request: reqwest::Request,
+    ///     ) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+    ///         let authorization = request.headers().get("authorization");
+    ///         *self.0.lock().unwrap() = authorization.map(|value| value.to_str().unwrap().into());
+    ///
+    ///         let response = match authorization {
+    ///             Some(_) => http::Response::builder().status(200),
+    ///             None => http::Response::builder().status(401).header(
+    ///                 "www-authenticate",
+    ///                 "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+    ///                  algorithm=SHA-256, \
+    ///                  nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+    ///                  opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+    ///             ),
+    ///         };
+    ///
+    ///         Box::pin(async move { Ok(response.body("").unwrap().into()) })
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Error> {
+    /// let authorization = Arc::new(Mutex::new(None));
+    /// let client = WrapiClient::with_transport(Server(authorization.clone())).with(
+    ///     DigestAuth::new("Mufasa", "Circle of Life")
+    ///         .cnonce("f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ"),
+    /// );
+    ///
+    /// let request = reqwest::Request::new(
+    ///     http::Method::GET,
+    ///     "http://www.example.org/dir/index.html".parse().unwrap(),
+    /// );
+    /// client.execute(request).await?;
+    ///
+    /// let authorization = authorization.lock().unwrap().clone().unwrap();
+    /// assert!(authorization.contains(
+    ///     r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cnonce<S: Into<String>>(mut self, cnonce: S) -> Self {
+        self.cnonce = Some(cnonce.into());
+        self
+    }
+
+    /// `Authorization` for `request` with the current
+    /// challenge, if of its origin, and the nonce used
+    fn authorize(&self, request: &mut Request) -> Option<String> {
+        let mut session = self.session.lock().unwrap();
+        let session = session
+            .as_mut()
+            .filter(|session| session.origin == request.url().origin())?;
+        session.count += 1;
+
+        let cnonce = match &self.cnonce {
+            Some(cnonce) => cnonce.clone(),
+            None => Uuid::new_v4().simple().to_string(),
+        };
+
+        let value = session.challenge.answer(
+            &self.username,
+            &self.password,
+            request,
+            session.count,
+            &cnonce,
+        );
+
+        let mut value = HeaderValue::try_from(value).ok()?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(AUTHORIZATION, value);
+
+        Some(session.challenge.nonce.clone())
+    }
+}
+
+impl fmt::Debug for DigestAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DigestAuth")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Middleware for DigestAuth {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            // Keep a pristine copy to answer a challenge with
+            let retry = request.try_clone();
+            let origin = request.url().origin();
+            let nonce = self.authorize(&mut request);
+
+            let response = next.clone().run(request).await?;
+
+            if response.status() != StatusCode::UNAUTHORIZED {
+                return Ok(response);
+            }
+
+            let Some(challenge) = Challenge::from_headers(response.headers()) else {
+                return Ok(response);
+            };
+
+            // A fresh nonce was rejected, so the credentials were
+            if nonce.as_ref() == Some(&challenge.nonce) && !challenge.stale {
+                return Ok(response);
+            }
+
+            *self.session.lock().unwrap() = Some(Session {
+                challenge,
+                origin,
+                count: 0,
+            });
+
+            let Some(mut retry) = retry else {
+                return Ok(response);
+            };
+
+            self.authorize(&mut retry);
+
+            next.run(retry).await
+        })
+    }
+}
+
+/// Hash function of a challenge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+impl Algorithm {
+    fn hash(&self, data: &[u8]) -> String {
+        match self {
+            Algorithm::Md5 => format!("{:x}", Md5::digest(data)),
+            Algorithm::Sha256 => format!("{:x}", Sha256::digest(data)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha256 => "SHA-256",
+        }
+    }
+}
+
+/// Quality of protection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Qop {
+    Auth,
+    /// Also covering the body
+    AuthInt,
+}
+
+/// A Digest challenge of `WWW-Authenticate`
+#[derive(Clone, Debug)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    /// Hash the credentials with the nonces,
+    /// i.e. `-sess` algorithms
+    session: bool,
+    /// None for RFC 2069 servers
+    qop: Option<Qop>,
+    stale: bool,
+    userhash: bool,
+}
+
+impl Challenge {
+    /// The strongest supported Digest
+    /// challenge of `headers`
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(Self::parse)
+            .max_by_key(|challenge| challenge.algorithm == Algorithm::Sha256)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let (scheme, params) = value.trim().split_once(' ')?;
+
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let params = parse_params(params);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let algorithm = param("algorithm").unwrap_or("MD5").to_ascii_uppercase();
+        let (algorithm, session) = match algorithm.as_str() {
+            "MD5" => (Algorithm::Md5, false),
+            "MD5-SESS" => (Algorithm::Md5, true),
+            "SHA-256" => (Algorithm::Sha256, false),
+            "SHA-256-SESS" => (Algorithm::Sha256, true),
+            _ => return None,
+        };
+
+        let qop = param("qop").map(|qop| {
+            let offered: Vec<&str> = qop.split(',').map(str::trim).collect();
+
+            match offered.contains(&"auth") || !offered.contains(&"auth-int") {
+                true => Qop::Auth,
+                false => Qop::AuthInt,
+            }
+        });
+
+        let flag = |name| param(name).is_some_and(|value| value.eq_ignore_ascii_case("true"));
+
+        Some(Self {
+            realm: param("realm").unwrap_or_default().to_string(),
+            nonce: param("nonce")?.to_string(),
+            opaque: param("opaque").map(str::to_string),
+            algorithm,
+            session,
+            qop,
+            stale: flag("stale"),
+            userhash: flag("userhash"),
+        })
+    }
+
+    /// `Authorization` value answering the challenge
+    /// for `request`, the `count`th with the nonce
+    fn answer(
+        &self,
+        username: &str,
+        password: &str,
+        request: &Request,
+        count: u32,
+        cnonce: &str,
+    ) -> String {
+        let hash = |data: String| self.algorithm.hash(data.as_bytes());
+
+        let uri = match request.url().query() {
+            Some(query) => format!("{}?{}", request.url().path(), query),
+            None => request.url().path().to_string(),
+        };
+
+        let nc = format!("{:08x}", count);
+
+        let mut ha1 = hash(format!("{}:{}:{}", username, self.realm, password));
+
+        if self.session {
+            ha1 = hash(format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+
+        let ha2 = match self.qop {
+            Some(Qop::AuthInt) => {
+                let body = request
+                    .body()
+                    .and_then(reqwest::Body::as_bytes)
+                    .unwrap_or_default();
+
+                hash(format!(
+                    "{}:{}:{}",
+                    request.method(),
+                    uri,
+                    self.algorithm.hash(body)
+                ))
+            }
+            _ => hash(format!("{}:{}", request.method(), uri)),
+        };
+
+        let qop = self.qop.map(|qop| match qop {
+            Qop::Auth => "auth",
+            Qop::AuthInt => "auth-int",
+        });
+
+        let response = match qop {
+            Some(qop) => hash(format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1, self.nonce, nc, cnonce, qop, ha2
+            )),
+            None => hash(format!("{}:{}:{}", ha1, self.nonce, ha2)),
+        };
+
+        let username = match self.userhash {
+            true => hash(format!("{}:{}", username, self.realm)),
+            false => username.to_string(),
+        };
+
+        let algorithm = match self.session {
+            true => format!("{}-sess", self.algorithm.name()),
+            false => self.algorithm.name().to_string(),
+        };
+
+        let mut value = format!(
+            "Digest username={}, realm={}, uri={}, algorithm={}, nonce={}, response={}",
+            quote(&username),
+            quote(&self.realm),
+            quote(&uri),
+            algorithm,
+            quote(&self.nonce),
+            quote(&response),
+        );
+
+        if let Some(qop) = qop {
+            value.push_str(&format!(
+                ", qop={}, nc={}, cnonce={}",
+                qop,
+                nc,
+                quote(cnonce)
+            ));
+        }
+
+        if let Some(opaque) = &self.opaque {
+            value.push_str(&format!(", opaque={}", quote(opaque)));
+        }
+
+        if self.userhash {
+            value.push_str(", userhash=true");
+        }
+
+        value
+    }
+}
+
+/// Parameters of a challenge, `key=value` or
+/// `key="quoted value"`, up to the next scheme
+fn parse_params(params: &str) -> Vec<(String, String)> {
+    let mut parsed = Vec::new();
+    let mut chars = params.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+
+        let key: String =
+            std::iter::from_fn(|| chars.next_if(|c| *c != '=' && *c != ',')).collect();
+        let key = key.trim();
+
+        // A key without a value starts the next challenge
+        if key.is_empty() || key.contains(' ') || chars.next_if_eq(&'=').is_none() {
+            break;
+        }
+
+        let mut value = String::new();
+
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        } else {
+            value.extend(std::iter::from_fn(|| chars.next_if(|c| *c != ',')));
+        }
+
+        parsed.push((key.to_string(), value.trim().to_string()));
+    }
+
+    parsed
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
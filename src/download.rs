@@ -0,0 +1,356 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use digest::Digest;
+use futures_util::{
+    io::{AsyncWrite, AsyncWriteExt},
+    StreamExt,
+};
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    HeaderValue, StatusCode,
+};
+use reqwest::Response;
+
+use super::{
+    backoff::{Attempts, Backoff},
+    cancel::CancellationToken,
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    request_id::request_id,
+    runtime::sleep,
+    throttle::Throttle,
+    transport::{builder_client, Transport},
+    url::join,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Progress = dyn Fn(u64, Option<u64>) + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Progress = dyn Fn(u64, Option<u64>);
+
+/// Downloads a resource, resuming
+/// interrupted transfers with `Range`
+///
+/// After a transient error (see `Error::is_transient`),
+/// including a connection dropped mid-body, the rest
+/// is requested from the last byte received, waiting
+/// as long as rate limits ask to, up to the longest
+/// delay of the backoff. `If-Range`
+/// with the `ETag` or `Last-Modified` of the first
+/// response makes sure the parts belong together, and
+/// every `Content-Range` is checked against the length
+/// received so far and the total length
+#[derive(Clone)]
+pub struct Download {
+    url: String,
+    backoff: Backoff,
+    max_retries: u32,
+    checksum: Option<(Arc<dyn Hasher>, Vec<u8>)>,
+    progress: Option<Arc<Progress>>,
+    throttle: Option<Throttle>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Download {
+    /// Download the resource at `url`
+    ///
+    /// Retries up to 5 times in a row by default
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            url: url.into(),
+            backoff: Backoff::default(),
+            max_retries: 5,
+            checksum: None,
+            progress: None,
+            throttle: None,
+            cancellation: None,
+        }
+    }
+
+    /// Delay between retries
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up after `max_retries`
+    /// consecutive failed attempts
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Verify the downloaded content against the
+    /// `expected` digest, e.g. `Sha256` from `sha2`
+    ///
+    /// Fails with `Error::ClientDecodeError` on a mismatch
+    pub fn checksum<D, E>(mut self, expected: E) -> Self
+    where
+        D: Digest + MaybeSend + MaybeSync + 'static,
+        E: AsRef<[u8]>,
+    {
+        self.checksum = Some((Arc::new(D::new()), expected.as_ref().to_vec()));
+        self
+    }
+
+    /// Cap the bandwidth of the transfer
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Stop the download, including retries,
+    /// once `token` is cancelled
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Report progress as the number of bytes
+    /// received and the total length, if known
+    pub fn on_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(u64, Option<u64>) + MaybeSend + MaybeSync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Download into memory
+    pub async fn bytes<C>(&self, client: &C) -> Result<Bytes, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let mut buffer = Vec::new();
+        self.write_to(client, &mut buffer).await?;
+
+        Ok(Bytes::from(buffer))
+    }
+
+    /// Download into `writer`, returning
+    /// the number of bytes written
+    ///
+    /// Fails if the resource changed while resuming,
+    /// as the writer cannot be rewound
+    pub async fn write_to<C, W>(&self, client: &C, writer: W) -> Result<u64, Error>
+    where
+        C: Transport + ?Sized,
+        W: AsyncWrite + Unpin,
+    {
+        match &self.cancellation {
+            Some(token) => token.run(self.transfer(client, writer)).await,
+            None => self.transfer(client, writer).await,
+        }
+    }
+
+    async fn transfer<C, W>(&self, client: &C, mut writer: W) -> Result<u64, Error>
+    where
+        C: Transport + ?Sized,
+        W: AsyncWrite + Unpin,
+    {
+        let mut hasher = self.checksum.as_ref().map(|(hasher, _)| hasher.fresh());
+        let mut written = 0;
+        let mut total = None;
+        let mut validator: Option<HeaderValue> = None;
+        let mut failures = 0;
+        let mut attempts = Attempts::new();
+
+        loop {
+            let error =
+                match self.request(client, written, validator.as_ref()).await {
+                    Ok(response) => {
+                        let part = check_part(&response, written, total)?;
+                        total = total.or(part);
+
+                        if written == 0 {
+                            validator = response
+                                .headers()
+                                .get(ETAG)
+                                .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+                                .or_else(|| response.headers().get(LAST_MODIFIED))
+                                .cloned();
+                        }
+
+                        let mut body = response.bytes_stream();
+
+                        loop {
+                            match body.next().await {
+                                Some(Ok(chunk)) => {
+                                    if let Some(throttle) = &self.throttle {
+                                        throttle.acquire(chunk.len() as u64).await;
+                                    }
+
+                                    writer.write_all(&chunk).await.map_err(|inner| {
+                                        Error::ClientDecodeError(inner.to_string())
+                                    })?;
+
+                                    if let Some(hasher) = &mut hasher {
+                                        hasher.update(&chunk);
+                                    }
+
+                                    written += chunk.len() as u64;
+                                    failures = 0;
+                                    attempts = Attempts::new();
+
+                                    if let Some(progress) = &self.progress {
+                                        progress(written, total);
+                                    }
+                                }
+                                None if total.is_none_or(|total| written >= total) => {
+                                    writer.flush().await.map_err(|inner| {
+                                        Error::ClientDecodeError(inner.to_string())
+                                    })?;
+
+                                    self.verify(hasher)?;
+
+                                    return Ok(written);
+                                }
+                                Some(Err(inner)) => break Error::from(inner),
+                                None => break Error::BodyTransfer("body ended early".to_string()),
+                            }
+                        }
+                    }
+                    Err(err) => err,
+                };
+
+            if !error.is_transient() || failures >= self.max_retries {
+                return Err(attempts.give_up(error));
+            }
+
+            let delay = self.backoff.retry_delay(failures, error.retry_after());
+            attempts.retry(error, delay);
+
+            sleep(delay).await;
+            failures += 1;
+        }
+    }
+
+    /// Request the resource from `offset` onwards
+    async fn request<C>(
+        &self,
+        client: &C,
+        offset: u64,
+        validator: Option<&HeaderValue>,
+    ) -> Result<Response, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let mut builder = builder_client().get(&self.url);
+
+        if offset > 0 {
+            builder = builder.header(RANGE, format!("bytes={}-", offset));
+
+            if let Some(validator) = validator {
+                builder = builder.header(IF_RANGE, validator);
+            }
+        }
+
+        let request = builder.build()?;
+        let response = client.execute(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let request_id = request_id(&response);
+
+            return Err(Error::from_status(
+                status,
+                &headers,
+                response.json().await.ok(),
+                request_id,
+            ));
+        }
+
+        Ok(response)
+    }
+
+    fn verify(&self, hasher: Option<Box<dyn Hasher>>) -> Result<(), Error> {
+        let (Some(hasher), Some((_, expected))) = (hasher, &self.checksum) else {
+            return Ok(());
+        };
+
+        match hasher.finish() == *expected {
+            true => Ok(()),
+            false => Err(Error::ClientDecodeError("checksum mismatch".to_string())),
+        }
+    }
+}
+
+/// Download the resource at `path` below `base_url`
+/// into memory, resuming interrupted transfers
+///
+/// See `Download` for more options
+pub async fn download_resumable<C>(client: &C, base_url: &str, path: &str) -> Result<Bytes, Error>
+where
+    C: Transport + ?Sized,
+{
+    Download::new(join(base_url, path)?).bytes(client).await
+}
+
+/// Check a response continues the download at
+/// `offset`, returning the total length if known
+fn check_part(response: &Response, offset: u64, total: Option<u64>) -> Result<Option<u64>, Error> {
+    let mismatch = |message: &str| Err(Error::ClientDecodeError(message.to_string()));
+
+    let part_total = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            // `Content-Range: bytes start-end/total`
+            let range = response
+                .headers()
+                .get(CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(|range| range.strip_prefix("bytes "))
+                .and_then(|range| range.split_once('/'))
+                .and_then(|(span, total)| Some((span.split_once('-')?.0, total)));
+
+            let Some((start, part_total)) = range else {
+                return mismatch("invalid header `content-range`");
+            };
+
+            if start.parse::<u64>().ok() != Some(offset) {
+                return mismatch("`content-range` does not continue the download");
+            }
+
+            part_total.parse().ok()
+        }
+        // The whole resource, e.g. because it changed
+        _ if offset > 0 => return mismatch("resource changed while resuming the download"),
+        _ => response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok()),
+    };
+
+    match (total, part_total) {
+        (Some(total), Some(part_total)) if total != part_total => {
+            mismatch("total length changed while resuming the download")
+        }
+        _ => Ok(part_total),
+    }
+}
+
+/// Incremental hash of the downloaded content
+trait Hasher: MaybeSend + MaybeSync {
+    fn fresh(&self) -> Box<dyn Hasher>;
+
+    fn update(&mut self, bytes: &[u8]);
+
+    fn finish(self: Box<Self>) -> Vec<u8>;
+}
+
+impl<D: Digest + MaybeSend + MaybeSync + 'static> Hasher for D {
+    fn fresh(&self) -> Box<dyn Hasher> {
+        Box::new(D::new())
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        self.finalize().to_vec()
+    }
+}
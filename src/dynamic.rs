@@ -0,0 +1,259 @@
+//! Requests built at the call site
+//!
+//! `DynamicRequest` sends one-off calls, e.g. to
+//! exploratory or admin endpoints, through the same
+//! pipeline as typed requests without defining a
+//! struct for them, and `Json` sends any `Serialize`
+//! payload as is. The response type is chosen where
+//! they're sent, `Value` for untyped JSON
+
+use std::{collections::HashMap, fmt};
+
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+use serde_json::Value;
+
+use super::{
+    error::ValidationError,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+};
+
+/// Request assembled with a builder
+///
+/// E.g. `DynamicRequest::get("/health").query("verbose", "1")`,
+/// sent with `send::<Value>` or into any other response
+/// type. Invalid headers or bodies fail the request
+/// with `Error::Validation` when it's sent
+#[derive(Clone)]
+pub struct DynamicRequest {
+    method: Method,
+    endpoint: String,
+    query: HashMap<String, String>,
+    headers: HeaderMap,
+    bearer: Option<String>,
+    basic_auth: Option<(String, Option<String>)>,
+    body: Option<Value>,
+    expected_statuses: Option<Vec<StatusCode>>,
+    invalid: Option<ValidationError>,
+}
+
+impl DynamicRequest {
+    /// Request `endpoint` with `method`, joined onto the
+    /// base URL as described in `url::join`
+    pub fn new<S: Into<String>>(method: Method, endpoint: S) -> Self {
+        Self {
+            method,
+            endpoint: endpoint.into(),
+            query: HashMap::new(),
+            headers: HeaderMap::new(),
+            bearer: None,
+            basic_auth: None,
+            body: None,
+            expected_statuses: None,
+            invalid: None,
+        }
+    }
+
+    pub fn get<S: Into<String>>(endpoint: S) -> Self {
+        Self::new(Method::GET, endpoint)
+    }
+
+    pub fn post<S: Into<String>>(endpoint: S) -> Self {
+        Self::new(Method::POST, endpoint)
+    }
+
+    pub fn put<S: Into<String>>(endpoint: S) -> Self {
+        Self::new(Method::PUT, endpoint)
+    }
+
+    pub fn patch<S: Into<String>>(endpoint: S) -> Self {
+        Self::new(Method::PATCH, endpoint)
+    }
+
+    pub fn delete<S: Into<String>>(endpoint: S) -> Self {
+        Self::new(Method::DELETE, endpoint)
+    }
+
+    /// Add the query parameter `name`
+    pub fn query<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.query.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add the header `name`, replacing
+    /// earlier values of it
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            (Ok(name), Ok(value)) => {
+                self.headers.insert(name, value);
+            }
+            _ => self.invalidate(ValidationError::new("invalid header").field(name)),
+        }
+
+        self
+    }
+
+    pub fn bearer<S: Into<String>>(mut self, token: S) -> Self {
+        self.bearer = Some(token.into());
+        self
+    }
+
+    pub fn basic_auth<U, P>(mut self, username: U, password: Option<P>) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.basic_auth = Some((username.into(), password.map(Into::into)));
+        self
+    }
+
+    /// Send `body` as JSON
+    pub fn json<B: Serialize + ?Sized>(mut self, body: &B) -> Self {
+        match serde_json::to_value(body) {
+            Ok(body) => self.body = Some(body),
+            Err(err) => self.invalidate(ValidationError::new(err.to_string()).field("body")),
+        }
+
+        self
+    }
+
+    /// Fail other successful statuses,
+    /// see `Request::expected_statuses`
+    pub fn expect(mut self, statuses: &[StatusCode]) -> Self {
+        self.expected_statuses = Some(statuses.to_vec());
+        self
+    }
+
+    /// Keep the first error, reported by `validate`
+    fn invalidate(&mut self, error: ValidationError) {
+        self.invalid.get_or_insert(error);
+    }
+}
+
+impl fmt::Debug for DynamicRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicRequest")
+            .field("method", &self.method)
+            .field("endpoint", &self.endpoint)
+            .field("query", &self.query)
+            .field("headers", &self.headers)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Serializes as the JSON body
+impl Serialize for DynamicRequest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.body.serialize(serializer)
+    }
+}
+
+impl<T> Request<T> for DynamicRequest
+where
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        self.method.clone()
+    }
+
+    fn headers(&self) -> Option<HeaderMap> {
+        (!self.headers.is_empty()).then(|| self.headers.clone())
+    }
+
+    fn query(&self) -> Option<HashMap<String, String>> {
+        (!self.query.is_empty()).then(|| self.query.clone())
+    }
+
+    fn bearer(&self) -> Option<String> {
+        self.bearer.clone()
+    }
+
+    fn basic_auth(&self) -> Option<(String, Option<String>)> {
+        self.basic_auth.clone()
+    }
+
+    fn expected_statuses(&self) -> Option<Vec<StatusCode>> {
+        self.expected_statuses.clone()
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        match &self.invalid {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn body(&self) -> Option<&Self> {
+        self.body.as_ref().map(|_| self)
+    }
+}
+
+/// Any `Serialize` payload sent
+/// as the JSON body of a request
+///
+/// E.g. `Json::post("/events", &event)`, or
+/// `event.into_request(Method::POST, "/events")`
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct Json<P> {
+    #[serde(skip)]
+    method: Method,
+    #[serde(skip)]
+    endpoint: String,
+    payload: P,
+}
+
+impl<P> Json<P> {
+    pub fn new<S: Into<String>>(method: Method, endpoint: S, payload: P) -> Self {
+        Self {
+            method,
+            endpoint: endpoint.into(),
+            payload,
+        }
+    }
+
+    pub fn post<S: Into<String>>(endpoint: S, payload: P) -> Self {
+        Self::new(Method::POST, endpoint, payload)
+    }
+
+    pub fn put<S: Into<String>>(endpoint: S, payload: P) -> Self {
+        Self::new(Method::PUT, endpoint, payload)
+    }
+
+    pub fn patch<S: Into<String>>(endpoint: S, payload: P) -> Self {
+        Self::new(Method::PATCH, endpoint, payload)
+    }
+
+    pub fn into_inner(self) -> P {
+        self.payload
+    }
+}
+
+impl<P, T> Request<T> for Json<P>
+where
+    P: Serialize + MaybeSend + MaybeSync,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    fn method(&self) -> Method {
+        self.method.clone()
+    }
+}
+
+/// Turn a `Serialize` payload into a `Json` request
+pub trait IntoRequest: Serialize + Sized {
+    fn into_request<S: Into<String>>(self, method: Method, endpoint: S) -> Json<Self> {
+        Json::new(method, endpoint, self)
+    }
+}
+
+impl<P: Serialize> IntoRequest for P {}
@@ -0,0 +1,38 @@
+/// Implement `Request` on an enum of endpoints
+///
+/// Maps each variant pattern to a method and an
+/// endpoint template, whose `{field}` placeholders
+/// are formatted from the fields bound by the pattern:
+///
+/// `endpoints! { impl Request<Value> for Api { Api::List => GET "/users", Api::Get { id } => GET "/users/{id}" } }`
+///
+/// Further trait methods, such as `body` or `query`,
+/// follow the arms after a `;`. Variants are
+/// serialized as the body, unless `body` skips it
+#[macro_export]
+macro_rules! endpoints {
+    (
+        impl Request<$response:ty> for $request:ty {
+            $($pattern:pat => $method:ident $endpoint:literal),+ $(,)?
+            $(; $($item:item)*)?
+        }
+    ) => {
+        impl $crate::request::Request<$response> for $request {
+            fn endpoint(&self) -> ::std::string::String {
+                #[allow(unused_variables)]
+                match self {
+                    $($pattern => ::std::format!($endpoint),)+
+                }
+            }
+
+            fn method(&self) -> $crate::http::Method {
+                #[allow(unused_variables)]
+                match self {
+                    $($pattern => $crate::http::Method::$method,)+
+                }
+            }
+
+            $($($item)*)?
+        }
+    };
+}
@@ -1,30 +1,553 @@
-use std::fmt;
+use std::{error::Error as StdError, fmt, sync::Arc, time::Duration};
 
-use http::StatusCode;
+use http::{HeaderMap, Method, StatusCode};
+use reqwest::Url;
 use serde_json::Value;
 
+use super::{
+    headers::{FromHeaders, RateLimitInfo, RetryAfter},
+    schema::Violation,
+};
+
+/// Errors of wrapi
+///
+/// New variants may be added, so matches need a
+/// wildcard arm. Match on `Error::inner` to see
+/// past the context and retries of an error
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// API response with possible body
-    ResponseError((StatusCode, Option<Value>)),
+    ResponseError {
+        status: StatusCode,
+        body: Option<Value>,
+        /// Request ID of the exchange, see `RequestId`
+        request_id: Option<String>,
+    },
+    /// API response `412 Precondition Failed`,
+    /// e.g. an `If-Match` ETag was outdated
+    PreconditionFailed {
+        body: Option<Value>,
+        /// Request ID of the exchange, see `RequestId`
+        request_id: Option<String>,
+    },
+    /// API response `429 Too Many Requests`,
+    /// with when and how much to retry
+    RateLimited {
+        /// From `Retry-After`, see `RetryAfter`
+        retry_after: Option<Duration>,
+        /// From the `RateLimit-*` headers
+        limit_info: Option<RateLimitInfo>,
+        body: Option<Value>,
+        /// Request ID of the exchange, see `RequestId`
+        request_id: Option<String>,
+    },
+    /// Response `Content-Type` didn't match
+    /// the accepted media type
+    UnexpectedContentType { expected: String, actual: String },
     /// Generic HTTP client error
     ClientError,
+    /// Request timed out in the HTTP client
+    Timeout,
+    /// Connecting to the server failed,
+    /// e.g. DNS resolution or a refused connection
+    Connect(String),
+    /// TLS handshake failed, e.g. an
+    /// invalid or untrusted certificate
+    Tls(String),
+    /// Sending the request or receiving the
+    /// response body failed midway
+    BodyTransfer(String),
+    /// Request couldn't be built,
+    /// e.g. from an invalid URL
+    Builder(String),
     /// HTTP client failed to decode/deserialize response
     ClientDecodeError(String),
+    /// Deadline passed before the operation completed
+    DeadlineExceeded,
+    /// Collecting stopped as there were
+    /// more items than the limit
+    TooManyItems { limit: usize },
+    /// Webhook signature missing, outdated
+    /// or not matching the payload
+    InvalidSignature(String),
+    /// Response violated the JSON Schema
+    /// of the request
+    SchemaViolation(Vec<Violation>),
+    /// Response body was still compressed with
+    /// the encoding, e.g. `gzip`, when decoding it
+    CompressedBody(String),
+    /// Request rejected by `Request::validate`
+    /// before it was sent
+    Validation(ValidationError),
+    /// Response body exceeded the size limit
+    /// of the request or client in bytes
+    ResponseTooLarge { limit: usize },
+    /// Response body was an HTML page instead of JSON,
+    /// e.g. the error page of a proxy, with its title
+    HtmlBody(Option<String>),
+    /// Request couldn't be sent again as its
+    /// body is a stream, see `middleware::replay`
+    NotReplayable,
+    /// Request identical to one sent shortly
+    /// before, see `Dedup`
+    Duplicate,
+    /// Request refused as the rest of the budget
+    /// is reserved for more urgent ones, see `Quota`
+    QuotaExhausted { remaining: u64 },
+    /// Request dropped as the queue of its
+    /// client was full, see `PriorityQueue`
+    QueueFull,
+    /// Operation stopped by its `CancellationToken`
+    Cancelled,
+    /// Error of a request with the request it
+    /// occurred for, see `Error::context`
+    WithContext {
+        context: Box<RequestContext>,
+        source: Box<Error>,
+    },
+    /// Error of the last attempt of an operation
+    /// given up on after retries, see `Error::retries`
+    Retried {
+        retries: Box<Retries>,
+        source: Box<Error>,
+    },
+    /// Failure of middleware, signers or user
+    /// hooks, see `Error::custom`
+    Custom(Arc<dyn StdError + Send + Sync>),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::ResponseError((status, body)) => {
+            Error::ResponseError {
+                status,
+                body,
+                request_id,
+            } => {
                 write!(
                     f,
                     "API response error with status {} and body {:?}",
                     status, body
-                )
+                )?;
+
+                if let Some(request_id) = request_id {
+                    write!(f, " (request ID {})", request_id)?;
+                }
+
+                Ok(())
+            }
+            Error::PreconditionFailed { body, request_id } => {
+                write!(f, "Precondition failed with body {:?}", body)?;
+
+                if let Some(request_id) = request_id {
+                    write!(f, " (request ID {})", request_id)?;
+                }
+
+                Ok(())
+            }
+            Error::RateLimited {
+                retry_after,
+                request_id,
+                ..
+            } => {
+                write!(f, "Rate limited")?;
+
+                if let Some(retry_after) = retry_after {
+                    write!(f, ", retry after {:?}", retry_after)?;
+                }
+
+                if let Some(request_id) = request_id {
+                    write!(f, " (request ID {})", request_id)?;
+                }
+
+                Ok(())
             }
+            Error::UnexpectedContentType { expected, actual } => write!(
+                f,
+                "Unexpected content type {}, expected {}",
+                actual, expected
+            ),
             Error::ClientError => write!(f, "HTTP client error"),
+            Error::Timeout => write!(f, "Request timed out"),
+            Error::Connect(inner) => write!(f, "Connection error: {}", inner),
+            Error::Tls(inner) => write!(f, "TLS error: {}", inner),
+            Error::BodyTransfer(inner) => write!(f, "Body transfer error: {}", inner),
+            Error::Builder(inner) => write!(f, "Invalid request: {}", inner),
             Error::ClientDecodeError(inner) => write!(f, "Decode error: {}", inner),
+            Error::DeadlineExceeded => write!(f, "Deadline exceeded"),
+            Error::TooManyItems { limit } => write!(f, "More than {} items", limit),
+            Error::InvalidSignature(inner) => write!(f, "Invalid signature: {}", inner),
+            Error::SchemaViolation(violations) => {
+                let violations: Vec<String> = violations
+                    .iter()
+                    .map(|violation| {
+                        format!("{} at `{}`", violation.message, violation.instance_path)
+                    })
+                    .collect();
+
+                write!(f, "Schema violations: {}", violations.join("; "))
+            }
+            Error::Validation(inner) => write!(f, "Invalid request: {}", inner),
+            Error::ResponseTooLarge { limit } => {
+                write!(f, "Response larger than {} bytes", limit)
+            }
+            Error::CompressedBody(encoding) => write!(
+                f,
+                "Decode error: body is {} compressed, enable the `{}` feature \
+                 or decompression in the HTTP client",
+                encoding, encoding
+            ),
+            Error::QueueFull => write!(f, "Request queue full"),
+            Error::QuotaExhausted { remaining } => write!(
+                f,
+                "Quota nearly exhausted, the {} requests left are reserved",
+                remaining
+            ),
+            Error::Duplicate => write!(f, "Duplicate of a request sent shortly before"),
+            Error::NotReplayable => {
+                write!(f, "Request body is a stream and can't be sent again")
+            }
+            Error::Cancelled => write!(f, "Cancelled"),
+            Error::WithContext { context, source } => write!(
+                f,
+                "{} {} ({}): {}",
+                context.method, context.url, context.endpoint, source
+            ),
+            Error::Retried { retries, source } => write!(
+                f,
+                "{} (after {} attempts over {:?})",
+                source, retries.attempts, retries.elapsed
+            ),
+            Error::Custom(inner) => write!(f, "{}", inner),
+            Error::HtmlBody(title) => {
+                write!(f, "Decode error: received an HTML page")?;
+
+                if let Some(title) = title {
+                    write!(f, " \"{}\"", title)?;
+                }
+
+                write!(
+                    f,
+                    " instead of JSON, check the base URL and any proxies in between"
+                )
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::WithContext { source, .. } | Error::Retried { source, .. } => Some(source),
+            Error::Custom(inner) => Some(inner.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Surface an error of your own, e.g.
+    /// of a middleware or signer
+    pub fn custom<E: Into<Box<dyn StdError + Send + Sync>>>(error: E) -> Self {
+        Error::Custom(Arc::from(error.into()))
+    }
+
+    /// The error passed to `Error::custom`, if of type `E`
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        match self.inner() {
+            Error::Custom(inner) => inner.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Request the error occurred for, if known
+    ///
+    /// Errors of `Request::send` and its variants
+    /// carry the method, URL and request type
+    pub fn context(&self) -> Option<&RequestContext> {
+        match self {
+            Error::WithContext { context, .. } => Some(context),
+            Error::Retried { source, .. } => source.context(),
+            _ => None,
+        }
+    }
+
+    /// Attempts made before giving up,
+    /// if the operation was retried
+    ///
+    /// Set by the retries of `Download`,
+    /// `ResumableUpload` and `LongPoll`
+    pub fn retries(&self) -> Option<&Retries> {
+        match self {
+            Error::Retried { retries, .. } => Some(retries),
+            Error::WithContext { source, .. } => source.retries(),
+            _ => None,
+        }
+    }
+
+    /// The error without its context and
+    /// retries, to match on the actual failure
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::WithContext { source, .. } | Error::Retried { source, .. } => source.inner(),
+            error => error,
+        }
+    }
+
+    pub fn into_inner(self) -> Error {
+        match self {
+            Error::WithContext { source, .. } | Error::Retried { source, .. } => {
+                source.into_inner()
+            }
+            error => error,
+        }
+    }
+
+    /// Error of an unsuccessful response, by its status
+    ///
+    /// `PreconditionFailed` for `412`, `RateLimited`
+    /// for `429` and `ResponseError` otherwise
+    pub(crate) fn from_status(
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Option<Value>,
+        request_id: Option<String>,
+    ) -> Self {
+        match status {
+            StatusCode::PRECONDITION_FAILED => Error::PreconditionFailed { body, request_id },
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimited {
+                retry_after: RetryAfter::from_headers(headers)
+                    .ok()
+                    .map(|retry_after| retry_after.0),
+                limit_info: RateLimitInfo::from_headers(headers).ok(),
+                body,
+                request_id,
+            },
+            _ => Error::ResponseError {
+                status,
+                body,
+                request_id,
+            },
+        }
+    }
+
+    /// Attach `context`, unless the
+    /// error already has a context
+    pub(crate) fn with_context(self, context: RequestContext) -> Self {
+        match self.context() {
+            Some(_) => self,
+            None => Error::WithContext {
+                context: Box::new(context),
+                source: Box::new(self),
+            },
+        }
+    }
+
+    /// How long a rate limit asked to wait
+    /// before retrying, from `Retry-After`
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.inner() {
+            Error::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the error is likely temporary,
+    /// so the request may succeed when retried
+    ///
+    /// True for timeouts, failed connections, interrupted
+    /// bodies and other client errors, for rate limits,
+    /// and for `408`, `500`, `502`, `503` and `504` responses,
+    /// whether or not with context
+    pub fn is_transient(&self) -> bool {
+        match self.inner() {
+            Error::ClientError
+            | Error::Timeout
+            | Error::Connect(_)
+            | Error::BodyTransfer(_)
+            | Error::RateLimited { .. } => true,
+            Error::ResponseError { status, .. } => matches!(
+                *status,
+                StatusCode::REQUEST_TIMEOUT
+                    | StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::INTERNAL_SERVER_ERROR
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Classified by the kind of the error
+///
+/// The message omits the URL, which may
+/// hold credentials in its query
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Error::Timeout;
+        }
+
+        let error = error.without_url();
+        let message = describe(&error);
+
+        if error.is_builder() {
+            return Error::Builder(message);
+        }
+
+        if error.is_body() || error.is_decode() {
+            return Error::BodyTransfer(message);
+        }
+
+        // TLS failures surface as connect errors,
+        // told apart by the messages of their sources
+        #[cfg(not(target_arch = "wasm32"))]
+        if error.is_connect() {
+            let lowercase = message.to_lowercase();
+
+            return match ["tls", "ssl", "certificate", "handshake"]
+                .iter()
+                .any(|term| lowercase.contains(term))
+            {
+                true => Error::Tls(message),
+                false => Error::Connect(message),
+            };
+        }
+
+        Error::ClientError
+    }
+}
+
+/// As `ClientDecodeError`, the errors
+/// mostly stemming from response bodies
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::ClientDecodeError(error.to_string())
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(error: url::ParseError) -> Self {
+        Error::Builder(format!("invalid URL: {}", error))
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(error: http::Error) -> Self {
+        Error::Builder(error.to_string())
+    }
+}
+
+impl From<http::header::InvalidHeaderName> for Error {
+    fn from(error: http::header::InvalidHeaderName) -> Self {
+        Error::Builder(error.to_string())
+    }
+}
+
+impl From<http::header::InvalidHeaderValue> for Error {
+    fn from(error: http::header::InvalidHeaderValue) -> Self {
+        Error::Builder(error.to_string())
+    }
+}
+
+impl From<http::status::InvalidStatusCode> for Error {
+    fn from(error: http::status::InvalidStatusCode) -> Self {
+        Error::Builder(error.to_string())
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        Error::Validation(error)
+    }
+}
+
+/// Request an error occurred for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestContext {
+    pub method: Method,
+    /// URL without credentials or secret
+    /// query values, see `BuiltRequest::redacted_url`
+    pub url: Url,
+    /// Name of the request type, e.g. `GetUser`
+    pub endpoint: &'static str,
+}
+
+/// Attempts of an operation which
+/// failed despite being retried
+#[derive(Clone, Debug)]
+pub struct Retries {
+    /// Attempts made, including the last one
+    pub attempts: u32,
+    /// Errors of the attempts before the last one
+    pub outcomes: Vec<Error>,
+    /// Time waited between attempts
+    pub total_delay: Duration,
+    /// Time from the first attempt until giving up
+    pub elapsed: Duration,
+}
+
+/// Message of `error` followed by its sources
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+
+    while let Some(inner) = source {
+        let inner_message = inner.to_string();
+
+        // Some errors already include their source
+        if !message.ends_with(&inner_message) {
+            message.push_str(": ");
+            message.push_str(&inner_message);
+        }
+
+        source = inner.source();
+    }
+
+    message
+}
+
+/// Client-side violation of an API limit
+/// or constraint, see `Request::validate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Offending field, if any
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Self {
+            field: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attribute the violation to `field`
+    pub fn field<S: Into<String>>(mut self, field: S) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// Fail if `field` has more than `max` items,
+    /// e.g. IDs of a batch call
+    pub fn check_max_items(field: &str, len: usize, max: usize) -> Result<(), Self> {
+        match len > max {
+            true => Err(Self::new(format!("{} items, at most {} allowed", len, max)).field(field)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "`{}`: {}", field, self.message),
+            None => write!(f, "{}", self.message),
         }
     }
 }
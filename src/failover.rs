@@ -0,0 +1,184 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use http::StatusCode;
+use reqwest::{Request, Response, Url};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    headers::{FromHeaders, RetryAfter},
+    hedge::is_idempotent,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Middleware failing over between base URLs
+///
+/// Requests sent to any of the base URLs are tried
+/// against each of them in order, moving on to the
+/// next one on connection errors or `5xx` responses.
+/// Other transient errors, e.g. timeouts, move on only
+/// for idempotent requests, as the host may have
+/// applied them. The last failure is returned if all
+/// of them fail.
+///
+/// With a cooldown, base URLs that failed are tried
+/// last until it has elapsed, so a recovered primary
+/// is used again later. Clones share this state.
+///
+/// `429` and `503` responses with `Retry-After`
+/// fail over as well, trying their base URL last
+/// for as long as asked, cooldown or not.
+///
+/// Requests whose body cannot be cloned are sent once
+#[derive(Clone, Debug)]
+pub struct Failover {
+    base_urls: Vec<String>,
+    cooldown: Option<Duration>,
+    unhealthy: Arc<Mutex<Vec<Option<Instant>>>>,
+}
+
+impl Failover {
+    /// Fail over between `base_urls`,
+    /// in order of preference
+    pub fn new<I, S>(base_urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let base_urls: Vec<String> = base_urls
+            .into_iter()
+            .map(|base_url| base_url.into().trim_end_matches('/').to_string())
+            .collect();
+
+        Self {
+            unhealthy: Arc::new(Mutex::new(vec![None; base_urls.len()])),
+            base_urls,
+            cooldown: None,
+        }
+    }
+
+    /// Try base URLs that failed last
+    /// until `cooldown` has elapsed
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    /// Indices of the base URLs in the order they
+    /// should be tried, healthy ones first
+    fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let unhealthy = self.unhealthy.lock().unwrap();
+
+        let mut order: Vec<usize> = (0..self.base_urls.len()).collect();
+        order.sort_by_key(|&index| unhealthy[index].is_some_and(|until| until > now));
+        order
+    }
+
+    fn record(&self, index: usize, healthy: bool, retry_after: Option<Duration>) {
+        let until = match (healthy, retry_after.or(self.cooldown)) {
+            (true, _) => None,
+            (false, Some(cooldown)) => Instant::now().checked_add(cooldown),
+            (false, None) => return,
+        };
+
+        self.unhealthy.lock().unwrap()[index] = until;
+    }
+}
+
+impl Middleware for Failover {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let url = request.url().to_string();
+
+            let rest = self
+                .base_urls
+                .iter()
+                .find_map(|base_url| strip_base_url(&url, base_url));
+
+            let (Some(rest), Some(_)) = (rest, request.try_clone()) else {
+                return next.run(request).await;
+            };
+
+            let idempotent = is_idempotent(&request);
+            let mut last = None;
+
+            for index in self.order() {
+                let Some(url) = rebase(&self.base_urls[index], rest) else {
+                    continue;
+                };
+
+                let mut attempt = request.try_clone().expect("body was cloned before");
+                *attempt.url_mut() = url;
+
+                let result = next.clone().run(attempt).await;
+                let retry_after = retry_after(&result);
+
+                let healthy = retry_after.is_none()
+                    && match &result {
+                        Ok(response) => !response.status().is_server_error(),
+                        Err(err) => !err.is_transient(),
+                    };
+
+                self.record(index, healthy, retry_after);
+
+                let fail_over = match &result {
+                    Ok(_) => !healthy,
+                    Err(err) => {
+                        !healthy && (idempotent || matches!(err.inner(), Error::Connect(_)))
+                    }
+                };
+
+                if !fail_over {
+                    return result;
+                }
+
+                last = Some(result);
+            }
+
+            match last {
+                Some(result) => result,
+                None => next.run(request).await,
+            }
+        })
+    }
+}
+
+/// How long the server asked to be left alone,
+/// from `Retry-After` of `429` and `503` responses
+pub(crate) fn retry_after(result: &Result<Response, Error>) -> Option<Duration> {
+    match result {
+        Ok(response) => match response.status() {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                RetryAfter::from_headers(response.headers())
+                    .ok()
+                    .map(|RetryAfter(delay)| delay)
+            }
+            _ => None,
+        },
+        Err(err) => err.retry_after(),
+    }
+}
+
+/// Remainder of `url` after `base_url`,
+/// if it is a URL below it
+pub(crate) fn strip_base_url<'u>(url: &'u str, base_url: &str) -> Option<&'u str> {
+    let rest = url.strip_prefix(base_url)?;
+
+    match rest.chars().next() {
+        None | Some('/' | '?' | '#') => Some(rest),
+        _ => None,
+    }
+}
+
+/// `rest` of a URL placed below another base URL
+pub(crate) fn rebase(base_url: &str, rest: &str) -> Option<Url> {
+    Url::parse(&format!("{}{}", base_url, rest)).ok()
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::Hasher,
+    sync::{Arc, Mutex},
+};
+
+use http::Method;
+use reqwest::Url;
+use serde::Serialize;
+
+/// Last fingerprints successfully sent per endpoint
+///
+/// Lets sync-style wrappers, which push the same
+/// configuration repeatedly, skip requests whose
+/// `Request::fingerprint` is unchanged, see
+/// `Request::send_if_changed`. Endpoints are keyed by
+/// method and URL including query. Holds up to 1024
+/// endpoints by default, forgetting the oldest ones.
+///
+/// Kept in memory only. Clones share the store
+#[derive(Clone, Debug)]
+pub struct Fingerprints {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    sent: HashMap<(Method, Url), String>,
+    /// Endpoints in order of recording
+    order: VecDeque<(Method, Url)>,
+    capacity: usize,
+}
+
+impl Fingerprints {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                sent: HashMap::new(),
+                order: VecDeque::new(),
+                capacity: 1024,
+            })),
+        }
+    }
+
+    /// Remember up to `capacity` endpoints
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.state.lock().unwrap().capacity = capacity.max(1);
+        self
+    }
+
+    /// Fingerprint of `value`, a hash of its JSON
+    ///
+    /// Stable within a process, but not across
+    /// Rust versions, so don't persist it
+    pub fn of<S: Serialize + ?Sized>(value: &S) -> Option<String> {
+        let json = serde_json::to_vec(value).ok()?;
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&json);
+
+        Some(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Whether `fingerprint` was the last one
+    /// sent to `method` on `url`
+    pub fn is_unchanged(&self, method: &Method, url: &Url, fingerprint: &str) -> bool {
+        let state = self.state.lock().unwrap();
+
+        state
+            .sent
+            .get(&(method.clone(), url.clone()))
+            .is_some_and(|sent| sent == fingerprint)
+    }
+
+    /// Record `fingerprint` as sent
+    /// to `method` on `url`
+    pub fn record<S: Into<String>>(&self, method: Method, url: Url, fingerprint: S) {
+        let mut state = self.state.lock().unwrap();
+        let key = (method, url);
+
+        if state.sent.insert(key.clone(), fingerprint.into()).is_some() {
+            state.order.retain(|existing| *existing != key);
+        }
+
+        state.order.push_back(key);
+
+        while state.order.len() > state.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.sent.remove(&oldest);
+            }
+        }
+    }
+
+    /// Send the next request to
+    /// `method` on `url` regardless
+    pub fn forget(&self, method: &Method, url: &Url) {
+        let mut state = self.state.lock().unwrap();
+        let key = (method.clone(), url.clone());
+
+        state.sent.remove(&key);
+        state.order.retain(|existing| *existing != key);
+    }
+
+    /// Send all next requests regardless
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.sent.clear();
+        state.order.clear();
+    }
+}
+
+impl Default for Fingerprints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(feature = "csv")]
+use super::csv::Csv;
+
+/// Format of response bodies,
+/// see `Request::format`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    /// CSV or TSV rows, e.g. of reporting
+    /// and export endpoints
+    #[cfg(feature = "csv")]
+    Csv(Csv),
+    /// Any `text/*` body, such as plain text
+    /// or HTML, see `TextResponse`
+    Text,
+}
+
+impl Format {
+    /// Media type of the format,
+    /// accepted by default
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            #[cfg(feature = "csv")]
+            Format::Csv(csv) => csv.media_type(),
+            Format::Text => "text/*",
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
+};
+
+use http::{HeaderMap, Version};
+use reqwest::{Request, Response};
+use serde_json::{json, Value};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::{
+    error::Error,
+    middleware::{buffer, BoxFuture, Middleware, Next},
+};
+
+/// Middleware recording every request and
+/// response into an HTTP Archive (HAR 1.2)
+///
+/// Clones share the same recording, so keep
+/// one around after adding it to a client
+/// to save the archive later on
+#[derive(Clone, Debug, Default)]
+pub struct HarRecorder {
+    entries: Arc<Mutex<Vec<Value>>>,
+}
+
+impl HarRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recorded session as a HAR document
+    pub fn to_har(&self) -> Value {
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": *self.entries.lock().unwrap(),
+            }
+        })
+    }
+
+    /// Write the recorded session to a `.har` file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(&self.to_har())?)
+    }
+
+    /// Discard all recorded entries
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl Middleware for HarRecorder {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let started = OffsetDateTime::from(SystemTime::now())
+                .format(&Rfc3339)
+                .unwrap_or_default();
+            let entry_request = har_request(&request);
+            let start = Instant::now();
+
+            let response = next.run(request).await?;
+            let (response, body) = buffer(response).await?;
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+            let status = response.status();
+            let headers = response.headers();
+
+            self.entries.lock().unwrap().push(json!({
+                "startedDateTime": started,
+                "time": elapsed,
+                "request": entry_request,
+                "response": {
+                    "status": status.as_u16(),
+                    "statusText": status.canonical_reason().unwrap_or_default(),
+                    "httpVersion": http_version(response.version()),
+                    "cookies": [],
+                    "headers": har_headers(headers),
+                    "content": {
+                        "size": body.len(),
+                        "mimeType": content_type(headers),
+                        "text": String::from_utf8_lossy(&body),
+                    },
+                    "redirectURL": headers
+                        .get(http::header::LOCATION)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default(),
+                    "headersSize": -1,
+                    "bodySize": body.len(),
+                },
+                "cache": {},
+                "timings": {
+                    "send": 0,
+                    "wait": elapsed,
+                    "receive": 0,
+                },
+            }));
+
+            Ok(response)
+        })
+    }
+}
+
+fn har_request(request: &Request) -> Value {
+    let body = request.body().and_then(|body| body.as_bytes());
+
+    let mut entry = json!({
+        "method": request.method().as_str(),
+        "url": request.url().as_str(),
+        "httpVersion": http_version(request.version()),
+        "cookies": [],
+        "headers": har_headers(request.headers()),
+        "queryString": request
+            .url()
+            .query_pairs()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+        "headersSize": -1,
+        "bodySize": body.map(|body| body.len() as i64).unwrap_or(-1),
+    });
+
+    if let Some(body) = body {
+        entry["postData"] = json!({
+            "mimeType": content_type(request.headers()),
+            "text": String::from_utf8_lossy(body),
+        });
+    }
+
+    entry
+}
+
+fn har_headers(headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            json!({
+                "name": name.as_str(),
+                "value": String::from_utf8_lossy(value.as_bytes()),
+            })
+        })
+        .collect()
+}
+
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+fn http_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/1.1",
+    }
+}
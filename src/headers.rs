@@ -0,0 +1,352 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::{
+    header::{ACCESS_CONTROL_ALLOW_METHODS, ALLOW, CONTENT_LENGTH, CONTENT_TYPE, ETAG, WARNING},
+    HeaderMap, Method,
+};
+
+use super::{error::Error, runtime};
+
+/// Typed data extracted from response headers
+///
+/// Implement for structs such as rate limit or
+/// pagination info, then receive them alongside
+/// the body with `Request::send_with_headers`
+pub trait FromHeaders: Sized {
+    /// Parse from the headers of a response
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error>;
+}
+
+impl FromHeaders for () {
+    fn from_headers(_headers: &HeaderMap) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+impl FromHeaders for HeaderMap {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        Ok(headers.clone())
+    }
+}
+
+/// `None` if the headers are missing or invalid
+impl<H: FromHeaders> FromHeaders for Option<H> {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        Ok(H::from_headers(headers).ok())
+    }
+}
+
+/// Parse the value of header `name`
+///
+/// Fails with `Error::ClientDecodeError`
+/// if it is missing or invalid
+pub fn parse_header<V: FromStr>(headers: &HeaderMap, name: &str) -> Result<V, Error> {
+    parse_header_opt(headers, name)?
+        .ok_or_else(|| Error::ClientDecodeError(format!("missing header `{}`", name)))
+}
+
+/// Parse the value of header `name` if present
+///
+/// Fails with `Error::ClientDecodeError`
+/// if it is invalid
+pub fn parse_header_opt<V: FromStr>(headers: &HeaderMap, name: &str) -> Result<Option<V>, Error> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .map(Some)
+        .ok_or_else(|| Error::ClientDecodeError(format!("invalid header `{}`", name)))
+}
+
+/// Rate limit state reported by the server
+///
+/// Read from the `RateLimit-*` headers,
+/// falling back to `X-RateLimit-*`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Requests allowed per window
+    pub limit: Option<u64>,
+    /// Requests left in the current window
+    pub remaining: u64,
+    /// Seconds, or a timestamp depending on
+    /// the API, until the window resets
+    pub reset: Option<u64>,
+}
+
+impl FromHeaders for RateLimitInfo {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let find = |name: &str| -> Result<Option<u64>, Error> {
+            match parse_header_opt(headers, &format!("ratelimit-{}", name))? {
+                Some(value) => Ok(Some(value)),
+                None => parse_header_opt(headers, &format!("x-ratelimit-{}", name)),
+            }
+        };
+
+        Ok(Self {
+            limit: find("limit")?,
+            remaining: find("remaining")?.ok_or_else(|| {
+                Error::ClientDecodeError("missing header `ratelimit-remaining`".to_string())
+            })?,
+            reset: find("reset")?,
+        })
+    }
+}
+
+/// Time to wait before retrying
+///
+/// Read from `Retry-After`, in seconds
+/// or as a date, e.g. of a `429` or `503`.
+/// At most a day
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryAfter(pub Duration);
+
+impl RetryAfter {
+    /// Longest wait accepted from a server
+    pub const MAX: Duration = Duration::from_secs(24 * 60 * 60);
+}
+
+impl FromHeaders for RetryAfter {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let value: String = parse_header(headers, "retry-after")?;
+
+        if let Ok(seconds) = value.parse() {
+            return Ok(Self(Duration::from_secs(seconds).min(Self::MAX)));
+        }
+
+        let date = httpdate::parse_http_date(&value)
+            .map_err(|_| Error::ClientDecodeError("invalid header `retry-after`".to_string()))?;
+
+        // Dates in the past mean retrying right away
+        Ok(Self(
+            date.duration_since(runtime::now())
+                .unwrap_or_default()
+                .min(Self::MAX),
+        ))
+    }
+}
+
+/// Methods allowed on a resource
+///
+/// Read from the `Allow` header of an `OPTIONS`
+/// response, falling back to the CORS
+/// `Access-Control-Allow-Methods`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allow(pub Vec<Method>);
+
+impl Allow {
+    /// Whether `method` is allowed
+    pub fn contains(&self, method: &Method) -> bool {
+        self.0.contains(method)
+    }
+}
+
+impl FromHeaders for Allow {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let value = headers
+            .get(ALLOW)
+            .or_else(|| headers.get(ACCESS_CONTROL_ALLOW_METHODS))
+            .ok_or_else(|| Error::ClientDecodeError("missing header `allow`".to_string()))?
+            .to_str()
+            .map_err(|_| Error::ClientDecodeError("invalid header `allow`".to_string()))?;
+
+        let methods = value
+            .split(',')
+            .map(str::trim)
+            .filter(|method| !method.is_empty())
+            .map(|method| {
+                Method::from_bytes(method.as_bytes())
+                    .map_err(|_| Error::ClientDecodeError("invalid header `allow`".to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self(methods))
+    }
+}
+
+/// Size and type of a resource,
+/// e.g. from a `HEAD` response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentInfo {
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+}
+
+impl FromHeaders for ContentInfo {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        Ok(Self {
+            content_length: parse_header_opt(headers, CONTENT_LENGTH.as_str())?,
+            content_type: parse_header_opt(headers, CONTENT_TYPE.as_str())?,
+        })
+    }
+}
+
+/// Version of a resource from the `ETag` header
+///
+/// Holds the raw value, including quotes and
+/// a `W/` prefix for weak validators, so it can
+/// be sent back as is with `Request::if_match`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ETag(pub String);
+
+impl ETag {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether it is a weak validator
+    pub fn is_weak(&self) -> bool {
+        self.0.starts_with("W/")
+    }
+}
+
+impl FromHeaders for ETag {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        parse_header(headers, ETAG.as_str()).map(Self)
+    }
+}
+
+/// Deprecation of a resource announced by the server
+///
+/// Read from the `Deprecation` header, either a
+/// structured `@<unix seconds>` date or the legacy
+/// `true` or HTTP date, and the `Sunset` header
+/// with the date the resource goes away. Fails if
+/// neither header is present
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Whether the resource is deprecated,
+    /// false if only a sunset is announced
+    pub deprecated: bool,
+    /// When the resource was or will be deprecated
+    pub since: Option<SystemTime>,
+    /// When the resource will stop responding
+    pub sunset: Option<SystemTime>,
+}
+
+impl FromHeaders for Deprecation {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let deprecation: Option<String> = parse_header_opt(headers, "deprecation")?;
+        let sunset: Option<String> = parse_header_opt(headers, "sunset")?;
+
+        if deprecation.is_none() && sunset.is_none() {
+            return Err(Error::ClientDecodeError(
+                "missing header `deprecation`".to_string(),
+            ));
+        }
+
+        let since = deprecation
+            .as_deref()
+            .and_then(|value| match value.strip_prefix('@') {
+                // Unknown if out of range
+                Some(seconds) => UNIX_EPOCH.checked_add(Duration::from_secs(seconds.parse().ok()?)),
+                None => httpdate::parse_http_date(value).ok(),
+            });
+
+        Ok(Self {
+            deprecated: deprecation.is_some_and(|value| value != "false"),
+            since,
+            sunset: sunset.and_then(|value| httpdate::parse_http_date(&value).ok()),
+        })
+    }
+}
+
+/// A `Warning` header entry, e.g.
+/// `299 - "Deprecated API"`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub code: u16,
+    /// Host or pseudonym of the warning's
+    /// sender, `-` if unknown
+    pub agent: String,
+    pub text: String,
+    pub date: Option<SystemTime>,
+}
+
+/// All `Warning` entries, empty if there are none
+///
+/// Fails if an entry is invalid
+impl FromHeaders for Vec<Warning> {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let invalid = || Error::ClientDecodeError("invalid header `warning`".to_string());
+
+        let mut warnings = Vec::new();
+
+        for value in headers.get_all(WARNING) {
+            let mut rest = value.to_str().map_err(|_| invalid())?.trim();
+
+            while !rest.is_empty() {
+                let (code, after) = rest.split_once(' ').ok_or_else(invalid)?;
+                let (agent, after) = after.trim_start().split_once(' ').ok_or_else(invalid)?;
+                let (text, after) = quoted(after.trim_start()).ok_or_else(invalid)?;
+
+                let (date, after) = match quoted(after.trim_start()) {
+                    Some((date, after)) => (httpdate::parse_http_date(&date).ok(), after),
+                    None => (None, after),
+                };
+
+                warnings.push(Warning {
+                    code: code.parse().map_err(|_| invalid())?,
+                    agent: agent.to_string(),
+                    text,
+                    date,
+                });
+
+                rest = after.trim_start().trim_start_matches(',').trim_start();
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+/// Split a leading quoted string off `value`,
+/// unescaping it
+fn quoted(value: &str) -> Option<(String, &str)> {
+    let mut chars = value.strip_prefix('"')?.char_indices();
+    let mut text = String::new();
+
+    while let Some((index, char)) = chars.next() {
+        match char {
+            '"' => return Some((text, &value[index + 2..])),
+            '\\' => text.push(chars.next()?.1),
+            char => text.push(char),
+        }
+    }
+
+    None
+}
+
+/// Whether a `Content-Type` satisfies an `Accept` value
+///
+/// Supports lists of media ranges, `*/*`, `type/*` and
+/// structured syntax suffixes, so `application/json`
+/// accepts `application/problem+json`
+pub fn media_type_matches(accept: &str, content_type: &str) -> bool {
+    let essence = |media_type: &str| {
+        media_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    };
+
+    let content_type = essence(content_type);
+    let (kind, subtype) = content_type.split_once('/').unwrap_or((&content_type, ""));
+
+    accept.split(',').map(essence).any(|range| {
+        let (range_kind, range_subtype) = range.split_once('/').unwrap_or((&range, ""));
+
+        (range_kind == "*" || range_kind == kind)
+            && (range_subtype == "*"
+                || range_subtype == subtype
+                || subtype.ends_with(&format!("+{}", range_subtype)))
+    })
+}
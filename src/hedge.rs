@@ -0,0 +1,79 @@
+use std::{pin::pin, time::Duration};
+
+use futures_util::future::{select, Either};
+use http::Method;
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    idempotency::IDEMPOTENCY_KEY,
+    middleware::{BoxFuture, Middleware, Next},
+    runtime::sleep,
+};
+
+/// Middleware hedging slow requests
+///
+/// If a request hasn't completed within the
+/// threshold, an identical second request is
+/// sent and whichever finishes first wins, the
+/// other one being cancelled. Should the first
+/// to finish fail, the other is awaited instead.
+///
+/// Only idempotent requests are hedged: those
+/// with an idempotent method or carrying an
+/// `Idempotency-Key` header. Requests whose body
+/// cannot be cloned are sent once.
+///
+/// Hedges pass through the middleware added
+/// after this one, so add retries before and
+/// rate limiting after it
+#[derive(Clone, Debug)]
+pub struct Hedge {
+    threshold: Duration,
+}
+
+impl Hedge {
+    /// Hedge requests still running after `threshold`
+    pub fn new(threshold: Duration) -> Self {
+        Self { threshold }
+    }
+}
+
+impl Middleware for Hedge {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let hedge = match is_idempotent(&request) {
+                true => request.try_clone(),
+                false => None,
+            };
+
+            let Some(hedge) = hedge else {
+                return next.run(request).await;
+            };
+
+            let primary = next.clone().run(request);
+
+            let primary = match select(primary, pin!(sleep(self.threshold))).await {
+                Either::Left((result, _)) => return result,
+                Either::Right((_, primary)) => primary,
+            };
+
+            match select(primary, next.run(hedge)).await {
+                Either::Left((Err(_), other)) | Either::Right((Err(_), other)) => other.await,
+                Either::Left((result, _)) | Either::Right((result, _)) => result,
+            }
+        })
+    }
+}
+
+/// Whether `request` may be sent twice
+pub(crate) fn is_idempotent(request: &Request) -> bool {
+    matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    ) || request.headers().contains_key(IDEMPOTENCY_KEY)
+}
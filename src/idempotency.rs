@@ -0,0 +1,58 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+use uuid::Uuid;
+
+/// Header used for idempotency keys by default
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Idempotency key policy of a request
+///
+/// The key is generated once when the policy is
+/// created, so storing the policy on a request
+/// keeps the key stable for the request's lifetime.
+/// `send` additionally reuses a single key across
+/// every attempt of one call
+#[derive(Clone, Debug)]
+pub struct Idempotency {
+    header: HeaderName,
+    key: String,
+}
+
+impl Idempotency {
+    /// Policy with a generated UUID v4 key
+    /// sent as `Idempotency-Key`
+    pub fn new() -> Self {
+        Self::with_key(Uuid::new_v4().to_string())
+    }
+
+    /// Policy with an explicit key
+    pub fn with_key<S: Into<String>>(key: S) -> Self {
+        Self {
+            header: IDEMPOTENCY_KEY,
+            key: key.into(),
+        }
+    }
+
+    /// Send the key under a different header name
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// The idempotency key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Set the key header, replacing existing values
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(value) = HeaderValue::try_from(&self.key) {
+            headers.insert(self.header.clone(), value);
+        }
+    }
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,315 @@
+//! JWT assertions signed with a private key
+//!
+//! Service accounts, e.g. of Google, and OAuth clients
+//! using `private_key_jwt` authenticate with a JWT they
+//! sign themselves. `JwtAssertion` builds and signs it
+//! with RS256 or ES256, and either presents it as a
+//! bearer token or exchanges it for an access token,
+//! e.g. refreshed through a `CachedToken`. With the
+//! `jwt` feature
+
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http::Method;
+use p256::{
+    ecdsa::{Signature as EcdsaSignature, SigningKey as EcdsaKey},
+    SecretKey,
+};
+use reqwest::Url;
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs1v15::SigningKey as RsaKey,
+    pkcs8::DecodePrivateKey,
+    signature::{SignatureEncoding, Signer},
+    RsaPrivateKey,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::{error::Error, request::Request, runtime, token::Token, transport::Transport};
+
+/// Grant exchanging an assertion for an access token, RFC 7523
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// Client authentication with an assertion, RFC 7523
+const JWT_BEARER_CLIENT: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Key signing the assertion
+#[derive(Clone)]
+enum Key {
+    Rs256(Box<RsaKey<Sha256>>),
+    Es256(Box<EcdsaKey>),
+}
+
+impl Key {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            Key::Rs256(_) => "RS256",
+            Key::Es256(_) => "ES256",
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Key::Rs256(key) => key.sign(message).to_vec(),
+            Key::Es256(key) => {
+                let signature: EcdsaSignature = key.sign(message);
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+}
+
+/// A JWT signed with a private key
+///
+/// Carries `iat`, `exp` and a unique `jti`, besides
+/// the issuer, subject, audience and any other claims
+/// set. Signed anew by every `sign`, so each use gets
+/// fresh timestamps.
+///
+/// E.g. for a Google service account,
+/// `JwtAssertion::rs256(key)?.issuer(email).audience(token_url).claim("scope", scopes)`
+/// exchanged with `exchange`
+#[derive(Clone)]
+pub struct JwtAssertion {
+    key: Key,
+    key_id: Option<String>,
+    claims: Map<String, Value>,
+    lifetime: Duration,
+}
+
+impl JwtAssertion {
+    /// Sign with RS256, with a private key
+    /// in PKCS#8 or PKCS#1 PEM
+    pub fn rs256(private_key: &str) -> Result<Self, Error> {
+        let key = RsaPrivateKey::from_pkcs8_pem(private_key)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key))
+            .map_err(|_| invalid_key("RSA"))?;
+
+        Ok(Self::new(Key::Rs256(Box::new(RsaKey::new(key)))))
+    }
+
+    /// Sign with ES256, with a P-256 private
+    /// key in PKCS#8 or SEC1 PEM
+    pub fn es256(private_key: &str) -> Result<Self, Error> {
+        let key = SecretKey::from_pkcs8_pem(private_key)
+            .or_else(|_| SecretKey::from_sec1_pem(private_key))
+            .map_err(|_| invalid_key("P-256"))?;
+
+        Ok(Self::new(Key::Es256(Box::new(key.into()))))
+    }
+
+    fn new(key: Key) -> Self {
+        Self {
+            key,
+            key_id: None,
+            claims: Map::new(),
+            lifetime: Duration::from_secs(3600),
+        }
+    }
+
+    /// ID of the key, sent as `kid` in the
+    /// header, e.g. the `private_key_id` of
+    /// a Google service account
+    pub fn key_id<S: Into<String>>(mut self, key_id: S) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// `iss`, e.g. the client ID or the
+    /// email of a service account
+    pub fn issuer<S: Into<String>>(self, issuer: S) -> Self {
+        self.claim("iss", issuer.into())
+    }
+
+    /// `sub`, e.g. the client ID or the
+    /// user impersonated by a service account
+    pub fn subject<S: Into<String>>(self, subject: S) -> Self {
+        self.claim("sub", subject.into())
+    }
+
+    /// `aud`, usually the token endpoint
+    /// or the API the token is for
+    pub fn audience<S: Into<String>>(self, audience: S) -> Self {
+        self.claim("aud", audience.into())
+    }
+
+    /// Time until the assertion expires,
+    /// one hour by default
+    pub fn lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Set claim `name`, e.g. `scope`. Values
+    /// failing to serialize are left out
+    pub fn claim<S: Into<String>, V: Serialize>(mut self, name: S, value: V) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.claims.insert(name.into(), value);
+        }
+
+        self
+    }
+
+    /// The signed JWT
+    pub fn sign(&self) -> Result<String, Error> {
+        let now = runtime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut header = json!({ "alg": self.key.algorithm(), "typ": "JWT" });
+
+        if let Some(key_id) = &self.key_id {
+            header["kid"] = key_id.clone().into();
+        }
+
+        let mut claims = self.claims.clone();
+        claims.insert("iat".to_string(), now.as_secs().into());
+        claims.insert("exp".to_string(), (now + self.lifetime).as_secs().into());
+        claims
+            .entry("jti")
+            .or_insert_with(|| Uuid::new_v4().to_string().into());
+
+        let claims = Value::Object(claims);
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let signature = URL_SAFE_NO_PAD.encode(self.key.sign(message.as_bytes()));
+
+        Ok(format!("{}.{}", message, signature))
+    }
+
+    /// The signed JWT as a token to present as
+    /// bearer itself, for APIs accepting
+    /// self-signed JWTs
+    pub fn token(&self) -> Result<Token, Error> {
+        Ok(Token::new(self.sign()?, self.lifetime))
+    }
+
+    /// Exchange the assertion for an access
+    /// token with the `jwt-bearer` grant at
+    /// `token_url`, e.g. as a service account
+    pub async fn exchange<C>(&self, client: &C, token_url: &str) -> Result<Token, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let form = HashMap::from([
+            ("grant_type".to_string(), JWT_BEARER_GRANT.to_string()),
+            ("assertion".to_string(), self.sign()?),
+        ]);
+
+        request_token(client, token_url, form).await
+    }
+
+    /// Authenticate as the client with the
+    /// assertion, i.e. `private_key_jwt`, for a
+    /// `client_credentials` grant at `token_url`
+    pub async fn exchange_as_client<C>(
+        &self,
+        client: &C,
+        token_url: &str,
+        scope: Option<&str>,
+    ) -> Result<Token, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let mut form = HashMap::from([
+            ("grant_type".to_string(), "client_credentials".to_string()),
+            (
+                "client_assertion_type".to_string(),
+                JWT_BEARER_CLIENT.to_string(),
+            ),
+            ("client_assertion".to_string(), self.sign()?),
+        ]);
+
+        if let Some(scope) = scope {
+            form.insert("scope".to_string(), scope.to_string());
+        }
+
+        request_token(client, token_url, form).await
+    }
+}
+
+impl fmt::Debug for JwtAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtAssertion")
+            .field("algorithm", &self.key.algorithm())
+            .field("key_id", &self.key_id)
+            .field("claims", &self.claims)
+            .field("lifetime", &self.lifetime)
+            .finish_non_exhaustive()
+    }
+}
+
+fn invalid_key(kind: &str) -> Error {
+    Error::Builder(format!("invalid {} private key", kind))
+}
+
+/// Form request to a token endpoint
+#[derive(Serialize)]
+struct TokenRequest {
+    #[serde(skip)]
+    url: Url,
+    #[serde(skip)]
+    form: HashMap<String, String>,
+}
+
+/// Successful response of a token endpoint
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+impl Request<TokenResponse> for TokenRequest {
+    fn endpoint(&self) -> String {
+        String::new()
+    }
+
+    fn absolute_url(&self) -> Option<Url> {
+        Some(self.url.clone())
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn form(&self) -> Option<HashMap<String, String>> {
+        Some(self.form.clone())
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
+
+async fn request_token<C>(
+    client: &C,
+    token_url: &str,
+    form: HashMap<String, String>,
+) -> Result<Token, Error>
+where
+    C: Transport + ?Sized,
+{
+    let request = TokenRequest {
+        url: Url::parse(token_url)?,
+        form,
+    };
+
+    let response = request.send(client, "").await?;
+
+    Ok(match response.expires_in {
+        Some(expires_in) => Token::new(response.access_token, Duration::from_secs(expires_in)),
+        None => Token::never_expiring(response.access_token),
+    })
+}
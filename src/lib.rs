@@ -11,7 +11,7 @@
 //!
 //! ## Example
 //!
-//! ```rust
+//! ```rust,no_run
 //! use serde::{Deserialize, Serialize};
 //! use wrapi::http::Method;
 //! use wrapi::request::Request;
@@ -29,7 +29,7 @@
 //!
 //! impl Request<Id> for NewUser {
 //!     fn endpoint(&self) -> String {
-//!         "user"
+//!         "user".to_string()
 //!     }
 //!
 //!     fn method(&self) -> Method {
@@ -54,9 +54,84 @@
 //! }
 //! ```
 
+pub mod backoff;
+pub mod balance;
+pub mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod body_stream;
+pub mod builder;
+pub mod built;
+pub mod cancel;
+pub mod client;
+pub mod compression;
+pub mod cookie;
+pub mod crud;
+#[cfg(feature = "csv")]
+pub mod csv;
+mod decode;
+pub mod dedup;
+pub mod describe;
+#[cfg(feature = "digest-auth")]
+pub mod digest_auth;
+pub mod download;
+pub mod dynamic;
+mod endpoints;
 pub mod error;
+pub mod failover;
+pub mod fingerprint;
+pub mod format;
+#[cfg(all(feature = "har", not(target_arch = "wasm32")))]
+pub mod har;
+pub mod headers;
+pub mod hedge;
+pub mod idempotency;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+pub mod links;
+pub mod locale;
+pub mod long_poll;
+pub mod marker;
+pub mod middleware;
+pub mod notice;
+#[cfg(feature = "oauth1")]
+pub mod oauth1;
+pub mod pagination;
 pub mod parameters;
+pub mod patch;
+pub mod pipeline;
+pub mod poll;
+pub mod prefer;
+pub mod priority;
+pub mod quota;
+pub mod redirect;
 pub mod request;
+pub mod request_id;
+pub mod resource;
+pub mod response;
+pub mod runtime;
+pub mod saga;
+pub mod schema;
+pub mod scope;
+pub mod serialization;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod session;
+pub mod size_limit;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+pub mod text;
+pub mod throttle;
+pub mod timing;
+pub mod token;
+pub mod transport;
+#[cfg(all(feature = "unix-socket", unix))]
+pub mod unix_socket;
+pub mod upload;
+pub mod url;
+pub mod user_agent;
+pub mod version;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
 // Re-exports
 pub use http;
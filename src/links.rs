@@ -0,0 +1,291 @@
+use std::marker::PhantomData;
+
+use http::{header::LINK, HeaderMap, Method};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::{
+    error::Error,
+    headers::FromHeaders,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+};
+
+/// A hypermedia link
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    /// Relation type, e.g. `next` or `self`
+    pub rel: String,
+    /// Target, possibly relative
+    pub href: String,
+    pub title: Option<String>,
+    /// Media type of the target
+    pub media_type: Option<String>,
+}
+
+impl Link {
+    fn new(rel: &str, href: &str) -> Self {
+        Self {
+            rel: rel.to_string(),
+            href: href.to_string(),
+            title: None,
+            media_type: None,
+        }
+    }
+
+    /// GET request for the target, deserializing into `T`
+    pub fn follow<T>(&self) -> Follow<T> {
+        Follow::new(&self.href)
+    }
+}
+
+/// Hypermedia links of a response
+///
+/// Read from the `Link` header, or from the
+/// `_links` (HAL) or `links` (JSON:API and
+/// `[{"rel": .., "href": ..}]` lists) fields
+/// of a body with `from_body`.
+///
+/// `FullResponse::links` combines both and
+/// resolves relative links
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Links(pub Vec<Link>);
+
+impl Links {
+    /// Links from the `_links` or
+    /// `links` field of a JSON body
+    pub fn from_body(body: &Value) -> Self {
+        let Some(links) = body.get("_links").or_else(|| body.get("links")) else {
+            return Self::default();
+        };
+
+        let link = |rel: &str, value: &Value| -> Option<Link> {
+            match value {
+                Value::String(href) => Some(Link::new(rel, href)),
+                Value::Object(fields) => {
+                    let text = |name: &str| fields.get(name)?.as_str().map(str::to_string);
+
+                    Some(Link {
+                        rel: text("rel").unwrap_or_else(|| rel.to_string()),
+                        href: text("href")?,
+                        title: text("title"),
+                        media_type: text("type"),
+                    })
+                }
+                _ => None,
+            }
+        };
+
+        let links = match links {
+            Value::Object(rels) => rels
+                .iter()
+                .flat_map(|(rel, value)| match value {
+                    Value::Array(values) => {
+                        values.iter().filter_map(|value| link(rel, value)).collect()
+                    }
+                    value => link(rel, value).into_iter().collect::<Vec<_>>(),
+                })
+                .collect(),
+            Value::Array(values) => values
+                .iter()
+                .filter_map(|value| link("", value))
+                .filter(|link| !link.rel.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self(links)
+    }
+
+    /// First link with relation type `rel`
+    pub fn get(&self, rel: &str) -> Option<&Link> {
+        self.0
+            .iter()
+            .find(|link| link.rel.eq_ignore_ascii_case(rel))
+    }
+
+    /// All links with relation type `rel`
+    pub fn all<'a>(&'a self, rel: &'a str) -> impl Iterator<Item = &'a Link> + 'a {
+        self.0
+            .iter()
+            .filter(move |link| link.rel.eq_ignore_ascii_case(rel))
+    }
+
+    /// GET request for the first link with
+    /// relation type `rel`, if there is one
+    pub fn follow<T>(&self, rel: &str) -> Option<Follow<T>> {
+        self.get(rel).map(Link::follow)
+    }
+
+    /// Resolve relative links against `url`,
+    /// usually the URL of the response
+    pub fn resolve(mut self, url: &Url) -> Self {
+        for link in &mut self.0 {
+            if let Ok(resolved) = url.join(&link.href) {
+                link.href = resolved.into();
+            }
+        }
+
+        self
+    }
+
+    /// Add the links of `other`
+    pub fn extend(&mut self, other: Links) {
+        self.0.extend(other.0);
+    }
+}
+
+/// All links of the `Link` headers,
+/// empty if there are none
+///
+/// Fails if a header is invalid
+impl FromHeaders for Links {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let invalid = || Error::ClientDecodeError("invalid header `link`".to_string());
+
+        let mut links = Vec::new();
+
+        for value in headers.get_all(LINK) {
+            let mut rest = value.to_str().map_err(|_| invalid())?.trim();
+
+            while !rest.is_empty() {
+                let (href, after) = rest
+                    .strip_prefix('<')
+                    .and_then(|rest| rest.split_once('>'))
+                    .ok_or_else(invalid)?;
+
+                let mut rels = None;
+                let mut title = None;
+                let mut media_type = None;
+
+                rest = after.trim_start();
+
+                // `; name=value` or `; name="value"` up to the next link
+                while let Some(after) = rest.strip_prefix(';') {
+                    let (name, after) = after.split_once('=').ok_or_else(invalid)?;
+                    let after = after.trim_start();
+
+                    let (value, after) = match after.strip_prefix('"') {
+                        Some(quoted) => {
+                            let (value, after) = quoted.split_once('"').ok_or_else(invalid)?;
+                            (value, after)
+                        }
+                        None => after.split_at(after.find([';', ',']).unwrap_or(after.len())),
+                    };
+
+                    match name.trim().to_ascii_lowercase().as_str() {
+                        "rel" => rels = Some(value.trim()),
+                        "title" => title = Some(value.trim().to_string()),
+                        "type" => media_type = Some(value.trim().to_string()),
+                        _ => {}
+                    }
+
+                    rest = after.trim_start();
+                }
+
+                // Space-separated relation types share the link
+                for rel in rels.unwrap_or_default().split_whitespace() {
+                    links.push(Link {
+                        rel: rel.to_string(),
+                        href: href.to_string(),
+                        title: title.clone(),
+                        media_type: media_type.clone(),
+                    });
+                }
+
+                rest = rest.trim_start_matches(',').trim_start();
+            }
+        }
+
+        Ok(Self(links))
+    }
+}
+
+/// GET request following a link
+///
+/// Sent like any other request, so auth and
+/// headers added by the client, e.g. by its
+/// middleware or a `Scope`, are reused. Links
+/// should be resolved first, otherwise relative
+/// ones are joined onto the base URL.
+///
+/// Links to another origin than the base URL
+/// fail with `Error::Builder` unless allowed with
+/// `allow_cross_origin`, so credentials aren't
+/// sent to hosts named by a response
+#[derive(Serialize)]
+pub struct Follow<T> {
+    #[serde(skip)]
+    href: String,
+    #[serde(skip)]
+    headers: Option<HeaderMap>,
+    #[serde(skip)]
+    cross_origin: bool,
+    #[serde(skip)]
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> Follow<T> {
+    pub fn new<S: Into<String>>(href: S) -> Self {
+        Self {
+            href: href.into(),
+            headers: None,
+            cross_origin: false,
+            response: PhantomData,
+        }
+    }
+
+    /// Allow links to other origins,
+    /// e.g. a trusted CDN
+    pub fn allow_cross_origin(mut self) -> Self {
+        self.cross_origin = true;
+        self
+    }
+
+    /// Send additional headers
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = Some(headers);
+        self
+    }
+
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+}
+
+impl<T> Clone for Follow<T> {
+    fn clone(&self) -> Self {
+        Self {
+            href: self.href.clone(),
+            headers: self.headers.clone(),
+            cross_origin: self.cross_origin,
+            response: PhantomData,
+        }
+    }
+}
+
+impl<T> Request<T> for Follow<T>
+where
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn endpoint(&self) -> String {
+        self.href.clone()
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn cross_origin(&self) -> bool {
+        self.cross_origin
+    }
+
+    fn headers(&self) -> Option<HeaderMap> {
+        self.headers.clone()
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
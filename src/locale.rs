@@ -0,0 +1,87 @@
+use http::{header::ACCEPT_LANGUAGE, HeaderValue};
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Middleware setting the locale of every request
+///
+/// Sends `Accept-Language` unless the request already
+/// carries one, e.g. from `Request::locale`. For APIs
+/// taking the locale as a query parameter instead, or
+/// as well, `query_param` adds the first language
+/// of the request's locale to the URL
+#[derive(Clone, Debug)]
+pub struct Locale {
+    locale: Option<HeaderValue>,
+    query_param: Option<String>,
+}
+
+impl Locale {
+    /// Default to `locale`, e.g. `de-CH`
+    /// or `fr-CH, fr;q=0.9`
+    pub fn new<S: AsRef<str>>(locale: S) -> Result<Self, http::Error> {
+        Ok(Self {
+            locale: Some(HeaderValue::try_from(locale.as_ref())?),
+            query_param: None,
+        })
+    }
+
+    /// No default, only mirror the locales
+    /// of requests into `query_param`
+    pub fn from_requests() -> Self {
+        Self {
+            locale: None,
+            query_param: None,
+        }
+    }
+
+    /// Also send the locale as query parameter
+    /// `name`, e.g. `lang`, unless present
+    pub fn query_param<S: Into<String>>(mut self, name: S) -> Self {
+        self.query_param = Some(name.into());
+        self
+    }
+}
+
+impl Middleware for Locale {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            if let Some(locale) = &self.locale {
+                if !request.headers().contains_key(ACCEPT_LANGUAGE) {
+                    request
+                        .headers_mut()
+                        .insert(ACCEPT_LANGUAGE, locale.clone());
+                }
+            }
+
+            if let Some(name) = &self.query_param {
+                let present = request.url().query_pairs().any(|(key, _)| key == *name);
+
+                // The first language range, without weight
+                let language = request
+                    .headers()
+                    .get(ACCEPT_LANGUAGE)
+                    .and_then(|locale| locale.to_str().ok())
+                    .and_then(|locale| locale.split([',', ';']).next())
+                    .map(|language| language.trim().to_string())
+                    .filter(|language| !language.is_empty() && language != "*");
+
+                if let (false, Some(language)) = (present, language) {
+                    request
+                        .url_mut()
+                        .query_pairs_mut()
+                        .append_pair(name, &language);
+                }
+            }
+
+            next.run(request).await
+        })
+    }
+}
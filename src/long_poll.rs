@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+use futures_core::Stream;
+use futures_util::stream;
+use serde::de::DeserializeOwned;
+
+use super::{
+    backoff::{Attempts, Backoff},
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+    runtime::sleep,
+    transport::Transport,
+};
+
+/// Stream of items from a long-poll endpoint
+///
+/// Repeatedly sends the request created by a factory,
+/// which should carry the server-side timeout
+/// parameter, and yields the items of each response
+/// as they arrive. The factory receives the last item
+/// yielded so far, e.g. to compute the next offset
+/// (as with Telegram's `getUpdates`).
+///
+/// Transient errors (see `Error::is_transient`)
+/// reconnect after waiting according to the
+/// backoff, or as long as rate limits ask to up
+/// to its longest delay. Other errors end the stream
+pub struct LongPoll<F> {
+    factory: F,
+    backoff: Backoff,
+    max_retries: Option<u32>,
+}
+
+impl<F> LongPoll<F> {
+    /// Long-poll requests created by `factory`
+    ///
+    /// Reconnects indefinitely with the
+    /// default backoff unless configured
+    pub fn new(factory: F) -> Self {
+        Self {
+            factory,
+            backoff: Backoff::default(),
+            max_retries: None,
+        }
+    }
+
+    /// Delay between reconnection attempts
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up after `max_retries` consecutive
+    /// failed reconnection attempts
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Start polling through `client`
+    pub fn stream<C, R, T, I, S>(
+        self,
+        client: C,
+        base_url: S,
+    ) -> impl Stream<Item = Result<I, Error>>
+    where
+        C: Transport,
+        F: FnMut(Option<&I>) -> R,
+        R: Request<T>,
+        T: IntoIterator<Item = I> + DeserializeOwned + MaybeSend + MaybeSync,
+        I: Clone,
+        S: Into<String>,
+    {
+        let state = State {
+            poll: self,
+            client,
+            base_url: base_url.into(),
+            buffered: VecDeque::<I>::new(),
+            last: None,
+            failures: 0,
+            attempts: Attempts::new(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.buffered.pop_front() {
+                    state.last = Some(item.clone());
+                    return Some((Ok(item), state));
+                }
+
+                if state.done {
+                    return None;
+                }
+
+                let request = (state.poll.factory)(state.last.as_ref());
+
+                match request.send(&state.client, &state.base_url).await {
+                    Ok(items) => {
+                        state.failures = 0;
+                        state.attempts = Attempts::new();
+                        state.buffered.extend(items);
+                    }
+                    Err(err) if err.is_transient() && state.can_retry() => {
+                        let delay = state
+                            .poll
+                            .backoff
+                            .retry_delay(state.failures, err.retry_after());
+                        state.attempts.retry(err, delay);
+
+                        sleep(delay).await;
+                        state.failures += 1;
+                    }
+                    Err(err) => {
+                        state.done = true;
+                        let attempts = std::mem::replace(&mut state.attempts, Attempts::new());
+
+                        return Some((Err(attempts.give_up(err)), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct State<F, C, I> {
+    poll: LongPoll<F>,
+    client: C,
+    base_url: String,
+    buffered: VecDeque<I>,
+    last: Option<I>,
+    failures: u32,
+    attempts: Attempts,
+    done: bool,
+}
+
+impl<F, C, I> State<F, C, I> {
+    fn can_retry(&self) -> bool {
+        self.poll
+            .max_retries
+            .is_none_or(|max_retries| self.failures < max_retries)
+    }
+}
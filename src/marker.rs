@@ -0,0 +1,34 @@
+//! Thread-safety markers
+//!
+//! On native targets these are equivalent to `Send`
+//! and `Sync`. On `wasm32` the browser's fetch API
+//! is single-threaded and its futures are neither,
+//! so the markers are implemented for every type.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    /// `Send` on native targets, no bound on `wasm32`
+    pub trait MaybeSend: Send {}
+
+    impl<T: Send + ?Sized> MaybeSend for T {}
+
+    /// `Sync` on native targets, no bound on `wasm32`
+    pub trait MaybeSync: Sync {}
+
+    impl<T: Sync + ?Sized> MaybeSync for T {}
+}
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    /// `Send` on native targets, no bound on `wasm32`
+    pub trait MaybeSend {}
+
+    impl<T: ?Sized> MaybeSend for T {}
+
+    /// `Sync` on native targets, no bound on `wasm32`
+    pub trait MaybeSync {}
+
+    impl<T: ?Sized> MaybeSync for T {}
+}
+
+pub use imp::{MaybeSend, MaybeSync};
@@ -0,0 +1,131 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    transport::Transport,
+};
+
+/// Boxed future returned by middleware
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Boxed future returned by middleware
+#[cfg(target_arch = "wasm32")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Hook into the execution of every request
+/// sent through a `WrapiClient`
+///
+/// Middleware is run in the order it was added
+/// to the client, the last one handing the request
+/// over to the client's transport
+pub trait Middleware: MaybeSend + MaybeSync {
+    /// Handle the request
+    ///
+    /// Call `next.run(request)` to pass the request
+    /// further down the stack, or return a response
+    /// directly to short-circuit it
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>>;
+}
+
+/// The remainder of a middleware stack
+#[derive(Clone)]
+pub struct Next<'a> {
+    transport: &'a dyn Transport,
+    middleware: &'a [Arc<dyn Middleware>],
+    extensions: &'a Extensions,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        transport: &'a dyn Transport,
+        middleware: &'a [Arc<dyn Middleware>],
+        extensions: &'a Extensions,
+    ) -> Self {
+        Self {
+            transport,
+            middleware,
+            extensions,
+        }
+    }
+
+    /// Context of the call, e.g. a tenant ID or trace
+    /// context, see `Request::send_with_context`
+    ///
+    /// Empty for requests sent without one
+    pub fn extensions(&self) -> &'a Extensions {
+        self.extensions
+    }
+
+    /// Pass the request to the next middleware,
+    /// or execute it if none are left
+    pub fn run(self, request: Request) -> BoxFuture<'a, Result<Response, Error>> {
+        match self.middleware.split_first() {
+            Some((current, rest)) => {
+                current.handle(request, Next::new(self.transport, rest, self.extensions))
+            }
+            None => self.transport.execute_with(request, self.extensions),
+        }
+    }
+}
+
+/// Context of requests sent without one
+pub(crate) fn no_extensions() -> &'static Extensions {
+    static EXTENSIONS: OnceLock<Extensions> = OnceLock::new();
+
+    EXTENSIONS.get_or_init(Extensions::new)
+}
+
+/// Read the full body of a response
+///
+/// Returns the body alongside an equivalent
+/// response which can still be consumed
+///
+/// Not available on `wasm32`, where responses
+/// cannot be constructed from parts
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn buffer(response: Response) -> Result<(Response, bytes::Bytes), Error> {
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let url = response.url().clone();
+
+    let body = response.bytes().await?;
+
+    use reqwest::ResponseBuilderExt;
+
+    let mut builder = http::Response::builder()
+        .status(status)
+        .version(version)
+        .url(url);
+
+    if let Some(map) = builder.headers_mut() {
+        *map = headers;
+    }
+
+    let response = builder.body(body.clone())?;
+
+    Ok((Response::from(response), body))
+}
+
+/// Copy of `request` to send again,
+/// e.g. when retrying it
+///
+/// Bodies of bytes are shared rather than copied.
+/// Fails with `Error::NotReplayable` if the body
+/// is a stream, which can only be read once
+pub fn replay(request: &Request) -> Result<Request, Error> {
+    request.try_clone().ok_or(Error::NotReplayable)
+}
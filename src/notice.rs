@@ -0,0 +1,139 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use http::Method;
+use reqwest::{Request, Response, Url};
+
+use super::{
+    error::Error,
+    headers::{Deprecation, FromHeaders, Warning},
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Hook = dyn Fn(&Notice) + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Hook = dyn Fn(&Notice);
+
+/// Most endpoints remembered as reported, after
+/// which all of them are reported again
+const MAX_REPORTED: usize = 1024;
+
+/// Deprecation and warnings the server
+/// attached to a response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notice {
+    pub method: Method,
+    /// URL of the endpoint, without query
+    pub url: Url,
+    /// From the `Deprecation` and `Sunset` headers
+    pub deprecation: Option<Deprecation>,
+    /// From the `Warning` headers
+    pub warnings: Vec<Warning>,
+}
+
+/// Middleware reporting deprecations and warnings
+/// announced in response headers
+///
+/// Responses with `Deprecation`, `Sunset` or `Warning`
+/// headers are passed to the hook, e.g. to log them
+/// with `tracing::warn!`, once per method and endpoint
+/// so wrapper users learn about upstream deprecations
+/// without being flooded. Clones share this state.
+///
+/// Endpoints are told apart by host and path, with
+/// segments containing digits, e.g. IDs, counting as
+/// the same, versions like `v2` aside. At most 1024
+/// are remembered, then all are reported again
+#[derive(Clone)]
+pub struct Notices {
+    hook: Arc<Hook>,
+    reported: Arc<Mutex<HashSet<(Method, String)>>>,
+}
+
+impl Notices {
+    /// Report notices to `hook`
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&Notice) + MaybeSend + MaybeSync + 'static,
+    {
+        Self {
+            hook: Arc::new(hook),
+            reported: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Report notices of all endpoints
+    /// again, e.g. once a day
+    pub fn reset(&self) {
+        self.reported.lock().unwrap().clear();
+    }
+}
+
+impl Middleware for Notices {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let method = request.method().clone();
+
+            let mut url = request.url().clone();
+            url.set_query(None);
+            url.set_fragment(None);
+
+            let response = next.run(request).await?;
+
+            let notice = Notice {
+                method,
+                url,
+                deprecation: Deprecation::from_headers(response.headers()).ok(),
+                warnings: Vec::from_headers(response.headers()).unwrap_or_default(),
+            };
+
+            if notice.deprecation.is_some() || !notice.warnings.is_empty() {
+                let key = (notice.method.clone(), endpoint(&notice.url));
+                let mut reported = self.reported.lock().unwrap();
+
+                if reported.len() >= MAX_REPORTED && !reported.contains(&key) {
+                    reported.clear();
+                }
+
+                if reported.insert(key) {
+                    drop(reported);
+                    (self.hook)(&notice);
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Host and path of `url`, with segments
+/// containing digits replaced by `{id}`,
+/// except versions like `v2`
+fn endpoint(url: &Url) -> String {
+    let path: Vec<&str> = url
+        .path()
+        .split('/')
+        .map(|segment| match is_id(segment) {
+            true => "{id}",
+            false => segment,
+        })
+        .collect();
+
+    format!("{}{}", url.host_str().unwrap_or_default(), path.join("/"))
+}
+
+fn is_id(segment: &str) -> bool {
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    let version = segment.strip_prefix('v').is_some_and(digits);
+
+    !version && segment.bytes().any(|byte| byte.is_ascii_digit())
+}
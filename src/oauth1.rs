@@ -0,0 +1,356 @@
+//! OAuth 1.0a request signing, RFC 5849
+//!
+//! Older APIs still require every request to be signed
+//! with the consumer's and the token's credentials.
+//! `OAuth1` signs a `BuiltRequest`, e.g. in an override of
+//! `Request::prepare`, or every request of a client as
+//! middleware. With the `oauth1` feature
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap, HeaderValue, Method,
+};
+use reqwest::{Request, Response, Url};
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs1v15::SigningKey,
+    pkcs8::DecodePrivateKey,
+    signature::{SignatureEncoding, Signer},
+    RsaPrivateKey,
+};
+use sha1::Sha1;
+use uuid::Uuid;
+
+use super::{
+    built::{BodyFormat, BuiltRequest},
+    error::Error,
+    headers::media_type_matches,
+    middleware::{BoxFuture, Middleware, Next},
+    runtime,
+};
+
+/// How requests are signed
+#[derive(Clone)]
+enum SignatureMethod {
+    /// With the consumer secret
+    HmacSha1(String),
+    RsaSha1(Box<SigningKey<Sha1>>),
+}
+
+impl SignatureMethod {
+    fn name(&self) -> &'static str {
+        match self {
+            SignatureMethod::HmacSha1(_) => "HMAC-SHA1",
+            SignatureMethod::RsaSha1(_) => "RSA-SHA1",
+        }
+    }
+}
+
+/// Signs requests with OAuth 1.0a
+///
+/// Adds an `Authorization: OAuth ..` header signed over
+/// the method, URL, query and form parameters, with a
+/// fresh nonce and timestamp. Requests are signed for
+/// the consumer alone unless a `token` is set.
+///
+/// Add it to a `WrapiClient` as middleware, after any
+/// middleware changing requests, or `sign` a request
+#[derive(Clone)]
+pub struct OAuth1 {
+    consumer_key: String,
+    method: SignatureMethod,
+    /// Token and its secret
+    token: Option<(String, String)>,
+    realm: Option<String>,
+}
+
+impl OAuth1 {
+    /// Sign with `HMAC-SHA1`
+    pub fn hmac_sha1<K, S>(consumer_key: K, consumer_secret: S) -> Self
+    where
+        K: Into<String>,
+        S: Into<String>,
+    {
+        Self::new(
+            consumer_key.into(),
+            SignatureMethod::HmacSha1(consumer_secret.into()),
+        )
+    }
+
+    /// Sign with `RSA-SHA1`, with a private key
+    /// in PKCS#8 or PKCS#1 PEM
+    pub fn rsa_sha1<K: Into<String>>(consumer_key: K, private_key: &str) -> Result<Self, Error> {
+        let key = RsaPrivateKey::from_pkcs8_pem(private_key)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key))
+            .map_err(|_| Error::Builder("invalid RSA private key".to_string()))?;
+
+        Ok(Self::new(
+            consumer_key.into(),
+            SignatureMethod::RsaSha1(Box::new(SigningKey::new(key))),
+        ))
+    }
+
+    fn new(consumer_key: String, method: SignatureMethod) -> Self {
+        Self {
+            consumer_key,
+            method,
+            token: None,
+            realm: None,
+        }
+    }
+
+    /// Sign on behalf of the user
+    /// who granted `token`
+    pub fn token<T, S>(mut self, token: T, token_secret: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.token = Some((token.into(), token_secret.into()));
+        self
+    }
+
+    /// Send `realm` in the header, as
+    /// some APIs such as NetSuite require
+    pub fn realm<S: Into<String>>(mut self, realm: S) -> Self {
+        self.realm = Some(realm.into());
+        self
+    }
+
+    /// Sign `request`, replacing any
+    /// `Authorization` header
+    pub fn sign(&self, request: &mut BuiltRequest) -> Result<(), Error> {
+        self.sign_at(
+            request,
+            runtime::now(),
+            &Uuid::new_v4().simple().to_string(),
+        )
+    }
+
+    /// Like `sign`, at time `now` with `nonce`
+    ///
+    /// Nonces must not repeat, so this is
+    /// meant for known-answer tests, such as
+    /// the example of RFC 5849, section 1.2:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use wrapi::{built::BuiltRequest, http::Method, oauth1::OAuth1};
+    ///
+    /// let mut request = BuiltRequest {
+    ///     method: Method::GET,
+    ///     url: "http://photos.example.net/photos?file=vacation.jpg&size=original"
+    ///         .parse()
+    ///         .unwrap(),
+    ///     headers: Default::default(),
+    ///     query: Vec::new(),
+    ///     query_encoding: Default::default(),
+    ///     body: None,
+    /// };
+    ///
+    /// OAuth1::hmac_sha1("dpf43f3p2l4k3l03", "kd94hf93k423kf44")
+    ///     .token("nnch734d00sl2jdk", "pfkkdhi9sl3r4s00")
+    ///     .sign_at(
+    ///         &mut request,
+    ///         UNIX_EPOCH + Duration::from_secs(1191242096),
+    ///         "kllo9940pd9333jh",
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let authorization = request.headers["authorization"].to_str().unwrap();
+    /// assert!(authorization.contains(r#"oauth_signature="tR3%2BTy81lMeYAr%2FFid0kMTYa%2FWM%3D""#));
+    /// ```
+    pub fn sign_at(
+        &self,
+        request: &mut BuiltRequest,
+        now: SystemTime,
+        nonce: &str,
+    ) -> Result<(), Error> {
+        let form = request
+            .body
+            .as_ref()
+            .filter(|body| body.format == BodyFormat::Form)
+            .map(|body| body.bytes.as_ref());
+
+        let value = self.authorization(&request.method, &request.full_url(), form, now, nonce)?;
+        request.headers.insert(AUTHORIZATION, value);
+
+        Ok(())
+    }
+
+    /// `Authorization` header for a request to `url`,
+    /// with the parameters of a form body if any
+    fn authorization(
+        &self,
+        method: &Method,
+        url: &Url,
+        form: Option<&[u8]>,
+        now: SystemTime,
+        nonce: &str,
+    ) -> Result<HeaderValue, Error> {
+        let timestamp = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        let mut oauth = vec![
+            ("oauth_consumer_key", self.consumer_key.clone()),
+            ("oauth_nonce", nonce.to_string()),
+            ("oauth_signature_method", self.method.name().to_string()),
+            ("oauth_timestamp", timestamp.to_string()),
+            ("oauth_version", "1.0".to_string()),
+        ];
+
+        if let Some((token, _)) = &self.token {
+            oauth.push(("oauth_token", token.clone()));
+        }
+
+        // Parameters of the query, the form body and
+        // the protocol, encoded and sorted
+        let mut params: Vec<(String, String)> = url
+            .query_pairs()
+            .chain(form.into_iter().flat_map(form_urlencoded::parse))
+            .map(|(key, value)| (encode(&key), encode(&value)))
+            .chain(
+                oauth
+                    .iter()
+                    .map(|(key, value)| (encode(key), encode(value))),
+            )
+            .collect();
+        params.sort();
+
+        let params: Vec<String> = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+
+        let base = format!(
+            "{}&{}&{}",
+            method.as_str().to_ascii_uppercase(),
+            encode(&base_uri(url)),
+            encode(&params.join("&"))
+        );
+
+        let signature = self.signature(&base)?;
+        oauth.push(("oauth_signature", signature));
+
+        let mut fields: Vec<String> = self
+            .realm
+            .iter()
+            .map(|realm| format!("realm=\"{}\"", encode(realm)))
+            .collect();
+
+        fields.extend(
+            oauth
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, encode(value))),
+        );
+
+        let mut value = HeaderValue::try_from(format!("OAuth {}", fields.join(", ")))?;
+        value.set_sensitive(true);
+
+        Ok(value)
+    }
+
+    /// Base64 signature of the signature base string
+    fn signature(&self, base: &str) -> Result<String, Error> {
+        let signature = match &self.method {
+            SignatureMethod::HmacSha1(consumer_secret) => {
+                let token_secret = self.token.as_ref().map_or("", |(_, secret)| secret);
+                let key = format!("{}&{}", encode(consumer_secret), encode(token_secret));
+
+                let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes())
+                    .map_err(|_| Error::Builder("invalid OAuth secret".to_string()))?;
+                mac.update(base.as_bytes());
+
+                mac.finalize().into_bytes().to_vec()
+            }
+            SignatureMethod::RsaSha1(key) => key.sign(base.as_bytes()).to_vec(),
+        };
+
+        Ok(STANDARD.encode(signature))
+    }
+}
+
+impl fmt::Debug for OAuth1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth1")
+            .field("consumer_key", &self.consumer_key)
+            .field("signature_method", &self.method.name())
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Middleware for OAuth1 {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let form = match is_form(request.headers()) {
+                true => request.body().and_then(reqwest::Body::as_bytes),
+                false => None,
+            };
+
+            let value = self.authorization(
+                request.method(),
+                request.url(),
+                form,
+                runtime::now(),
+                &Uuid::new_v4().simple().to_string(),
+            )?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+
+            next.run(request).await
+        })
+    }
+}
+
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| {
+            media_type_matches("application/x-www-form-urlencoded", content_type)
+        })
+}
+
+/// URL without query and fragment, and
+/// without the port if it's the default
+fn base_uri(url: &Url) -> String {
+    match url.port() {
+        Some(port) => format!(
+            "{}://{}:{}{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default(),
+            port,
+            url.path()
+        ),
+        None => format!(
+            "{}://{}{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default(),
+            url.path()
+        ),
+    }
+}
+
+/// Percent-encode all but unreserved characters
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,381 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    future::Future,
+    sync::Arc,
+};
+
+use futures_core::Stream;
+use futures_util::{stream, stream::FuturesOrdered, StreamExt};
+use serde::de::DeserializeOwned;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+    transport::Transport,
+};
+
+/// Uniform pagination metadata of a response page
+///
+/// Implement for page response types so the
+/// `Paginator` can walk them, or read it directly
+/// when only one page and the knowledge whether
+/// more exist is needed
+pub trait PageInfo {
+    type Item;
+
+    /// Items of the page
+    fn into_items(self) -> Vec<Self::Item>;
+
+    /// Cursor of the next page, if any
+    #[inline]
+    fn next_cursor(&self) -> Option<String> {
+        None
+    }
+
+    /// Number of items across all pages,
+    /// if reported by the API
+    #[inline]
+    fn total_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether more pages follow
+    ///
+    /// Whether there is a `next_cursor()` by default
+    #[inline]
+    fn has_more(&self) -> bool {
+        self.next_cursor().is_some()
+    }
+}
+
+/// Pages are plain lists of items,
+/// without further pages
+impl<I> PageInfo for Vec<I> {
+    type Item = I;
+
+    fn into_items(self) -> Vec<I> {
+        self
+    }
+}
+
+/// Stream of items across the pages of a
+/// cursor-paginated endpoint
+///
+/// The factory creates the request of a page from
+/// the cursor of the previous one, `None` for the
+/// first page. The stream ends after a page without
+/// `has_more()` or `next_cursor()`, or if the API
+/// repeats a cursor, and on the first error
+pub struct Paginator<F> {
+    factory: F,
+}
+
+impl<F> Paginator<F> {
+    /// Paginate requests created by `factory`
+    pub fn new(factory: F) -> Self {
+        Self { factory }
+    }
+
+    /// Stream the pages through `client`
+    pub fn pages<C, R, P, S>(self, client: C, base_url: S) -> impl Stream<Item = Result<P, Error>>
+    where
+        C: Transport,
+        F: FnMut(Option<&str>) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        let state = State {
+            factory: self.factory,
+            client,
+            base_url: base_url.into(),
+            cursor: None,
+            seen: HashSet::new(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let request = (state.factory)(state.cursor.as_deref());
+
+            match request.send(&state.client, &state.base_url).await {
+                Ok(page) => {
+                    let next = page.next_cursor().filter(|_| page.has_more());
+
+                    state.done = match &next {
+                        Some(next) => !state.seen.insert(next.clone()),
+                        None => true,
+                    };
+                    state.cursor = next;
+
+                    Some((Ok(page), state))
+                }
+                Err(err) => {
+                    state.done = true;
+                    Some((Err(err), state))
+                }
+            }
+        })
+    }
+
+    /// Stream the items of all pages through `client`
+    pub fn stream<C, R, P, S>(
+        self,
+        client: C,
+        base_url: S,
+    ) -> impl Stream<Item = Result<P::Item, Error>>
+    where
+        C: Transport,
+        F: FnMut(Option<&str>) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        items(self.pages(client, base_url))
+    }
+
+    /// Collect the items of all pages, see `collect_all`
+    pub async fn collect_all<C, R, P, S>(
+        self,
+        client: C,
+        base_url: S,
+        limit: Option<usize>,
+    ) -> Result<Vec<P::Item>, Error>
+    where
+        C: Transport,
+        F: FnMut(Option<&str>) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        collect_all(self.stream(client, base_url), limit).await
+    }
+}
+
+struct State<F, C> {
+    factory: F,
+    client: C,
+    base_url: String,
+    cursor: Option<String>,
+    /// Cursors of all pages requested, to stop on cycles
+    seen: HashSet<String>,
+    done: bool,
+}
+
+/// Stream of items across the pages of an
+/// offset/limit-paginated endpoint
+///
+/// The factory creates the request of the page at
+/// an offset with the page size. Once the first
+/// page reports `total_count()`, the remaining pages
+/// are fetched concurrently, still yielding them in
+/// order, which greatly speeds up listing everything.
+/// Otherwise pages are fetched one after another
+/// while they report `has_more()`.
+///
+/// The stream ends on the first error
+pub struct OffsetPaginator<F> {
+    factory: F,
+    limit: u64,
+    concurrency: usize,
+}
+
+impl<F> OffsetPaginator<F> {
+    /// Paginate requests created by `factory`
+    /// with pages of `limit` items
+    ///
+    /// Fetches up to 4 pages at a time by default
+    pub fn new(limit: u64, factory: F) -> Self {
+        Self {
+            factory,
+            limit: limit.max(1),
+            concurrency: 4,
+        }
+    }
+
+    /// Fetch up to `concurrency` pages at a time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Stream the pages through `client`
+    pub fn pages<C, R, P, S>(self, client: C, base_url: S) -> impl Stream<Item = Result<P, Error>>
+    where
+        C: Transport,
+        F: FnMut(u64, u64) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        let mut state = OffsetState {
+            paginator: self,
+            target: Arc::new((client, base_url.into())),
+            next_offset: 0,
+            total: None,
+            in_flight: FuturesOrdered::new(),
+            done: false,
+        };
+
+        // The first page tells the total
+        state.queue(fetch::<C, R, P>);
+
+        stream::unfold(state, |mut state| async move {
+            let page = state.in_flight.next().await?;
+
+            match &page {
+                Ok(page) if state.total.is_none() => match page.total_count() {
+                    Some(total) => state.total = Some(total),
+                    None if page.has_more() => {}
+                    None => state.done = true,
+                },
+                Ok(_) => {}
+                Err(_) => {
+                    state.done = true;
+                    state.in_flight = FuturesOrdered::new();
+                }
+            }
+
+            if !state.done {
+                match state.total {
+                    Some(_) => {
+                        while state.in_flight.len() < state.paginator.concurrency
+                            && state.queue(fetch::<C, R, P>)
+                        {}
+                    }
+                    None => {
+                        state.queue(fetch::<C, R, P>);
+                    }
+                }
+            }
+
+            Some((page, state))
+        })
+    }
+
+    /// Stream the items of all pages through `client`
+    pub fn stream<C, R, P, S>(
+        self,
+        client: C,
+        base_url: S,
+    ) -> impl Stream<Item = Result<P::Item, Error>>
+    where
+        C: Transport,
+        F: FnMut(u64, u64) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        items(self.pages(client, base_url))
+    }
+
+    /// Collect the items of all pages, see `collect_all`
+    pub async fn collect_all<C, R, P, S>(
+        self,
+        client: C,
+        base_url: S,
+        limit: Option<usize>,
+    ) -> Result<Vec<P::Item>, Error>
+    where
+        C: Transport,
+        F: FnMut(u64, u64) -> R,
+        R: Request<P>,
+        P: PageInfo + DeserializeOwned + MaybeSend + MaybeSync,
+        S: Into<String>,
+    {
+        collect_all(self.stream(client, base_url), limit).await
+    }
+}
+
+struct OffsetState<F, C, Fut: Future> {
+    paginator: OffsetPaginator<F>,
+    target: Arc<(C, String)>,
+    next_offset: u64,
+    total: Option<u64>,
+    in_flight: FuturesOrdered<Fut>,
+    done: bool,
+}
+
+impl<F, C, Fut: Future> OffsetState<F, C, Fut> {
+    /// Queue the page at the next offset unless past
+    /// the total, returning whether one was queued
+    fn queue<R, G>(&mut self, fetch: G) -> bool
+    where
+        F: FnMut(u64, u64) -> R,
+        G: Fn(Arc<(C, String)>, R) -> Fut,
+    {
+        if self.total.is_some_and(|total| self.next_offset >= total) {
+            return false;
+        }
+
+        let request = (self.paginator.factory)(self.next_offset, self.paginator.limit);
+
+        self.in_flight
+            .push_back(fetch(self.target.clone(), request));
+        self.next_offset += self.paginator.limit;
+
+        true
+    }
+}
+
+/// Send the request of a page
+async fn fetch<C, R, P>(target: Arc<(C, String)>, request: R) -> Result<P, Error>
+where
+    C: Transport,
+    R: Request<P>,
+    P: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    request.send(&target.0, &target.1).await
+}
+
+/// Collect a stream of items into a `Vec`
+///
+/// Stops fetching and fails with `Error::TooManyItems`
+/// once there are more than `limit` items, so callers
+/// don't accidentally load a huge endpoint into memory
+pub async fn collect_all<T, S>(items: S, limit: Option<usize>) -> Result<Vec<T>, Error>
+where
+    S: Stream<Item = Result<T, Error>>,
+{
+    let mut items = Box::pin(items);
+    let mut collected = Vec::new();
+
+    while let Some(item) = items.next().await {
+        if let Some(limit) = limit.filter(|limit| collected.len() >= *limit) {
+            return Err(Error::TooManyItems { limit });
+        }
+
+        collected.push(item?);
+    }
+
+    Ok(collected)
+}
+
+/// Items of a stream of pages, in order
+fn items<P, S>(pages: S) -> impl Stream<Item = Result<P::Item, Error>>
+where
+    P: PageInfo,
+    S: Stream<Item = Result<P, Error>>,
+{
+    let pages = Box::pin(pages);
+
+    stream::unfold(
+        (pages, VecDeque::new()),
+        |(mut pages, mut buffered)| async move {
+            loop {
+                if let Some(item) = buffered.pop_front() {
+                    return Some((Ok(item), (pages, buffered)));
+                }
+
+                match pages.next().await? {
+                    Ok(page) => buffered.extend(page.into_items()),
+                    Err(err) => return Some((Err(err), (pages, buffered))),
+                }
+            }
+        },
+    )
+}
@@ -1,23 +1,43 @@
-use std::collections::HashMap;
+use http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, IF_MATCH},
+    HeaderMap, HeaderName, HeaderValue,
+};
+use serde::{ser::Error as _, Serialize};
+use serde_json::Value;
 
-use http::HeaderMap;
+use super::headers::ETag;
 
 /// Helper struct to for adding
 /// parameters to a request
-#[derive(Clone, Debug)]
+///
+/// Query and form parameters are kept as ordered
+/// key/value pairs, so repeated keys are preserved
+#[derive(Clone, Debug, Default)]
 pub struct Parameters {
     pub headers: Option<HeaderMap>,
-    pub query: Option<HashMap<String, String>>,
-    pub form: Option<HashMap<String, String>>,
+    pub query: Option<Vec<(String, String)>>,
+    pub form: Option<Vec<(String, String)>>,
 }
 
 impl Parameters {
     pub fn new() -> Self {
-        Self {
-            headers: None,
-            query: None,
-            form: None,
-        }
+        Self::default()
+    }
+
+    /// Create parameters with the query
+    /// built from a serializable value
+    ///
+    /// See `pairs_from` for how values are flattened
+    pub fn query_from<T: Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::new().query(pairs_from(value)?))
+    }
+
+    /// Create parameters with the form
+    /// built from a serializable value
+    ///
+    /// See `pairs_from` for how values are flattened
+    pub fn form_from<T: Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::new().form(pairs_from(value)?))
     }
 
     pub fn headers(mut self, headers: HeaderMap) -> Self {
@@ -25,13 +45,304 @@ impl Parameters {
         self
     }
 
-    pub fn query(mut self, query: HashMap<String, String>) -> Self {
-        self.query = Some(query);
+    pub fn query<I, K, V>(mut self, query: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.query = Some(into_pairs(query));
+        self
+    }
+
+    pub fn form<I, K, V>(mut self, form: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.form = Some(into_pairs(form));
+        self
+    }
+
+    /// Set a header, replacing existing values
+    ///
+    /// Fails if the name or value is invalid
+    pub fn header<K, V>(self, name: K, value: V) -> Result<Self, http::Error>
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        let name = HeaderName::try_from(name).map_err(Into::into)?;
+        let value = HeaderValue::try_from(value).map_err(Into::into)?;
+
+        Ok(self.insert_header(name, value))
+    }
+
+    /// Set the `Authorization` header to a bearer token
+    ///
+    /// The value is marked as sensitive
+    pub fn bearer<S: AsRef<str>>(self, token: S) -> Result<Self, http::Error> {
+        let mut value = HeaderValue::try_from(format!("Bearer {}", token.as_ref()))?;
+        value.set_sensitive(true);
+
+        Ok(self.insert_header(AUTHORIZATION, value))
+    }
+
+    /// Set the `Content-Type` header
+    pub fn content_type<S: AsRef<str>>(self, mime: S) -> Result<Self, http::Error> {
+        self.header(CONTENT_TYPE, mime.as_ref())
+    }
+
+    /// Set the `Accept` header
+    pub fn accept<S: AsRef<str>>(self, mime: S) -> Result<Self, http::Error> {
+        self.header(ACCEPT, mime.as_ref())
+    }
+
+    /// Set the `If-Match` header to an ETag
+    pub fn if_match(self, etag: &ETag) -> Result<Self, http::Error> {
+        self.header(IF_MATCH, etag.as_str())
+    }
+
+    fn insert_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers
+            .get_or_insert_with(HeaderMap::new)
+            .insert(name, value);
+        self
+    }
+
+    /// Add headers, replacing existing
+    /// values for the same header names
+    pub fn extend_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers
+            .get_or_insert_with(HeaderMap::new)
+            .extend(headers);
         self
     }
 
-    pub fn form(mut self, form: HashMap<String, String>) -> Self {
-        self.form = Some(form);
+    /// Insert a query parameter, replacing
+    /// existing values for the same key
+    pub fn insert_query<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let query = self.query.get_or_insert_with(Vec::new);
+        override_pairs(query, vec![(key.into(), value.into())]);
         self
     }
+
+    /// Insert a form parameter, replacing
+    /// existing values for the same key
+    pub fn insert_form<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let form = self.form.get_or_insert_with(Vec::new);
+        override_pairs(form, vec![(key.into(), value.into())]);
+        self
+    }
+
+    /// Layer `other` on top of these parameters
+    ///
+    /// `self` is the lower-precedence layer, e.g.
+    /// defaults shared by every request of a client,
+    /// and `other` the overrides, e.g. per-request
+    /// parameters. Headers and keys present in
+    /// both are taken from `other`, everything
+    /// else is kept from either side
+    pub fn merge(self, other: Parameters) -> Self {
+        Self {
+            headers: merge_with(self.headers, other.headers, |base, other| {
+                base.extend(other)
+            }),
+            query: merge_with(self.query, other.query, override_pairs),
+            form: merge_with(self.form, other.form, override_pairs),
+        }
+    }
+}
+
+/// How query parameters are percent-encoded
+///
+/// For APIs strict about exact encodings. Defaults
+/// to `form`, the encoding of reqwest and HTML forms
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryEncoding {
+    space_as_plus: bool,
+    /// Characters not percent-encoded
+    /// besides alphanumerics
+    unreserved: &'static str,
+    /// Reserved characters left as is
+    keep: String,
+    preencoded: bool,
+}
+
+impl QueryEncoding {
+    /// `application/x-www-form-urlencoded`: spaces as
+    /// `+` and everything but alphanumerics and `*-._`
+    /// percent-encoded
+    pub fn form() -> Self {
+        Self {
+            space_as_plus: true,
+            unreserved: "*-._",
+            keep: String::new(),
+            preencoded: false,
+        }
+    }
+
+    /// RFC 3986: spaces as `%20` and everything but
+    /// alphanumerics and `-._~` percent-encoded
+    pub fn rfc3986() -> Self {
+        Self {
+            space_as_plus: false,
+            unreserved: "-._~",
+            ..Self::form()
+        }
+    }
+
+    /// Keys and values are already percent-encoded
+    /// and sent as is, e.g. with non-UTF-8
+    /// sequences like `%E9` for Latin-1
+    pub fn preencoded() -> Self {
+        Self {
+            preencoded: true,
+            ..Self::form()
+        }
+    }
+
+    /// Leave the reserved `characters` unencoded,
+    /// e.g. `:,` for `at=12:00&ids=1,2`
+    ///
+    /// `&`, `=`, `#`, `%` and `+`, which
+    /// change the meaning of a query,
+    /// are encoded regardless
+    pub fn keep<S: AsRef<str>>(mut self, characters: S) -> Self {
+        self.keep.extend(
+            characters
+                .as_ref()
+                .chars()
+                .filter(|c| c.is_ascii_punctuation() && !"&=#%+".contains(*c)),
+        );
+        self
+    }
+
+    pub(crate) fn is_preencoded(&self) -> bool {
+        self.preencoded
+    }
+
+    /// Encode `pairs` into a query string
+    pub fn encode(&self, pairs: &[(String, String)]) -> String {
+        pairs
+            .iter()
+            .map(|(key, value)| match self.preencoded {
+                true => format!("{}={}", key, value),
+                false => format!("{}={}", self.component(key), self.component(value)),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn component(&self, text: &str) -> String {
+        let mut encoded = String::with_capacity(text.len());
+
+        for byte in text.bytes() {
+            let c = byte as char;
+
+            if c.is_ascii_alphanumeric() || self.unreserved.contains(c) || self.keep.contains(c) {
+                encoded.push(c);
+            } else if c == ' ' && self.space_as_plus {
+                encoded.push('+');
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+
+        encoded
+    }
+}
+
+impl Default for QueryEncoding {
+    fn default() -> Self {
+        Self::form()
+    }
+}
+
+/// Flatten a serializable value into key/value pairs
+///
+/// The value must serialize to a map or struct.
+/// Sequences produce one pair per element under the
+/// same key, `None`/null fields are skipped, and
+/// nested maps are rejected
+pub fn pairs_from<T: Serialize>(value: &T) -> Result<Vec<(String, String)>, serde_json::Error> {
+    let Value::Object(fields) = serde_json::to_value(value)? else {
+        return Err(serde_json::Error::custom(
+            "parameters must serialize to a map or struct",
+        ));
+    };
+
+    let mut pairs = Vec::new();
+
+    for (key, value) in fields {
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    if let Some(item) = scalar(&key, item)? {
+                        pairs.push((key.clone(), item));
+                    }
+                }
+            }
+            value => {
+                if let Some(value) = scalar(&key, value)? {
+                    pairs.push((key, value));
+                }
+            }
+        }
+    }
+
+    Ok(pairs)
+}
+
+fn scalar(key: &str, value: Value) -> Result<Option<String>, serde_json::Error> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(value) => Ok(Some(value)),
+        Value::Bool(value) => Ok(Some(value.to_string())),
+        Value::Number(value) => Ok(Some(value.to_string())),
+        Value::Array(_) | Value::Object(_) => Err(serde_json::Error::custom(format!(
+            "parameter `{}` must be a scalar or a sequence of scalars",
+            key
+        ))),
+    }
+}
+
+fn into_pairs<I, K, V>(pairs: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    pairs
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect()
+}
+
+/// Replace all pairs of `base` whose key
+/// appears in `other`, then append `other`
+fn override_pairs(base: &mut Vec<(String, String)>, other: Vec<(String, String)>) {
+    base.retain(|(key, _)| !other.iter().any(|(other, _)| other == key));
+    base.extend(other);
+}
+
+fn merge_with<T>(base: Option<T>, other: Option<T>, extend: impl FnOnce(&mut T, T)) -> Option<T> {
+    match (base, other) {
+        (Some(mut base), Some(other)) => {
+            extend(&mut base, other);
+            Some(base)
+        }
+        (base, other) => base.or(other),
+    }
 }
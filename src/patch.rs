@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Content type of JSON Merge Patch documents
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+
+/// Content type of JSON Patch documents
+pub const JSON_PATCH_JSON: &str = "application/json-patch+json";
+
+/// A JSON Merge Patch (RFC 7396) body
+///
+/// Serializes transparently as `T`. Model partial
+/// updates with `Option<Option<V>>` fields marked
+/// `#[serde(skip_serializing_if = "Option::is_none")]`:
+/// `None` leaves a field untouched, `Some(None)` clears
+/// it and `Some(Some(v))` sets it. Alternatively,
+/// compute the patch between two states with `diff`.
+///
+/// Send it with the `MERGE_PATCH_JSON` content type,
+/// e.g. via `Parameters::content_type`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MergePatch<T>(pub T);
+
+impl<T> MergePatch<T> {
+    pub fn new(patch: T) -> Self {
+        Self(patch)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl MergePatch<Value> {
+    /// Patch containing only the fields
+    /// changed from `before` to `after`
+    ///
+    /// Removed fields are set to `null`. Since
+    /// `null` means removal, fields changed to
+    /// `null` in `after` are removed as well
+    pub fn diff<B, A>(before: &B, after: &A) -> Result<Self, serde_json::Error>
+    where
+        B: Serialize + ?Sized,
+        A: Serialize + ?Sized,
+    {
+        Ok(Self(diff(
+            &serde_json::to_value(before)?,
+            &serde_json::to_value(after)?,
+        )))
+    }
+
+    /// Whether the patch changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.0.as_object().is_some_and(Map::is_empty)
+    }
+}
+
+fn diff(before: &Value, after: &Value) -> Value {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        return after.clone();
+    };
+
+    let mut patch = Map::new();
+
+    for (key, old) in before {
+        match after.get(key) {
+            None => {
+                patch.insert(key.clone(), Value::Null);
+            }
+            Some(new) if new != old => {
+                patch.insert(key.clone(), diff(old, new));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (key, new) in after {
+        if !before.contains_key(key) {
+            patch.insert(key.clone(), new.clone());
+        }
+    }
+
+    Value::Object(patch)
+}
+
+/// A single JSON Patch operation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// A JSON Patch (RFC 6902) body
+///
+/// Builds a list of operations, applied by the
+/// server in order. Paths are JSON Pointers,
+/// e.g. `/tags/0`.
+///
+/// Send it with the `JSON_PATCH_JSON` content type,
+/// e.g. via `Parameters::content_type`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch(pub Vec<Operation>);
+
+impl JsonPatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `value` at `path`
+    pub fn add<P: Into<String>, V: Into<Value>>(self, path: P, value: V) -> Self {
+        self.operation(Operation::Add {
+            path: path.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Remove the value at `path`
+    pub fn remove<P: Into<String>>(self, path: P) -> Self {
+        self.operation(Operation::Remove { path: path.into() })
+    }
+
+    /// Replace the value at `path`
+    pub fn replace<P: Into<String>, V: Into<Value>>(self, path: P, value: V) -> Self {
+        self.operation(Operation::Replace {
+            path: path.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Move the value at `from` to `path`
+    pub fn move_to<F: Into<String>, P: Into<String>>(self, from: F, path: P) -> Self {
+        self.operation(Operation::Move {
+            from: from.into(),
+            path: path.into(),
+        })
+    }
+
+    /// Copy the value at `from` to `path`
+    pub fn copy<F: Into<String>, P: Into<String>>(self, from: F, path: P) -> Self {
+        self.operation(Operation::Copy {
+            from: from.into(),
+            path: path.into(),
+        })
+    }
+
+    /// Only apply the patch if the
+    /// value at `path` equals `value`
+    pub fn test<P: Into<String>, V: Into<Value>>(self, path: P, value: V) -> Self {
+        self.operation(Operation::Test {
+            path: path.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Append an operation
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.0.push(operation);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,173 @@
+//! Pipelines of dependent requests
+//!
+//! Every `Request` is a `Step`. Steps chain with
+//! `and_then`, building the next request from the
+//! previous response, so multi-step flows such as
+//! create → upload → finalize read as a single
+//! pipeline with a single error path instead
+//! of nested awaits.
+//!
+//! All steps are sent through the same client and
+//! base URL, sharing its retries, auth and other
+//! middleware
+
+use std::{future::Future, marker::PhantomData};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+    transport::Transport,
+};
+
+/// A step of a pipeline, resolving to `T`
+pub trait Step<T>: MaybeSend + MaybeSync {
+    /// Run the step and the steps before it
+    fn run<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized;
+
+    /// Continue with the step built by
+    /// `next` from the result of this one
+    fn and_then<F, S, U>(self, next: F) -> AndThen<Self, F, T>
+    where
+        Self: Sized,
+        F: Fn(T) -> S + MaybeSend + MaybeSync,
+        S: Step<U>,
+    {
+        AndThen {
+            step: self,
+            next,
+            output: PhantomData,
+        }
+    }
+
+    /// Continue with the step built by `next`
+    /// from the outcome of this one, e.g. to
+    /// recover from errors
+    ///
+    /// Errors returned by `next` end the pipeline
+    fn then<F, S, U>(self, next: F) -> Then<Self, F, T>
+    where
+        Self: Sized,
+        F: Fn(Result<T, Error>) -> Result<S, Error> + MaybeSend + MaybeSync,
+        S: Step<U>,
+    {
+        Then {
+            step: self,
+            next,
+            output: PhantomData,
+        }
+    }
+
+    /// Transform the result of this step
+    fn map<F, U>(self, map: F) -> Map<Self, F, T>
+    where
+        Self: Sized,
+        F: Fn(T) -> U + MaybeSend + MaybeSync,
+    {
+        Map {
+            step: self,
+            map,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<R, T> Step<T> for R
+where
+    R: Request<T>,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    fn run<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        self.send(client, base_url)
+    }
+}
+
+/// Step built from the result of another,
+/// see `Step::and_then`
+pub struct AndThen<A, F, T> {
+    step: A,
+    next: F,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<A, F, S, T, U> Step<U> for AndThen<A, F, T>
+where
+    A: Step<T>,
+    F: Fn(T) -> S + MaybeSend + MaybeSync,
+    S: Step<U>,
+    T: MaybeSend,
+    U: MaybeSend,
+{
+    async fn run<C>(&self, client: &C, base_url: &str) -> Result<U, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let output = self.step.run(client, base_url).await?;
+
+        (self.next)(output).run(client, base_url).await
+    }
+}
+
+/// Step built from the outcome of another,
+/// see `Step::then`
+pub struct Then<A, F, T> {
+    step: A,
+    next: F,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<A, F, S, T, U> Step<U> for Then<A, F, T>
+where
+    A: Step<T>,
+    F: Fn(Result<T, Error>) -> Result<S, Error> + MaybeSend + MaybeSync,
+    S: Step<U>,
+    T: MaybeSend,
+    U: MaybeSend,
+{
+    async fn run<C>(&self, client: &C, base_url: &str) -> Result<U, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let outcome = self.step.run(client, base_url).await;
+
+        (self.next)(outcome)?.run(client, base_url).await
+    }
+}
+
+/// Step with a transformed result,
+/// see `Step::map`
+pub struct Map<A, F, T> {
+    step: A,
+    map: F,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<A, F, T, U> Step<U> for Map<A, F, T>
+where
+    A: Step<T>,
+    F: Fn(T) -> U + MaybeSend + MaybeSync,
+    T: MaybeSend,
+    U: MaybeSend,
+{
+    async fn run<C>(&self, client: &C, base_url: &str) -> Result<U, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        Ok((self.map)(self.step.run(client, base_url).await?))
+    }
+}
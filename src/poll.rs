@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use web_time::Instant;
+
+use super::{
+    backoff::Backoff,
+    cancel::CancellationToken,
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+    runtime::sleep,
+    transport::Transport,
+};
+
+/// Polls a status endpoint until a job completes
+///
+/// Many APIs answer long-running operations with
+/// `202 Accepted` and a job ID. A `Poller` repeatedly
+/// sends the status request created by a factory,
+/// waiting according to its `Backoff` in between,
+/// until the response satisfies a predicate or
+/// the deadline passes
+pub struct Poller<F, D> {
+    factory: F,
+    is_done: D,
+    backoff: Backoff,
+    deadline: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl<F, D> Poller<F, D> {
+    /// Poll requests created by `factory`
+    /// until `is_done` returns `true`
+    ///
+    /// Polls every second without a deadline by default
+    pub fn new(factory: F, is_done: D) -> Self {
+        Self {
+            factory,
+            is_done,
+            backoff: Backoff::fixed(Duration::from_secs(1)),
+            deadline: None,
+            cancellation: None,
+        }
+    }
+
+    /// Delay between polls
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up with `Error::DeadlineExceeded`
+    /// once `deadline` has elapsed
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop polling once `token` is cancelled
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Poll until done, returning the final response
+    ///
+    /// Errors from individual polls are returned as is
+    pub async fn run<C, R, T>(mut self, client: &C, base_url: &str) -> Result<T, Error>
+    where
+        C: Transport + ?Sized,
+        F: FnMut() -> R,
+        D: Fn(&T) -> bool,
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        match self.cancellation.take() {
+            Some(token) => token.run(self.poll(client, base_url)).await,
+            None => self.poll(client, base_url).await,
+        }
+    }
+
+    async fn poll<C, R, T>(&mut self, client: &C, base_url: &str) -> Result<T, Error>
+    where
+        C: Transport + ?Sized,
+        F: FnMut() -> R,
+        D: Fn(&T) -> bool,
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        let start = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            let response = (self.factory)().send(client, base_url).await?;
+
+            if (self.is_done)(&response) {
+                return Ok(response);
+            }
+
+            let delay = self.backoff.delay(attempt);
+            attempt = attempt.saturating_add(1);
+
+            if let Some(deadline) = self.deadline {
+                if start.elapsed() + delay > deadline {
+                    return Err(Error::DeadlineExceeded);
+                }
+            }
+
+            sleep(delay).await;
+        }
+    }
+}
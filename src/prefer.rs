@@ -0,0 +1,219 @@
+//! `Prefer` request preferences, RFC 7240
+//!
+//! APIs such as PostgREST let clients choose whether
+//! writes return the affected resources, how errors
+//! in the payload are handled or whether to respond
+//! asynchronously. Requests state preferences with
+//! `Request::prefer`, servers report the ones they
+//! honored in `Preference-Applied`, and `Returned`
+//! decodes responses of either `return` preference
+
+use std::{fmt, time::Duration};
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Deserializer};
+
+use super::{error::Error, headers::FromHeaders};
+
+pub const PREFER: HeaderName = HeaderName::from_static("prefer");
+pub const PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+/// Preferences sent as `Prefer`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Prefer {
+    preferences: Vec<(String, Option<String>)>,
+}
+
+impl Prefer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefer `name`, or `name=value`,
+    /// replacing an earlier `name`
+    pub fn preference<N, V>(mut self, name: N, value: Option<V>) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name = name.into().to_ascii_lowercase();
+
+        self.preferences.retain(|(existing, _)| *existing != name);
+        self.preferences.push((name, value.map(Into::into)));
+        self
+    }
+
+    /// `return=minimal`, an empty response
+    pub fn return_minimal(self) -> Self {
+        self.preference("return", Some("minimal"))
+    }
+
+    /// `return=representation`, the
+    /// resource as in a `GET` response
+    pub fn return_representation(self) -> Self {
+        self.preference("return", Some("representation"))
+    }
+
+    /// `respond-async`, e.g. a `202 Accepted`
+    /// for long-running operations
+    pub fn respond_async(self) -> Self {
+        self.preference("respond-async", None::<String>)
+    }
+
+    /// `wait`, the time to process
+    /// the request synchronously
+    pub fn wait(self, wait: Duration) -> Self {
+        self.preference("wait", Some(wait.as_secs().to_string()))
+    }
+
+    /// `handling=strict`, rejecting
+    /// invalid parts of the request
+    pub fn handling_strict(self) -> Self {
+        self.preference("handling", Some("strict"))
+    }
+
+    /// `handling=lenient`, ignoring
+    /// invalid parts of the request
+    pub fn handling_lenient(self) -> Self {
+        self.preference("handling", Some("lenient"))
+    }
+
+    /// Whether `name` is preferred
+    pub fn contains(&self, name: &str) -> bool {
+        self.preferences
+            .iter()
+            .any(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+
+    /// Send the preferences as `Prefer`,
+    /// unless one is already set
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) {
+        if self.preferences.is_empty() || headers.contains_key(PREFER) {
+            return;
+        }
+
+        if let Ok(value) = HeaderValue::try_from(self.to_string()) {
+            headers.insert(PREFER, value);
+        }
+    }
+}
+
+impl fmt::Display for Prefer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let preferences: Vec<String> = self
+            .preferences
+            .iter()
+            .map(|(name, value)| match value {
+                Some(value) if needs_quotes(value) => {
+                    format!("{}=\"{}\"", name, value.replace('"', "\\\""))
+                }
+                Some(value) => format!("{}={}", name, value),
+                None => name.clone(),
+            })
+            .collect();
+
+        write!(f, "{}", preferences.join(", "))
+    }
+}
+
+/// Preferences honored by the server
+///
+/// Read from `Preference-Applied`, empty if missing
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreferenceApplied {
+    preferences: Vec<(String, Option<String>)>,
+}
+
+impl PreferenceApplied {
+    /// Whether `name` was applied
+    pub fn contains(&self, name: &str) -> bool {
+        self.preferences
+            .iter()
+            .any(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+
+    /// Value of the applied preference `name`,
+    /// e.g. `minimal` for `return`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.preferences
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Whether the server returned the
+    /// representation of the resource
+    pub fn is_representation(&self) -> bool {
+        self.get("return") == Some("representation")
+    }
+}
+
+impl FromHeaders for PreferenceApplied {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let mut preferences = Vec::new();
+
+        for value in headers.get_all(PREFERENCE_APPLIED) {
+            let value = value.to_str().map_err(|_| {
+                Error::ClientDecodeError("invalid header `preference-applied`".to_string())
+            })?;
+
+            for preference in value.split(',') {
+                // Parameters after `;` are ignored
+                let preference = preference.split(';').next().unwrap_or_default().trim();
+
+                if preference.is_empty() {
+                    continue;
+                }
+
+                let (name, value) = match preference.split_once('=') {
+                    Some((name, value)) => (name, Some(value.trim().trim_matches('"'))),
+                    None => (preference, None),
+                };
+
+                preferences.push((name.trim().to_ascii_lowercase(), value.map(str::to_string)));
+            }
+        }
+
+        Ok(Self { preferences })
+    }
+}
+
+/// Response of a write with a `return` preference
+///
+/// `Minimal` for an empty body, as with `return=minimal`
+/// or a server ignoring `return=representation`, and the
+/// `Representation` otherwise. Use it as the response type
+/// of requests whose `prefer()` varies, or of servers
+/// not always honoring it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Returned<T> {
+    Representation(T),
+    Minimal,
+}
+
+impl<T> Returned<T> {
+    pub fn representation(self) -> Option<T> {
+        match self {
+            Returned::Representation(representation) => Some(representation),
+            Returned::Minimal => None,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Returned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(representation) => Returned::Representation(representation),
+            None => Returned::Minimal,
+        })
+    }
+}
+
+/// Whether `value` isn't a token
+/// and must be sent quoted
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || !value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
@@ -0,0 +1,485 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+    timing::QueueWait,
+};
+
+/// Header carrying the priority of a request, RFC 9218
+pub const PRIORITY: HeaderName = HeaderName::from_static("priority");
+
+/// Number of urgency levels
+const LEVELS: usize = 8;
+
+/// Priority of a request, as an RFC 9218 urgency
+/// from `0`, most urgent, to `7`, least urgent
+///
+/// Sent in the `Priority` header, see `Request::priority`,
+/// which servers supporting it use to schedule responses
+/// and `PriorityQueue` to dispatch requests
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Priority(u8);
+
+impl Priority {
+    /// Interactive calls
+    pub const HIGH: Priority = Priority(1);
+    /// The default urgency
+    pub const NORMAL: Priority = Priority(3);
+    /// Background work, e.g. bulk syncs
+    pub const LOW: Priority = Priority(5);
+
+    /// Priority of urgency `urgency`,
+    /// capped at `7`
+    pub fn urgency(urgency: u8) -> Self {
+        Self(urgency.min(LEVELS as u8 - 1))
+    }
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Priority of the `Priority` header, `NORMAL`
+    /// if it's missing or sets no urgency
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(PRIORITY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value.split(',').find_map(|member| {
+                    let urgency = member.trim().strip_prefix("u=")?;
+                    urgency.trim().parse::<u8>().ok()
+                })
+            })
+            .map(Self::urgency)
+            .unwrap_or_default()
+    }
+
+    /// Send the priority as `Priority`,
+    /// unless one is already set
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if !headers.contains_key(PRIORITY) {
+            if let Ok(value) = HeaderValue::try_from(format!("u={}", self.0)) {
+                headers.insert(PRIORITY, value);
+            }
+        }
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+/// What `PriorityQueue` does with requests
+/// arriving while its queue is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait outside the queue, in order of
+    /// arrival, until it has room again
+    #[default]
+    Wait,
+    /// Fail the request with `Error::QueueFull`
+    Reject,
+    /// Fail the longest waiting request with
+    /// `Error::QueueFull` to make room
+    ShedOldest,
+}
+
+/// Snapshot of the state of a `PriorityQueue`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub in_flight: usize,
+    /// Requests waiting in the queue
+    pub queued: usize,
+    /// Requests waiting for room in the
+    /// queue, with `Overflow::Wait`
+    pub overflowing: usize,
+    /// Requests rejected or shed so far
+    pub dropped: u64,
+}
+
+/// Middleware limiting the requests in flight,
+/// dispatching waiting ones by priority
+///
+/// Once `max_in_flight` requests are running, further
+/// requests are queued and started most urgent first,
+/// in order within an urgency, so background syncs
+/// sharing a client don't starve interactive calls.
+/// Priorities are read from the `Priority` header,
+/// see `Request::priority`.
+///
+/// The queue is unbounded unless limited with
+/// `max_queued`, see `Overflow`. With `per_endpoint`,
+/// requests to a busy endpoint wait without holding
+/// up those to other endpoints, so bursts to one
+/// endpoint can't take every slot.
+///
+/// A request is in flight until its response headers
+/// arrive. Clones share the limit and queue
+#[derive(Clone, Debug)]
+pub struct PriorityQueue {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    max_in_flight: usize,
+    max_queued: usize,
+    per_endpoint: Option<usize>,
+    overflow: Overflow,
+    in_flight: usize,
+    in_flight_by_endpoint: HashMap<String, usize>,
+    next_ticket: u64,
+    /// Queued tickets by urgency
+    queued: [VecDeque<Waiting>; LEVELS],
+    /// Tickets waiting for room in the queue
+    overflowing: VecDeque<(usize, Waiting)>,
+    wakers: HashMap<u64, Waker>,
+    /// Tickets started but not yet polled,
+    /// already counted in flight
+    granted: HashSet<u64>,
+    /// Tickets shed but not yet polled
+    shed: HashSet<u64>,
+    dropped: u64,
+}
+
+/// A request waiting to start
+#[derive(Debug)]
+struct Waiting {
+    id: u64,
+    endpoint: String,
+}
+
+impl State {
+    fn queued(&self) -> usize {
+        self.queued.iter().map(VecDeque::len).sum()
+    }
+
+    fn has_room(&self, endpoint: &str) -> bool {
+        self.per_endpoint.is_none_or(|max| {
+            self.in_flight_by_endpoint
+                .get(endpoint)
+                .is_none_or(|in_flight| *in_flight < max)
+        })
+    }
+
+    /// Start queued tickets while there's room,
+    /// and queue overflowing ones
+    fn dispatch(&mut self) {
+        loop {
+            // Also into an empty queue, so overflowing
+            // tickets still run with `max_queued` of 0
+            while self.queued() < self.max_queued.max(1) {
+                let Some((urgency, waiting)) = self.overflowing.pop_front() else {
+                    break;
+                };
+
+                self.queued[urgency].push_back(waiting);
+            }
+
+            if self.in_flight >= self.max_in_flight {
+                break;
+            }
+
+            // The most urgent ticket whose endpoint has room
+            let next = self.queued.iter().enumerate().find_map(|(urgency, queue)| {
+                let index = queue
+                    .iter()
+                    .position(|waiting| self.has_room(&waiting.endpoint))?;
+
+                Some((urgency, index))
+            });
+
+            let Some((urgency, index)) = next else {
+                break;
+            };
+
+            let Some(waiting) = self.queued[urgency].remove(index) else {
+                break;
+            };
+
+            self.in_flight += 1;
+            *self
+                .in_flight_by_endpoint
+                .entry(waiting.endpoint)
+                .or_default() += 1;
+            self.granted.insert(waiting.id);
+
+            if let Some(waker) = self.wakers.remove(&waiting.id) {
+                waker.wake();
+            }
+        }
+    }
+
+    fn release(&mut self, endpoint: &str) {
+        self.in_flight -= 1;
+
+        if let Some(in_flight) = self.in_flight_by_endpoint.get_mut(endpoint) {
+            *in_flight -= 1;
+
+            if *in_flight == 0 {
+                self.in_flight_by_endpoint.remove(endpoint);
+            }
+        }
+
+        self.dispatch();
+    }
+
+    /// Remove a waiting ticket from wherever it waits
+    fn remove(&mut self, id: u64) {
+        for queue in &mut self.queued {
+            queue.retain(|waiting| waiting.id != id);
+        }
+
+        self.overflowing.retain(|(_, waiting)| waiting.id != id);
+    }
+
+    /// Apply the overflow policy if the queue is
+    /// over its limit after queueing ticket `id`
+    fn overflow(&mut self, id: u64, urgency: usize, endpoint: &str) -> Result<(), Error> {
+        if self.queued() <= self.max_queued {
+            return Ok(());
+        }
+
+        match self.overflow {
+            Overflow::Wait => {
+                self.remove(id);
+                self.overflowing.push_back((
+                    urgency,
+                    Waiting {
+                        id,
+                        endpoint: endpoint.to_string(),
+                    },
+                ));
+            }
+            Overflow::Reject => {
+                self.remove(id);
+                self.dropped += 1;
+                return Err(Error::QueueFull);
+            }
+            Overflow::ShedOldest => {
+                let oldest = self
+                    .queued
+                    .iter()
+                    .flatten()
+                    .map(|waiting| waiting.id)
+                    .min()
+                    .unwrap_or(id);
+
+                self.remove(oldest);
+                self.dropped += 1;
+
+                if oldest == id {
+                    return Err(Error::QueueFull);
+                }
+
+                self.shed.insert(oldest);
+
+                if let Some(waker) = self.wakers.remove(&oldest) {
+                    waker.wake();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PriorityQueue {
+    /// Run up to `max_in_flight` requests at a time
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                max_in_flight: max_in_flight.max(1),
+                max_queued: usize::MAX,
+                per_endpoint: None,
+                overflow: Overflow::default(),
+                in_flight: 0,
+                in_flight_by_endpoint: HashMap::new(),
+                next_ticket: 0,
+                queued: Default::default(),
+                overflowing: VecDeque::new(),
+                wakers: HashMap::new(),
+                granted: HashSet::new(),
+                shed: HashSet::new(),
+                dropped: 0,
+            })),
+        }
+    }
+
+    /// Queue up to `max_queued` requests,
+    /// handling more with `overflow`
+    pub fn max_queued(self, max_queued: usize, overflow: Overflow) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.max_queued = max_queued;
+            state.overflow = overflow;
+        }
+
+        self
+    }
+
+    /// Run up to `max_in_flight` requests
+    /// at a time per endpoint
+    ///
+    /// Endpoints are keyed by method,
+    /// host and path, without query
+    pub fn per_endpoint(self, max_in_flight: usize) -> Self {
+        self.state.lock().unwrap().per_endpoint = Some(max_in_flight.max(1));
+        self
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.state.lock().unwrap();
+
+        QueueStats {
+            in_flight: state.in_flight,
+            queued: state.queued(),
+            overflowing: state.overflowing.len(),
+            dropped: state.dropped,
+        }
+    }
+
+    /// Wait until a request of `priority`
+    /// to `endpoint` may start
+    async fn acquire(&self, priority: Priority, endpoint: String) -> Result<Permit<'_>, Error> {
+        let mut ticket = Ticket {
+            queue: self,
+            urgency: priority.0 as usize,
+            endpoint,
+            id: None,
+        };
+
+        poll_fn(|cx| ticket.poll(cx)).await?;
+
+        // Started, so dropping the ticket won't release
+        ticket.id = None;
+
+        Ok(Permit {
+            queue: self,
+            endpoint: std::mem::take(&mut ticket.endpoint),
+        })
+    }
+}
+
+/// Place of a request waiting in a `PriorityQueue`
+struct Ticket<'a> {
+    queue: &'a PriorityQueue,
+    urgency: usize,
+    endpoint: String,
+    /// Set once queued
+    id: Option<u64>,
+}
+
+impl Ticket<'_> {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let mut state = self.queue.state.lock().unwrap();
+
+        let Some(id) = self.id else {
+            let id = state.next_ticket;
+            state.next_ticket += 1;
+
+            state.queued[self.urgency].push_back(Waiting {
+                id,
+                endpoint: self.endpoint.clone(),
+            });
+            state.dispatch();
+
+            if state.granted.remove(&id) {
+                return Poll::Ready(Ok(()));
+            }
+
+            state.overflow(id, self.urgency, &self.endpoint)?;
+            state.wakers.insert(id, cx.waker().clone());
+            self.id = Some(id);
+
+            return Poll::Pending;
+        };
+
+        if state.granted.remove(&id) {
+            return Poll::Ready(Ok(()));
+        }
+
+        if state.shed.remove(&id) {
+            self.id = None;
+            return Poll::Ready(Err(Error::QueueFull));
+        }
+
+        state.wakers.insert(id, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Ticket<'_> {
+    /// Leave the queue if cancelled while waiting
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+
+        let mut state = self.queue.state.lock().unwrap();
+        state.wakers.remove(&id);
+        state.shed.remove(&id);
+
+        match state.granted.remove(&id) {
+            // Started in the meantime, pass the slot on
+            true => state.release(&self.endpoint),
+            false => {
+                state.remove(id);
+                state.dispatch();
+            }
+        }
+    }
+}
+
+/// Slot of a request in flight
+struct Permit<'a> {
+    queue: &'a PriorityQueue,
+    endpoint: String,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().release(&self.endpoint);
+    }
+}
+
+impl Middleware for PriorityQueue {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let url = request.url();
+            let endpoint = format!(
+                "{} {}{}",
+                request.method(),
+                url.host_str().unwrap_or_default(),
+                url.path()
+            );
+
+            let start = Instant::now();
+            let _permit = self
+                .acquire(Priority::from_headers(request.headers()), endpoint)
+                .await?;
+            let waited = start.elapsed();
+
+            let mut response = next.run(request).await?;
+            QueueWait(waited).record(&mut response);
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{Request, Response};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    headers::{FromHeaders, RateLimitInfo},
+    middleware::{BoxFuture, Middleware, Next},
+    priority::Priority,
+};
+
+/// Middleware tracking the request budget of a client
+///
+/// Counts the requests sent against a declared limit,
+/// e.g. `Quota::new(5000, Duration::from_secs(3600))`
+/// for 5000 requests per hour in fixed windows starting
+/// with the first request, and/or against the budget
+/// reported in `RateLimit-*` response headers.
+///
+/// With a reserve, requests of the given `Priority` or
+/// less urgent fail with `Error::QuotaExhausted` once
+/// the remaining budget is down to it, keeping it for
+/// interactive calls. Clones share the budget
+#[derive(Clone, Debug)]
+pub struct Quota {
+    declared: Option<(u64, Duration)>,
+    sync_headers: bool,
+    reserve: Option<(u64, Priority)>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    window_start: Option<Instant>,
+    /// Requests sent in the current window
+    used: u64,
+    reported: Option<Reported>,
+}
+
+/// Budget of the last `RateLimit-*` headers
+#[derive(Debug)]
+struct Reported {
+    limit: Option<u64>,
+    remaining: u64,
+    /// Requests sent since
+    sent: u64,
+}
+
+impl Quota {
+    /// Allow `limit` requests per `period`
+    pub fn new(limit: u64, period: Duration) -> Self {
+        Self {
+            declared: Some((limit, period)),
+            sync_headers: false,
+            reserve: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Track the budget reported in the
+    /// `RateLimit-*` headers of responses
+    pub fn from_headers() -> Self {
+        Self {
+            declared: None,
+            sync_headers: true,
+            reserve: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Also track the budget reported in the
+    /// `RateLimit-*` headers, using the lower
+    /// of it and the declared one
+    pub fn sync_headers(mut self) -> Self {
+        self.sync_headers = true;
+        self
+    }
+
+    /// Keep the last `requests` of the budget for
+    /// requests more urgent than `priority`
+    pub fn reserve(mut self, requests: u64, priority: Priority) -> Self {
+        self.reserve = Some((requests, priority));
+        self
+    }
+
+    /// Requests allowed per window, if known
+    pub fn limit(&self) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state);
+
+        match (self.declared, &state.reported) {
+            (Some((limit, _)), _) => Some(limit),
+            (None, Some(reported)) => reported.limit,
+            (None, None) => None,
+        }
+    }
+
+    /// Requests left in the current window, if known
+    pub fn remaining(&self) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state);
+
+        self.remaining_of(&state)
+    }
+
+    /// Requests sent in the current window
+    pub fn used(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state);
+
+        state.used
+    }
+
+    fn remaining_of(&self, state: &State) -> Option<u64> {
+        let declared = self
+            .declared
+            .map(|(limit, _)| limit.saturating_sub(state.used));
+
+        let reported = state
+            .reported
+            .as_ref()
+            .map(|reported| reported.remaining.saturating_sub(reported.sent));
+
+        match (declared, reported) {
+            (Some(declared), Some(reported)) => Some(declared.min(reported)),
+            (declared, reported) => declared.or(reported),
+        }
+    }
+
+    /// Start a new window once the current one passed
+    fn roll(&self, state: &mut State) {
+        let Some((_, period)) = self.declared else {
+            return;
+        };
+
+        if state
+            .window_start
+            .is_some_and(|start| start.elapsed() >= period)
+        {
+            *state = State::default();
+        }
+    }
+
+    /// Count a request of `priority`,
+    /// unless it must leave the reserve
+    fn acquire(&self, priority: Priority) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state);
+
+        if let (Some((reserve, reserved_below)), Some(remaining)) =
+            (self.reserve, self.remaining_of(&state))
+        {
+            if remaining <= reserve && priority.value() >= reserved_below.value() {
+                return Err(Error::QuotaExhausted { remaining });
+            }
+        }
+
+        state.window_start.get_or_insert_with(Instant::now);
+        state.used += 1;
+
+        if let Some(reported) = &mut state.reported {
+            reported.sent += 1;
+        }
+
+        Ok(())
+    }
+
+    fn report(&self, info: RateLimitInfo) {
+        self.state.lock().unwrap().reported = Some(Reported {
+            limit: info.limit,
+            remaining: info.remaining,
+            sent: 0,
+        });
+    }
+}
+
+impl Middleware for Quota {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            self.acquire(Priority::from_headers(request.headers()))?;
+
+            let response = next.run(request).await?;
+
+            if self.sync_headers {
+                if let Ok(info) = RateLimitInfo::from_headers(response.headers()) {
+                    self.report(info);
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,128 @@
+use http::{
+    header::{
+        AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+        PROXY_AUTHORIZATION,
+    },
+    Method, StatusCode,
+};
+use reqwest::{Request, Response};
+
+/// Redirect policy of a request,
+/// see `Request::redirect`
+///
+/// `303 See Other`, as well as `301` and `302` after a
+/// `POST`, are followed with a `GET` without body, other
+/// redirects with the same method and body. Redirects to
+/// another origin drop `Authorization`, `Proxy-Authorization`
+/// and `Cookie` unless `strip_auth` is disabled, so
+/// credentials aren't forwarded e.g. to presigned URLs.
+/// Such redirects are sent with `CrossOriginRedirect` in
+/// their call context, so middleware doesn't add them back.
+///
+/// Once the limit is reached, or a URL would be visited
+/// twice, the redirect response fails with
+/// `Error::ResponseError` unless it's among the
+/// `Request::expected_statuses`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirect {
+    max: usize,
+    strip_auth: bool,
+}
+
+impl Redirect {
+    /// Return redirect responses as is
+    pub fn none() -> Self {
+        Self::limited(0)
+    }
+
+    /// Follow up to `max` redirects in a row
+    pub fn limited(max: usize) -> Self {
+        Self {
+            max,
+            strip_auth: true,
+        }
+    }
+
+    /// Follow every redirect, short of loops
+    pub fn all() -> Self {
+        Self::limited(usize::MAX)
+    }
+
+    /// Whether to drop credentials when redirected
+    /// to another origin, enabled by default
+    pub fn strip_auth(mut self, strip_auth: bool) -> Self {
+        self.strip_auth = strip_auth;
+        self
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub(crate) fn strips_auth(&self) -> bool {
+        self.strip_auth
+    }
+
+    /// The request following `response`, the
+    /// answer to `request`, if it's a redirect
+    pub(crate) fn follow(&self, mut request: Request, response: &Response) -> Option<Request> {
+        let status = response.status();
+
+        if ![
+            StatusCode::MOVED_PERMANENTLY,
+            StatusCode::FOUND,
+            StatusCode::SEE_OTHER,
+            StatusCode::TEMPORARY_REDIRECT,
+            StatusCode::PERMANENT_REDIRECT,
+        ]
+        .contains(&status)
+        {
+            return None;
+        }
+
+        let location = response.headers().get(LOCATION)?.to_str().ok()?;
+        let url = request.url().join(location).ok()?;
+
+        let as_get = match status {
+            StatusCode::SEE_OTHER => *request.method() != Method::HEAD,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *request.method() == Method::POST,
+            _ => false,
+        };
+
+        if as_get {
+            *request.method_mut() = Method::GET;
+            *request.body_mut() = None;
+
+            for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+                request.headers_mut().remove(name);
+            }
+        }
+
+        if self.strip_auth && url.origin() != request.url().origin() {
+            for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+                request.headers_mut().remove(name);
+            }
+        }
+
+        *request.url_mut() = url;
+
+        Some(request)
+    }
+}
+
+/// Marks a redirect to another origin than the
+/// request in the context of its call, see
+/// `Next::extensions`
+///
+/// Middleware adding credentials, e.g. `CachedToken`
+/// and `Session`, leaves such requests alone. Only
+/// set if the `Redirect` strips credentials
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrossOriginRedirect;
+
+impl Default for Redirect {
+    /// Up to 10 redirects, like reqwest
+    fn default() -> Self {
+        Self::limited(10)
+    }
+}
@@ -1,22 +1,103 @@
 use std::{collections::HashMap, future::Future};
 
-use http::{HeaderMap, Method};
-use reqwest::{Client, RequestBuilder, Response};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::{
+    header::{
+        ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE,
+        IF_MATCH,
+    },
+    Extensions, HeaderMap, HeaderValue, Method, StatusCode,
+};
+use reqwest::{Client, RequestBuilder, Response, Url};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
+use web_time::Instant;
 
-use super::error::Error;
+use super::{
+    built::{basic_auth, redact_url, Body, BuiltRequest},
+    compression::{AcceptEncoding, Encoding},
+    cookie::{append_cookies, cookie_header},
+    decode,
+    error::{Error, RequestContext, ValidationError},
+    fingerprint::Fingerprints,
+    format::Format,
+    headers::{media_type_matches, ETag, FromHeaders},
+    idempotency::Idempotency,
+    marker::{MaybeSend, MaybeSync},
+    parameters::{Parameters, QueryEncoding},
+    prefer::Prefer,
+    priority::Priority,
+    redirect::{CrossOriginRedirect, Redirect},
+    request_id::request_id,
+    response::{BorrowedBody, FullResponse, Metadata},
+    serialization::Serialization,
+    size_limit::{check_length, effective_limit},
+    transport::{builder_client, Transport, WithExtensions},
+    url::join,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::body_stream::BodyStream;
+#[cfg(feature = "json-schema")]
+use super::schema::Schema;
+
+/// Derive `Request` from attributes, with the
+/// `derive` feature
+///
+/// `#[request(method = "GET", endpoint = "/users/{id}", response = User)]`
+/// on the request, with `{field}` placeholders
+/// formatted from its fields. Header values are
+/// templated alike with e.g.
+/// `#[request(header("X-Tenant" = "{tenant_id}"))]`,
+/// for APIs routing by header. `#[request(no_body)]`
+/// sends the request without a body
+#[cfg(feature = "derive")]
+pub use wrapi_derive::Request;
 
 pub trait Request<T>
 where
-    Self: Serialize + Send + Sync,
-    T: DeserializeOwned + Send + Sync,
+    Self: Serialize + MaybeSend + MaybeSync,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
 {
     /// Endpoint to perform the request for
     ///
-    /// E.g. `format!("/users/{}", user_id)`
+    /// E.g. `format!("/users/{}", user_id)`, joined
+    /// onto the base URL as described in `url::join`
     fn endpoint(&self) -> String;
 
+    /// Base URL overriding the one passed to `send`
+    ///
+    /// E.g. for endpoints served from a different
+    /// host, like uploads. `endpoint()` is still
+    /// joined onto it
+    #[inline]
+    fn base_url(&self) -> Option<String> {
+        None
+    }
+
+    /// Absolute URL to send the request to,
+    /// ignoring any base URL and `endpoint()`
+    ///
+    /// E.g. for following hypermedia links,
+    /// presigned URLs or pagination `next`
+    /// URLs returned by the server
+    #[inline]
+    fn absolute_url(&self) -> Option<Url> {
+        None
+    }
+
+    /// Whether an absolute `endpoint()` may point to
+    /// another origin than the base URL
+    ///
+    /// If not, such requests fail with `Error::Builder`
+    /// rather than sending the credentials of the client
+    /// elsewhere. Allowed by default
+    #[inline]
+    fn cross_origin(&self) -> bool {
+        true
+    }
+
     /// HTTP method to use
     fn method(&self) -> Method;
 
@@ -32,12 +113,38 @@ where
         None
     }
 
+    /// How query parameters are percent-encoded,
+    /// `QueryEncoding::form` by default
+    #[inline]
+    fn query_encoding(&self) -> QueryEncoding {
+        QueryEncoding::default()
+    }
+
     /// Form parameters to include in the request
     #[inline]
     fn form(&self) -> Option<HashMap<String, String>> {
         None
     }
 
+    /// Cookies to include in the request
+    ///
+    /// Combined with a `Cookie` header from
+    /// `headers()` or `params()` if present
+    #[inline]
+    fn cookies(&self) -> Option<HashMap<String, String>> {
+        None
+    }
+
+    /// Additional parameters to include in the request
+    ///
+    /// Merged on top of `headers()`, `query()` and
+    /// `form()`, so on conflicting header names or
+    /// parameter keys the values from `params()` win
+    #[inline]
+    fn params(&self) -> Option<Parameters> {
+        None
+    }
+
     /// Bearer token to include in the request
     #[inline]
     fn bearer(&self) -> Option<String> {
@@ -50,6 +157,221 @@ where
         None
     }
 
+    /// Idempotency key policy of the request
+    ///
+    /// `send` generates the key once per call
+    /// and reuses it for every attempt
+    #[inline]
+    fn idempotency(&self) -> Option<Idempotency> {
+        None
+    }
+
+    /// Media type accepted in the response
+    ///
+    /// Sent as `Accept` unless the headers already
+    /// contain one, and checked against the response
+    /// `Content-Type` before deserializing, failing with
+    /// `Error::UnexpectedContentType` on a mismatch.
+    /// The media type of `format()` by default
+    #[inline]
+    fn accept(&self) -> Option<String> {
+        Some(self.format().media_type().to_string())
+    }
+
+    /// Format of the response body,
+    /// `Format::Json` by default
+    #[inline]
+    fn format(&self) -> Format {
+        Format::default()
+    }
+
+    /// Locale to receive localized responses in,
+    /// e.g. `de-CH` or `fr-CH, fr;q=0.9`
+    ///
+    /// Sent as `Accept-Language` unless the
+    /// headers already contain one. See `Locale`
+    /// to set it for every request of a client
+    #[inline]
+    fn locale(&self) -> Option<String> {
+        None
+    }
+
+    /// Priority of the request
+    ///
+    /// Sent as `Priority` unless the headers already
+    /// contain one, for `PriorityQueue` to dispatch
+    /// urgent requests first. None by default
+    #[inline]
+    fn priority(&self) -> Option<Priority> {
+        None
+    }
+
+    /// Preferences of the request, e.g.
+    /// `Prefer::new().return_representation()`
+    ///
+    /// Sent as `Prefer` unless the headers already
+    /// contain one. See `PreferenceApplied` for the
+    /// ones honored and `Returned` to decode either
+    /// `return` preference
+    #[inline]
+    fn prefer(&self) -> Option<Prefer> {
+        None
+    }
+
+    /// Reject responses with fields unknown to `T`
+    ///
+    /// Catches schema drift, e.g. in CI against
+    /// sandbox APIs. Disabled by default unless
+    /// the `strict` feature is enabled
+    #[inline]
+    fn strict(&self) -> bool {
+        cfg!(feature = "strict")
+    }
+
+    /// JSON Schema to validate responses
+    /// against before deserializing them
+    ///
+    /// Violations fail with `Error::SchemaViolation`
+    #[cfg(feature = "json-schema")]
+    #[inline]
+    fn schema(&self) -> Option<&Schema> {
+        None
+    }
+
+    /// Keep the raw response body in
+    /// `FullResponse::raw` alongside `T`
+    ///
+    /// Gives access to fields not modeled in `T`,
+    /// or the exact payload for logging. Decoding
+    /// then bypasses `from_response()`
+    #[inline]
+    fn keep_raw(&self) -> bool {
+        false
+    }
+
+    /// Compress the body with the given encoding
+    ///
+    /// For APIs requiring compressed uploads,
+    /// see the `gzip` and `zstd` features
+    #[inline]
+    fn compress(&self) -> Option<Encoding> {
+        None
+    }
+
+    /// Decompress responses within wrapi
+    ///
+    /// With the `gzip` or `zstd` feature, the supported
+    /// encodings are advertised in `Accept-Encoding`
+    /// unless the headers already contain one, and
+    /// decoded before deserializing. Enabled by default
+    #[inline]
+    fn decompress(&self) -> bool {
+        true
+    }
+
+    /// Encodings to accept for the response,
+    /// sent as `Accept-Encoding`
+    ///
+    /// All supported ones if `decompress()` by default.
+    /// Encodings chosen explicitly are sent regardless,
+    /// e.g. `identity` for byte-exact bodies. See
+    /// `Metadata::content_encoding` for the one used
+    #[inline]
+    fn accept_encoding(&self) -> AcceptEncoding {
+        AcceptEncoding::default()
+    }
+
+    /// Maximum size of the response body in bytes
+    ///
+    /// Larger bodies fail with `Error::ResponseTooLarge`
+    /// while being read, before they're buffered whole,
+    /// and decompressed bodies are checked as well. See
+    /// `MaxResponseSize` to limit all responses of a
+    /// client. Unlimited by default
+    #[inline]
+    fn max_response_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Redirect policy of the request
+    ///
+    /// Redirects are then followed by wrapi rather
+    /// than the client, which must not follow them
+    /// itself, i.e. be built with reqwest's
+    /// `redirect::Policy::none()`. None by default,
+    /// leaving redirects to the client
+    #[inline]
+    fn redirect(&self) -> Option<Redirect> {
+        None
+    }
+
+    /// ETag to send as `If-Match`
+    ///
+    /// Makes the server reject the request with
+    /// `Error::PreconditionFailed` if the resource
+    /// changed since the ETag was captured, e.g.
+    /// with `send_with_headers::<_, ETag>`
+    #[inline]
+    fn if_match(&self) -> Option<ETag> {
+        None
+    }
+
+    /// Successful statuses the endpoint responds with
+    ///
+    /// Other successful responses fail with
+    /// `Error::ResponseError`, e.g. a `200 OK` where
+    /// `201 Created` was expected. Any by default
+    #[inline]
+    fn expected_statuses(&self) -> Option<Vec<StatusCode>> {
+        None
+    }
+
+    /// Check the request against API limits before
+    /// it's built, e.g. "at most 100 IDs per call"
+    /// or "body of at most 1 MB", see `body_size`
+    ///
+    /// Violations fail with `Error::Validation`
+    /// without a network round trip or using up
+    /// rate limits. Valid by default
+    #[inline]
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    /// Size of the JSON body in bytes,
+    /// 0 if the request has none
+    ///
+    /// As serialized by `serialize_body()`,
+    /// before any compression
+    fn body_size(&self) -> Result<usize, Error> {
+        Ok(self.serialize_body()?.map_or(0, |body| body.bytes.len()))
+    }
+
+    /// Fingerprint of the request's content
+    ///
+    /// Lets `send_if_changed` skip the request while
+    /// its content is unchanged, e.g. in sync-style
+    /// wrappers pushing the same configuration
+    /// repeatedly. Return `Fingerprints::of(self)` to
+    /// hash the body. None by default
+    #[inline]
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+
+    /// Called by `send` when the request was
+    /// rejected with `401 Unauthorized`
+    ///
+    /// Refresh the credentials returned by `bearer()`,
+    /// `basic_auth()` or `headers()` and return `true`
+    /// to have the request rebuilt and retried exactly
+    /// once. Returns `false` by default, passing
+    /// the 401 response through
+    #[inline]
+    fn on_unauthorized(&self) -> impl Future<Output = Result<bool, Error>> + MaybeSend {
+        async { Ok(false) }
+    }
+
     /// The body of the request
     ///
     /// Returns `Some(self)` by default
@@ -61,53 +383,503 @@ where
         Some(self)
     }
 
-    /// Build the request, adding all existing
-    /// attributes and parameters to the request
+    /// Body of the request read from a stream,
+    /// e.g. `BodyStream::from_reader(file).length(size)`
     ///
-    /// Exists so you can use the included builder
-    /// but also alter a request before executing it
-    fn build(&self, client: &Client, base_url: &str) -> RequestBuilder {
-        let mut request =
-            client.request(self.method(), format!("{}/{}", base_url, self.endpoint()));
+    /// Takes precedence over `body()`, which should
+    /// return `None` so nothing is serialized in
+    /// vain. Called on every build, so the stream
+    /// can be recreated for a second attempt. Not
+    /// part of `prepare()` or `dry_run()`. None
+    /// by default, and not available on `wasm32`
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn body_stream(&self) -> Option<BodyStream> {
+        None
+    }
+
+    /// Options of serializing `body()`, e.g.
+    /// `Serialization::new().skip_nulls(true)`
+    ///
+    /// Compact and emitting `null` fields by default
+    #[inline]
+    fn serialization(&self) -> Serialization {
+        Serialization::default()
+    }
+
+    /// The JSON body as sent, before compression
+    ///
+    /// Serializes `body()` with `serialization()` by
+    /// default. Exists so you can serialize with a
+    /// custom `Formatter` via `Body::json_with`, or
+    /// return bytes serialized once, e.g. when the
+    /// same request is retried
+    fn serialize_body(&self) -> Result<Option<Body>, Error> {
+        self.body()
+            .map(|body| self.serialization().json(body))
+            .transpose()
+    }
+
+    /// Prepare the request, adding all existing
+    /// attributes and parameters to it
+    ///
+    /// Produces a `BuiltRequest` which `build()`
+    /// then turns into a `reqwest::RequestBuilder`.
+    /// Exists so you can alter the request in a
+    /// client-independent way, e.g. to sign it
+    fn prepare(&self, base_url: &str) -> Result<BuiltRequest, Error> {
+        self.validate().map_err(Error::Validation)?;
+
+        let url = match (self.absolute_url(), self.base_url()) {
+            (Some(url), _) => url,
+            (None, base_url_override) => {
+                let base_url = base_url_override.as_deref().unwrap_or(base_url);
+                let url = join(base_url, &self.endpoint())?;
 
-        // Apply headers
-        if let Some(headers) = self.headers() {
-            request = request.headers(headers);
+                if !self.cross_origin() && url.origin() != Url::parse(base_url)?.origin() {
+                    return Err(Error::Builder(format!(
+                        "endpoint {} is not on the origin of the base URL",
+                        redact_url(url)
+                    )));
+                }
+
+                url
+            }
+        };
+
+        // Merge `params()` on top of the individual hooks
+        let mut params = Parameters {
+            headers: self.headers(),
+            query: self.query().map(|query| query.into_iter().collect()),
+            form: self.form().map(|form| form.into_iter().collect()),
+        };
+
+        if let Some(overrides) = self.params() {
+            params = params.merge(overrides);
+        }
+
+        let mut headers = params.headers.unwrap_or_default();
+
+        if let Some(cookies) = self.cookies() {
+            append_cookies(&mut headers, &cookie_header(cookies));
+        }
+
+        if let Some(idempotency) = self.idempotency() {
+            idempotency.apply(&mut headers);
+        }
+
+        if let Some(accept) = self.accept() {
+            if !headers.contains_key(ACCEPT) {
+                if let Ok(value) = HeaderValue::try_from(accept) {
+                    headers.insert(ACCEPT, value);
+                }
+            }
+        }
+
+        if let Some(locale) = self.locale() {
+            if !headers.contains_key(ACCEPT_LANGUAGE) {
+                if let Ok(value) = HeaderValue::try_from(locale) {
+                    headers.insert(ACCEPT_LANGUAGE, value);
+                }
+            }
+        }
+
+        if let Some(priority) = self.priority() {
+            priority.apply(&mut headers);
         }
 
-        // Apply query parameters
-        if let Some(query) = self.query() {
-            request = request.query(&query);
+        if let Some(prefer) = self.prefer() {
+            prefer.apply(&mut headers);
         }
 
-        // Apply form parameters
-        if let Some(form) = self.form() {
-            request = request.form(&form);
+        let accept_encoding = match self.accept_encoding() {
+            AcceptEncoding::Supported if !self.decompress() => None,
+            accept_encoding => accept_encoding.value(),
+        };
+
+        if let Some(accept_encoding) = accept_encoding {
+            if !headers.contains_key(ACCEPT_ENCODING) {
+                if let Ok(value) = HeaderValue::try_from(accept_encoding) {
+                    headers.insert(ACCEPT_ENCODING, value);
+                }
+            }
+        }
+
+        if let Some(etag) = self.if_match() {
+            if let Ok(value) = HeaderValue::try_from(etag.as_str()) {
+                headers.insert(IF_MATCH, value);
+            }
         }
 
         // Apply bearer token
         if let Some(bearer) = self.bearer() {
-            request = request.bearer_auth(bearer);
+            if let Ok(mut value) = HeaderValue::try_from(format!("Bearer {}", bearer)) {
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
         }
 
         // Apply basic auth
         if let Some((username, password)) = self.basic_auth() {
-            request = request.basic_auth(username, password);
+            headers.insert(AUTHORIZATION, basic_auth(&username, password.as_deref()));
         }
 
-        // Apply body
-        if let Some(body) = self.body() {
-            request = request.json(body);
+        // Apply body, which takes precedence over form parameters
+        let mut body = match (self.serialize_body()?, params.form) {
+            (Some(body), _) => Some(body),
+            (None, Some(form)) => Some(Body::form(&form)),
+            (None, None) => None,
+        };
+
+        if let Some(body) = &mut body {
+            if !headers.contains_key(CONTENT_TYPE) {
+                headers.insert(CONTENT_TYPE, body.format.content_type());
+            }
+
+            if let Some(encoding) = self.compress() {
+                if let Ok(compressed) = encoding.encode(&body.bytes) {
+                    body.bytes = compressed.into();
+                    headers.insert(
+                        CONTENT_ENCODING,
+                        HeaderValue::from_static(encoding.as_str()),
+                    );
+                }
+            }
         }
 
-        request
+        Ok(BuiltRequest {
+            method: self.method(),
+            url,
+            headers,
+            query: params.query.unwrap_or_default(),
+            query_encoding: self.query_encoding(),
+            body,
+        })
+    }
+
+    /// Build the request from `prepare()`, sending
+    /// the body from `body_stream()` if any
+    ///
+    /// Exists so you can use the included builder
+    /// but also alter a request before executing it.
+    ///
+    /// If preparing fails, so does `RequestBuilder::build`,
+    /// but with a generic builder error as this can't
+    /// carry the original one. `dry_run` and `send`
+    /// return the original
+    fn build(&self, client: &Client, base_url: &str) -> RequestBuilder {
+        // An empty URL fails when building
+        try_build(self, client, base_url).unwrap_or_else(|_| client.request(self.method(), ""))
+    }
+
+    /// Build the request without sending it
+    ///
+    /// Lets tests assert exactly what would be sent
+    /// and tools preview mutations. Middleware of
+    /// a `WrapiClient` is not applied
+    fn dry_run(&self, client: &Client, base_url: &str) -> Result<BuiltRequest, Error> {
+        let request = try_build(self, client, base_url)?.build()?;
+
+        Ok(BuiltRequest::from_request(&request, self.query_encoding()))
     }
 
     /// Build and execute the request
-    fn send(&self, client: &Client, base_url: &str) -> impl Future<Output = Result<T, Error>> {
-        let request = self.build(client, base_url);
+    /// through the given transport
+    ///
+    /// Accepts a `reqwest::Client`, a `WrapiClient`
+    /// or any other `Transport` implementation
+    ///
+    /// Retries once after a `401 Unauthorized`
+    /// if `on_unauthorized()` refreshed credentials
+    ///
+    /// Errors carry the method, URL and type
+    /// of the request, see `Error::context`
+    fn send<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move { Ok(self.send_full(client, base_url).await?.body) }
+    }
 
-        async move { self.exec(request).await }
+    /// Like `send`, with the context of the call
+    /// visible to middleware as `Next::extensions`
+    ///
+    /// Carries values such as a tenant ID or trace
+    /// context to the auth and logging layers of a
+    /// `WrapiClient` for a single call. See
+    /// `WithExtensions` for the other methods
+    fn send_with_context<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+        extensions: &Extensions,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move {
+            self.send(&WithExtensions::new(client, extensions), base_url)
+                .await
+        }
+    }
+
+    /// Like `send`, but also return the
+    /// metadata of the response
+    fn send_full<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<FullResponse<T>, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move {
+            let mut url = None;
+
+            let result: Result<FullResponse<T>, Error> = async {
+                let idempotency = self.idempotency();
+                let start = Instant::now();
+
+                let response =
+                    execute(self, client, base_url, idempotency.as_ref(), None, &mut url).await?;
+
+                let mut metadata = Metadata::new(&response, start);
+                metadata.idempotency_key =
+                    idempotency.map(|idempotency| idempotency.key().to_string());
+
+                if !self.keep_raw() {
+                    let body = self.from_response(response).await?;
+                    metadata.timing.total = start.elapsed();
+
+                    return Ok(FullResponse {
+                        body,
+                        metadata,
+                        raw: None,
+                    });
+                }
+
+                let response = self.check_response(response).await?;
+                check_content_type(self.accept(), &response)?;
+
+                let raw = read_body(response, self.decompress(), self.max_response_size()).await?;
+                let body = decode_body(self, &raw)?;
+                metadata.timing.total = start.elapsed();
+
+                Ok(FullResponse {
+                    body,
+                    metadata,
+                    raw: Some(raw),
+                })
+            }
+            .await;
+
+            result.map_err(|error| with_context(self, base_url, url, None, error))
+        }
+    }
+
+    /// Like `send`, but keep the body to deserialize
+    /// types borrowing from it with `BorrowedBody::parse`
+    ///
+    /// For high-throughput wrappers, avoiding an
+    /// allocation per string of large responses
+    fn send_borrowed<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<BorrowedBody, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move {
+            let mut url = None;
+
+            let result: Result<BorrowedBody, Error> = async {
+                let idempotency = self.idempotency();
+                let start = Instant::now();
+
+                let response =
+                    execute(self, client, base_url, idempotency.as_ref(), None, &mut url).await?;
+
+                let mut metadata = Metadata::new(&response, start);
+                metadata.idempotency_key =
+                    idempotency.map(|idempotency| idempotency.key().to_string());
+
+                let response = self.check_response(response).await?;
+                check_content_type(self.accept(), &response)?;
+
+                let bytes =
+                    read_body(response, self.decompress(), self.max_response_size()).await?;
+
+                #[cfg(feature = "json-schema")]
+                if let Some(schema) = self.schema() {
+                    schema.validate_bytes(&bytes)?;
+                }
+
+                // Until read, as parsing is up to the caller
+                metadata.timing.total = start.elapsed();
+
+                Ok(BorrowedBody::new(metadata, bytes, self.strict()))
+            }
+            .await;
+
+            result.map_err(|error| with_context(self, base_url, url, None, error))
+        }
+    }
+
+    /// Like `send`, but skip the request if its
+    /// `fingerprint()` is the last one successfully
+    /// sent to its endpoint, as recorded in `fingerprints`
+    ///
+    /// Returns `None` if skipped. Requests
+    /// without a fingerprint are always sent
+    fn send_if_changed<C>(
+        &self,
+        client: &C,
+        base_url: &str,
+        fingerprints: &Fingerprints,
+    ) -> impl Future<Output = Result<Option<T>, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move {
+            let Some(fingerprint) = self.fingerprint() else {
+                return self.send(client, base_url).await.map(Some);
+            };
+
+            let built = self.prepare(base_url)?;
+            let url = built.full_url();
+
+            if fingerprints.is_unchanged(&built.method, &url, &fingerprint) {
+                return Ok(None);
+            }
+
+            let body = self.send(client, base_url).await?;
+            fingerprints.record(built.method, url, fingerprint);
+
+            Ok(Some(body))
+        }
+    }
+
+    /// Like `send`, but also extract typed
+    /// data from the response headers
+    ///
+    /// E.g. `let (user, rate): (User, RateLimitInfo) = ..`
+    fn send_with_headers<C, H>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<(T, H), Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+        H: FromHeaders + MaybeSend,
+    {
+        async move {
+            let response = self.send_full(client, base_url).await?;
+            let url = Some(redact_url(response.metadata.url.clone()));
+            let headers = response
+                .metadata
+                .extract()
+                .map_err(|error| with_context(self, base_url, url, None, error))?;
+
+            Ok((response.body, headers))
+        }
+    }
+
+    /// Send the request as `HEAD` and extract
+    /// typed data from the response headers
+    ///
+    /// The body is skipped, e.g. for existence
+    /// or size checks with `ContentInfo`
+    fn send_head<C, H>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<H, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+        H: FromHeaders + MaybeSend,
+    {
+        async move {
+            let mut url = None;
+
+            let result: Result<H, Error> = async {
+                let idempotency = self.idempotency();
+                let response = execute(
+                    self,
+                    client,
+                    base_url,
+                    idempotency.as_ref(),
+                    Some(Method::HEAD),
+                    &mut url,
+                );
+
+                H::from_headers(self.check_response(response.await?).await?.headers())
+            }
+            .await;
+
+            result.map_err(|error| with_context(self, base_url, url, Some(Method::HEAD), error))
+        }
+    }
+
+    /// Send the request as `OPTIONS` and extract
+    /// typed data from the response headers
+    ///
+    /// The body is skipped, e.g. for the
+    /// allowed methods with `Allow`
+    fn send_options<C, H>(
+        &self,
+        client: &C,
+        base_url: &str,
+    ) -> impl Future<Output = Result<H, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+        H: FromHeaders + MaybeSend,
+    {
+        async move {
+            let mut url = None;
+
+            let result: Result<H, Error> = async {
+                let idempotency = self.idempotency();
+                let response = execute(
+                    self,
+                    client,
+                    base_url,
+                    idempotency.as_ref(),
+                    Some(Method::OPTIONS),
+                    &mut url,
+                );
+
+                H::from_headers(self.check_response(response.await?).await?.headers())
+            }
+            .await;
+
+            result.map_err(|error| with_context(self, base_url, url, Some(Method::OPTIONS), error))
+        }
+    }
+
+    /// Execute a request builder through the given
+    /// transport and deserialize the response into `T`
+    ///
+    /// Like `exec`, but the builder's own client
+    /// is bypassed in favor of `client`
+    fn exec_via<C>(
+        &self,
+        client: &C,
+        builder: RequestBuilder,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend
+    where
+        C: Transport + ?Sized,
+    {
+        async move {
+            let request = builder.build()?;
+            let response = client.execute(request).await?;
+
+            self.from_response(response).await
+        }
     }
 
     /// Execute the request and deserialize
@@ -116,11 +888,11 @@ where
     /// Can be used to pass your custom builder
     /// while still utilizing the built-in
     /// parsing and type-mapping
-    fn exec(&self, builder: RequestBuilder) -> impl Future<Output = Result<T, Error>> {
+    fn exec(&self, builder: RequestBuilder) -> impl Future<Output = Result<T, Error>> + MaybeSend {
         async move {
-            let response = builder.send().await.map_err(|_| Error::ClientError)?;
+            let response = builder.send().await?;
 
-            Ok(self.from_response(response).await?)
+            self.from_response(response).await
         }
     }
 
@@ -130,48 +902,357 @@ where
     /// Can be used to pass your custom builder
     /// while still utilizing the built-in
     /// parsing and type-mapping
-    fn exec_opt(&self, builder: RequestBuilder) -> impl Future<Output = Result<Option<T>, Error>> {
+    fn exec_opt(
+        &self,
+        builder: RequestBuilder,
+    ) -> impl Future<Output = Result<Option<T>, Error>> + MaybeSend {
         async move {
-            let response = builder.send().await.map_err(|_| Error::ClientError)?;
+            let response = builder.send().await?;
 
-            Ok(self.from_response_opt(response).await?)
+            self.from_response_opt(response).await
         }
     }
 
     /// Deserialize `reqwest::Response` into `T`
-    fn from_response(&self, response: Response) -> impl Future<Output = Result<T, Error>> {
+    ///
+    /// Fails with `Error::UnexpectedContentType` if
+    /// the response isn't of the `accept()` media type,
+    /// and on unknown fields if `strict()`
+    #[allow(clippy::wrong_self_convention)]
+    fn from_response(
+        &self,
+        response: Response,
+    ) -> impl Future<Output = Result<T, Error>> + MaybeSend {
         async move {
-            Ok(self
-                .check_response(response)
-                .await?
-                .json::<T>()
-                .await
-                .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?)
+            let response = self.check_response(response).await?;
+
+            check_content_type(self.accept(), &response)?;
+
+            let bytes = read_body(response, self.decompress(), self.max_response_size()).await?;
+
+            decode_body(self, &bytes)
         }
     }
 
     /// Deserialize `reqwest::Response` into `Option<T>`
+    #[allow(clippy::wrong_self_convention)]
     fn from_response_opt(
         &self,
         response: Response,
-    ) -> impl Future<Output = Result<Option<T>, Error>> {
-        async move { Ok(self.check_response(response).await?.json::<T>().await.ok()) }
+    ) -> impl Future<Output = Result<Option<T>, Error>> + MaybeSend {
+        async move {
+            let response = self.check_response(response).await?;
+
+            match read_body(response, self.decompress(), self.max_response_size()).await {
+                Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+                Err(_) => Ok(None),
+            }
+        }
     }
 
     /// Deserialize `reqwest::Response` into
     /// `Error::ResponseError` if the response
-    /// was erroneous
-    fn check_response(&self, response: Response) -> impl Future<Output = Result<Response, Error>> {
+    /// was erroneous or unexpected,
+    /// `Error::PreconditionFailed` on
+    /// `412 Precondition Failed`, or
+    /// `Error::RateLimited` on `429`
+    fn check_response(
+        &self,
+        response: Response,
+    ) -> impl Future<Output = Result<Response, Error>> + MaybeSend {
         async move {
-            if let Err(_) = response.error_for_status_ref() {
-                return Err(Error::ResponseError((
-                    response.status(),
-                    response.json::<Value>().await.ok(),
-                ))
-                .into());
+            let status = response.status();
+
+            let expected = match self.expected_statuses() {
+                Some(statuses) => statuses.contains(&status),
+                // Redirects left unfollowed by the policy
+                None => !(status.is_redirection() && self.redirect().is_some()),
+            };
+
+            if response.error_for_status_ref().is_ok() && expected {
+                return Ok(response);
+            }
+
+            let request_id = request_id(&response);
+            let headers = response.headers().clone();
+
+            let body = read_body(response, self.decompress(), self.max_response_size())
+                .await
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+
+            Err(Error::from_status(status, &headers, body, request_id))
+        }
+    }
+}
+
+/// Builder of `request` from `prepare()`, sending
+/// the body from `body_stream()` if any
+pub(crate) fn try_build<R, T>(
+    request: &R,
+    client: &Client,
+    base_url: &str,
+) -> Result<RequestBuilder, Error>
+where
+    R: Request<T> + ?Sized,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    let built = request.prepare(base_url)?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(stream) = request.body_stream() {
+        let mut built = built;
+        built.body = None;
+        let body = stream.apply(&mut built.headers);
+
+        return Ok(built.into_builder(client).body(body));
+    }
+
+    Ok(built.into_builder(client))
+}
+
+/// Attach the method, redacted URL and
+/// type name of `request` to `error`
+///
+/// `url` is the one the request was sent to, if it
+/// got that far, redacted. Otherwise it's derived from
+/// the endpoint without the query, as building failed
+fn with_context<R, T>(
+    request: &R,
+    base_url: &str,
+    url: Option<Url>,
+    method: Option<Method>,
+    error: Error,
+) -> Error
+where
+    R: Request<T> + ?Sized,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    let url = match url {
+        Some(url) => url,
+        None => {
+            let url = match (request.absolute_url(), request.base_url()) {
+                (Some(url), _) => Ok(url),
+                (None, Some(base_url)) => join(&base_url, &request.endpoint()),
+                (None, None) => join(base_url, &request.endpoint()),
+            };
+
+            match url {
+                Ok(url) => redact_url(url),
+                Err(_) => return error,
+            }
+        }
+    };
+
+    // Without module path and generics
+    let name = std::any::type_name::<R>();
+    let name = name.split('<').next().unwrap_or(name);
+
+    error.with_context(RequestContext {
+        method: method.unwrap_or_else(|| request.method()),
+        url,
+        endpoint: name.rsplit("::").next().unwrap_or(name),
+    })
+}
+
+/// Build `request` into a `reqwest::Request`,
+/// pinning the idempotency key across attempts
+fn build_request<R, T>(
+    request: &R,
+    base_url: &str,
+    idempotency: Option<&Idempotency>,
+) -> Result<reqwest::Request, Error>
+where
+    R: Request<T> + ?Sized,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    // Before building, which can't carry the error
+    request.validate().map_err(Error::Validation)?;
+
+    let mut built = try_build(request, builder_client(), base_url)?.build()?;
+
+    if let Some(idempotency) = idempotency {
+        idempotency.apply(built.headers_mut());
+    }
+
+    Ok(built)
+}
+
+/// Build and execute `request`, optionally under a
+/// different method, retrying once after a `401`
+/// if `on_unauthorized()` refreshed credentials.
+/// Records the redacted URL in `url` once built
+async fn execute<R, T, C>(
+    request: &R,
+    client: &C,
+    base_url: &str,
+    idempotency: Option<&Idempotency>,
+    method: Option<Method>,
+    url: &mut Option<Url>,
+) -> Result<Response, Error>
+where
+    R: Request<T> + ?Sized,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+    C: Transport + ?Sized,
+{
+    let build = || {
+        let mut built = build_request(request, base_url, idempotency)?;
+
+        if let Some(method) = &method {
+            *built.method_mut() = method.clone();
+        }
+
+        Ok::<_, Error>(built)
+    };
+
+    let mut built = build()?;
+    *url = Some(redact_url(built.url().clone()));
+    let mut previous = built.try_clone();
+    let mut response = client.execute(built).await?;
+
+    if response.status() == StatusCode::UNAUTHORIZED && request.on_unauthorized().await? {
+        built = build()?;
+        previous = built.try_clone();
+        response = client.execute(built).await?;
+    }
+
+    let Some(redirect) = request.redirect() else {
+        return Ok(response);
+    };
+
+    let mut visited: Vec<Url> = previous
+        .iter()
+        .map(|request| request.url().clone())
+        .collect();
+
+    let origin = visited.first().map(Url::origin);
+
+    let mut cross_origin = Extensions::new();
+    cross_origin.insert(CrossOriginRedirect);
+
+    for _ in 0..redirect.max() {
+        let Some(next) = previous.and_then(|previous| redirect.follow(previous, &response)) else {
+            break;
+        };
+
+        if visited.contains(next.url()) {
+            break;
+        }
+
+        visited.push(next.url().clone());
+        previous = next.try_clone();
+
+        // Kept from credentials the middleware adds
+        response = match redirect.strips_auth() && Some(next.url().origin()) != origin {
+            true => client.execute_with(next, &cross_origin).await?,
+            false => client.execute(next).await?,
+        };
+    }
+
+    Ok(response)
+}
+
+/// Fail with `Error::UnexpectedContentType` if the
+/// response isn't of the accepted media type
+fn check_content_type(accept: Option<String>, response: &Response) -> Result<(), Error> {
+    let (Some(accept), Some(content_type)) = (accept, response.headers().get(CONTENT_TYPE)) else {
+        return Ok(());
+    };
+
+    let content_type = String::from_utf8_lossy(content_type.as_bytes());
+
+    if !media_type_matches(&accept, &content_type) {
+        return Err(Error::UnexpectedContentType {
+            expected: accept,
+            actual: content_type.into_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Deserialize a response body for `request`,
+/// validating it against its schema first
+///
+/// Empty bodies, e.g. of `204 No Content`, are
+/// deserialized as `null`, so `T` can be `()`,
+/// an `Option` or `NoContent`
+fn decode_body<R, T>(request: &R, bytes: &[u8]) -> Result<T, Error>
+where
+    R: Request<T> + ?Sized,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+{
+    match request.format() {
+        Format::Json => {}
+        #[cfg(feature = "csv")]
+        Format::Csv(csv) => return csv.decode(bytes, request.strict()),
+        Format::Text => return decode::text(bytes),
+    }
+
+    if bytes.trim_ascii().is_empty() {
+        return decode::json(b"null", false);
+    }
+
+    #[cfg(feature = "json-schema")]
+    if let Some(schema) = request.schema() {
+        schema.validate_bytes(bytes)?;
+    }
+
+    decode::owned(bytes, request.strict())
+}
+
+/// Read the body of `response`, decoding it
+/// if it was compressed with a supported encoding
+async fn read_body(
+    response: Response,
+    decompress: bool,
+    limit: Option<usize>,
+) -> Result<Bytes, Error> {
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_name)
+        .filter(|_| decompress);
+
+    let limit = effective_limit(&response, limit);
+
+    let bytes = match limit {
+        Some(limit) => {
+            check_length(&response, limit)?;
+
+            let mut body = Vec::new();
+            let mut chunks = response.bytes_stream();
+
+            while let Some(chunk) = chunks.next().await {
+                let chunk = chunk?;
+
+                if body.len() + chunk.len() > limit {
+                    return Err(Error::ResponseTooLarge { limit });
+                }
+
+                body.extend_from_slice(&chunk);
             }
 
-            Ok(response)
+            Bytes::from(body)
         }
+        None => response.bytes().await?,
+    };
+
+    let Some(encoding) = encoding else {
+        return Ok(bytes);
+    };
+
+    // Bounded, so compressed bodies can't
+    // inflate far beyond the limit
+    let decoded = match limit {
+        Some(limit) => encoding.decode_limited(&bytes, limit),
+        None => encoding.decode(&bytes),
+    }
+    .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+    match limit {
+        Some(limit) if decoded.len() > limit => Err(Error::ResponseTooLarge { limit }),
+        _ => Ok(Bytes::from(decoded)),
     }
 }
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use http::{HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use uuid::Uuid;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Header used for request IDs by default
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Request ID of an exchange, stored in
+/// the extensions of the response
+///
+/// The ID echoed by the server if present,
+/// otherwise the one that was sent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestIdValue(pub String);
+
+#[cfg(not(target_arch = "wasm32"))]
+type Generator = dyn Fn() -> String + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Generator = dyn Fn() -> String;
+
+/// Middleware injecting a request/correlation ID
+/// header into every outgoing request
+///
+/// IDs are UUID v4 by default. Use `generator`
+/// to take them from elsewhere, e.g. the current
+/// tracing span. Requests already carrying the
+/// header are left untouched.
+///
+/// The server's echoed ID is surfaced in
+/// `Metadata::request_id` and response errors
+#[derive(Clone)]
+pub struct RequestId {
+    header: HeaderName,
+    generator: Arc<Generator>,
+}
+
+impl RequestId {
+    /// Inject generated IDs as `X-Request-Id`
+    pub fn new() -> Self {
+        Self {
+            header: X_REQUEST_ID,
+            generator: Arc::new(|| Uuid::new_v4().to_string()),
+        }
+    }
+
+    /// Inject IDs under a different header
+    /// name, e.g. `X-Correlation-Id`
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Generate IDs with a custom function
+    pub fn generator<F>(mut self, generator: F) -> Self
+    where
+        F: Fn() -> String + MaybeSend + MaybeSync + 'static,
+    {
+        self.generator = Arc::new(generator);
+        self
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for RequestId {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            if !request.headers().contains_key(&self.header) {
+                if let Ok(value) = HeaderValue::try_from((self.generator)()) {
+                    request.headers_mut().insert(self.header.clone(), value);
+                }
+            }
+
+            let sent = request
+                .headers()
+                .get(&self.header)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+
+            let response = next.run(request).await?;
+
+            Ok(record(response, &self.header, sent))
+        })
+    }
+}
+
+/// Store the echoed, or else the sent,
+/// request ID in the response extensions
+#[cfg(not(target_arch = "wasm32"))]
+fn record(mut response: Response, header: &HeaderName, sent: Option<String>) -> Response {
+    let echoed = response
+        .headers()
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    if let Some(id) = echoed.or(sent) {
+        response.extensions_mut().insert(RequestIdValue(id));
+    }
+
+    response
+}
+
+/// Responses have no extensions on `wasm32`
+#[cfg(target_arch = "wasm32")]
+fn record(response: Response, _header: &HeaderName, _sent: Option<String>) -> Response {
+    response
+}
+
+/// Request ID recorded by `RequestId` for a response
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn request_id(response: &Response) -> Option<String> {
+    response
+        .extensions()
+        .get::<RequestIdValue>()
+        .map(|id| id.0.clone())
+}
+
+/// Request ID recorded by `RequestId` for a response
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn request_id(_response: &Response) -> Option<String> {
+    None
+}
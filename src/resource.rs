@@ -0,0 +1,92 @@
+//! Generic CRUD resources
+//!
+//! Implement `Resource` for a REST resource with the
+//! usual shape, a collection at `path()` and items
+//! below it by ID, and wrapi provides the requests
+//! to get, list, create, update and delete them
+
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    crud::{Create, Delete, Get, Update},
+    marker::{MaybeSend, MaybeSync},
+    pagination::{PageInfo, Paginator},
+};
+
+/// Paginator over the pages of a `Resource`
+pub type List<R> = Paginator<fn(Option<&str>) -> Get<<R as Resource>::Page>>;
+
+/// A REST resource
+///
+/// E.g. for `/users` and `/users/{id}`, with `Page`
+/// being `Vec<User>` for unpaginated collections or
+/// a page type implementing `PageInfo`. Override the
+/// provided requests for endpoints deviating from
+/// the shape, e.g. with a `PATCH` update
+pub trait Resource {
+    /// ID of an item in its path
+    type Id: fmt::Display;
+    type Item: DeserializeOwned + MaybeSend + MaybeSync;
+    /// Response of listing the collection
+    type Page: PageInfo<Item = Self::Item> + DeserializeOwned + MaybeSend + MaybeSync;
+    type CreatePayload: Serialize + MaybeSend + MaybeSync;
+    type UpdatePayload: Serialize + MaybeSend + MaybeSync;
+
+    /// Path of the collection, e.g. `/users`
+    fn path() -> String;
+
+    /// Path of the item `id`, `{path}/{id}` by default
+    #[inline]
+    fn item_path(id: &Self::Id) -> String {
+        format!("{}/{}", Self::path().trim_end_matches('/'), id)
+    }
+
+    /// Query parameter sending the cursor
+    /// of the next page, `cursor` by default
+    #[inline]
+    fn cursor_param() -> String {
+        "cursor".to_string()
+    }
+
+    /// Get the item `id`
+    fn get(id: &Self::Id) -> Get<Self::Item> {
+        Get::new(Self::item_path(id))
+    }
+
+    /// Get the page of the collection at `cursor`,
+    /// `None` for the first one
+    fn list_page(cursor: Option<&str>) -> Get<Self::Page> {
+        let request = Get::new(Self::path());
+
+        match cursor {
+            Some(cursor) => request.query(Self::cursor_param(), cursor),
+            None => request,
+        }
+    }
+
+    /// List the whole collection, following
+    /// the `next_cursor()` of its pages
+    fn list() -> List<Self> {
+        Paginator::new(Self::list_page as fn(Option<&str>) -> Get<Self::Page>)
+    }
+
+    /// Create an item from `payload`
+    fn create(payload: Self::CreatePayload) -> Create<Self::CreatePayload, Self::Item> {
+        Create::new(Self::path(), payload)
+    }
+
+    /// Replace the item `id` with `payload`
+    fn update(
+        id: &Self::Id,
+        payload: Self::UpdatePayload,
+    ) -> Update<Self::UpdatePayload, Self::Item> {
+        Update::new(Self::item_path(id), payload)
+    }
+
+    /// Delete the item `id`
+    fn delete(id: &Self::Id) -> Delete {
+        Delete::new(Self::item_path(id))
+    }
+}
@@ -0,0 +1,151 @@
+use bytes::Bytes;
+use http::{header::CONTENT_ENCODING, HeaderMap, StatusCode, Version};
+use reqwest::{Response, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use web_time::Instant;
+
+use super::{
+    decode, error::Error, headers::FromHeaders, links::Links, request_id::request_id,
+    timing::Timing,
+};
+
+/// Metadata of a received response
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub url: Url,
+    /// HTTP version the response was served over,
+    /// e.g. `HTTP/2` when multiplexed. `None` on
+    /// `wasm32`, where the browser doesn't tell
+    pub version: Option<Version>,
+    /// Idempotency key sent with the request
+    pub idempotency_key: Option<String>,
+    /// Request ID of the exchange, see `RequestId`
+    pub request_id: Option<String>,
+    /// Time spent queuing, waiting for
+    /// the response and reading it
+    pub timing: Timing,
+}
+
+impl Metadata {
+    /// Metadata of `response` to a
+    /// request sent at `start`
+    pub(crate) fn new(response: &Response, start: Instant) -> Self {
+        Self {
+            status: response.status(),
+            headers: response.headers().clone(),
+            url: response.url().clone(),
+            version: version(response),
+            idempotency_key: None,
+            request_id: request_id(response),
+            timing: Timing::new(response, start),
+        }
+    }
+
+    /// Extract typed data from the headers
+    pub fn extract<H: FromHeaders>(&self) -> Result<H, Error> {
+        H::from_headers(&self.headers)
+    }
+
+    /// Whether the response was served over a
+    /// multiplexed connection, i.e. HTTP/2 or later,
+    /// where concurrent requests share a connection
+    pub fn multiplexed(&self) -> bool {
+        self.version
+            .is_some_and(|version| version >= Version::HTTP_2)
+    }
+
+    /// Encoding the server compressed the body with,
+    /// from `Content-Encoding`, e.g. `gzip`
+    pub fn content_encoding(&self) -> Option<&str> {
+        self.headers
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|encoding| !encoding.is_empty() && *encoding != "identity")
+    }
+}
+
+/// HTTP version of `response`, unknown on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+fn version(response: &Response) -> Option<Version> {
+    Some(response.version())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn version(_response: &Response) -> Option<Version> {
+    None
+}
+
+/// A deserialized response alongside its metadata
+#[derive(Clone, Debug)]
+pub struct FullResponse<T> {
+    pub body: T,
+    pub metadata: Metadata,
+    /// Raw body, if the request set `keep_raw()`
+    pub raw: Option<Bytes>,
+}
+
+impl<T> FullResponse<T> {
+    /// Raw body as untyped JSON
+    pub fn raw_json(&self) -> Option<Value> {
+        serde_json::from_slice(self.raw.as_ref()?).ok()
+    }
+
+    /// Links from the `Link` header and, if the
+    /// request set `keep_raw()`, from the body,
+    /// resolved against the response URL
+    pub fn links(&self) -> Links {
+        let mut links = self.metadata.extract::<Links>().unwrap_or_default();
+
+        if let Some(body) = self.raw_json() {
+            links.extend(Links::from_body(&body));
+        }
+
+        links.resolve(&self.metadata.url)
+    }
+}
+
+/// A response body kept to deserialize
+/// types borrowing from it
+///
+/// Returned by `Request::send_borrowed`. Parsing into
+/// types with `&str`, `Cow<str>` marked `#[serde(borrow)]`
+/// or `&RawValue` fields avoids allocating each string,
+/// e.g. of very large arrays. Parsed values can't
+/// outlive the body
+#[derive(Clone, Debug)]
+pub struct BorrowedBody {
+    pub metadata: Metadata,
+    bytes: Bytes,
+    strict: bool,
+}
+
+impl BorrowedBody {
+    pub(crate) fn new(metadata: Metadata, bytes: Bytes, strict: bool) -> Self {
+        Self {
+            metadata,
+            bytes,
+            strict,
+        }
+    }
+
+    /// Deserialize the body into `T`,
+    /// borrowing from it
+    ///
+    /// `&str` fields fail for strings
+    /// containing escapes, use `Cow<str>`
+    pub fn parse<'a, T: Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        decode::json(&self.bytes, self.strict)
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+}
@@ -0,0 +1,212 @@
+//! Async runtime abstraction
+//!
+//! Timers used by wrapi's async machinery go through
+//! this module so no particular runtime is required.
+//! The implementation is picked by target and feature,
+//! in order:
+//!
+//! - `wasm32`: `gloo_timers`, backed by `setTimeout`
+//! - `tokio` (default): `tokio::time::sleep`
+//! - `async-io`: `async_io::Timer`, for async-std and smol
+//! - otherwise: a runtime-agnostic timer thread,
+//!   shared by all sleeps
+//!
+//! Note that reqwest's native client always needs a
+//! tokio reactor, on other runtimes pair this with a
+//! custom `Transport`
+//!
+//! Clocks are read through `web_time`, as those of
+//! std panic on `wasm32-unknown-unknown`
+
+use std::time::{Duration, SystemTime};
+
+/// Current time
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> SystemTime {
+    SystemTime::now()
+}
+
+/// Current time, from `Date.now()`
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> SystemTime {
+    let since_epoch = web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    std::time::UNIX_EPOCH + since_epoch
+}
+
+/// Wait until `duration` has elapsed
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Wait until `duration` has elapsed
+#[cfg(all(not(target_arch = "wasm32"), feature = "tokio"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Wait until `duration` has elapsed
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "tokio"),
+    feature = "async-io"
+))]
+pub async fn sleep(duration: Duration) {
+    async_io::Timer::after(duration).await;
+}
+
+/// Wait until `duration` has elapsed
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "tokio"),
+    not(feature = "async-io")
+))]
+pub async fn sleep(duration: Duration) {
+    thread_timer::Sleep::new(duration).await;
+}
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "tokio"),
+    not(feature = "async-io")
+))]
+mod thread_timer {
+    use std::{
+        cmp::Reverse,
+        collections::BinaryHeap,
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Condvar, Mutex, OnceLock},
+        task::{Context, Poll, Waker},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Timer woken by the shared timer thread
+    pub struct Sleep {
+        /// None if too far out to ever elapse
+        deadline: Option<Instant>,
+        waker: Option<Arc<Mutex<Waker>>>,
+    }
+
+    impl Sleep {
+        pub fn new(duration: Duration) -> Self {
+            Self {
+                deadline: Instant::now().checked_add(duration),
+                waker: None,
+            }
+        }
+    }
+
+    impl Future for Sleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let Some(deadline) = self.deadline else {
+                return Poll::Pending;
+            };
+
+            if Instant::now() >= deadline {
+                return Poll::Ready(());
+            }
+
+            match &self.waker {
+                Some(waker) => waker.lock().unwrap().clone_from(cx.waker()),
+                None => {
+                    let waker = Arc::new(Mutex::new(cx.waker().clone()));
+                    timer().schedule(deadline, waker.clone());
+                    self.waker = Some(waker);
+                }
+            }
+
+            Poll::Pending
+        }
+    }
+
+    struct Entry {
+        deadline: Instant,
+        waker: Arc<Mutex<Waker>>,
+    }
+
+    impl PartialEq for Entry {
+        fn eq(&self, other: &Self) -> bool {
+            self.deadline == other.deadline
+        }
+    }
+
+    impl Eq for Entry {}
+
+    impl PartialOrd for Entry {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Entry {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.deadline.cmp(&other.deadline)
+        }
+    }
+
+    /// Deadlines of all pending sleeps, earliest
+    /// first, served by a single thread
+    struct Timer {
+        deadlines: Mutex<BinaryHeap<Reverse<Entry>>>,
+        changed: Condvar,
+    }
+
+    impl Timer {
+        fn schedule(&self, deadline: Instant, waker: Arc<Mutex<Waker>>) {
+            self.deadlines
+                .lock()
+                .unwrap()
+                .push(Reverse(Entry { deadline, waker }));
+            self.changed.notify_one();
+        }
+
+        /// Wake sleeps as their deadlines pass
+        fn run(&self) {
+            let mut deadlines = self.deadlines.lock().unwrap();
+
+            loop {
+                let now = Instant::now();
+
+                while deadlines
+                    .peek()
+                    .is_some_and(|Reverse(entry)| entry.deadline <= now)
+                {
+                    if let Some(Reverse(entry)) = deadlines.pop() {
+                        entry.waker.lock().unwrap().wake_by_ref();
+                    }
+                }
+
+                deadlines = match deadlines.peek() {
+                    Some(Reverse(entry)) => {
+                        let timeout = entry.deadline - now;
+                        self.changed.wait_timeout(deadlines, timeout).unwrap().0
+                    }
+                    None => self.changed.wait(deadlines).unwrap(),
+                };
+            }
+        }
+    }
+
+    fn timer() -> &'static Timer {
+        static TIMER: OnceLock<Timer> = OnceLock::new();
+
+        TIMER.get_or_init(|| {
+            thread::Builder::new()
+                .name("wrapi-timer".to_string())
+                .spawn(|| timer().run())
+                .expect("timer thread should spawn");
+
+            Timer {
+                deadlines: Mutex::new(BinaryHeap::new()),
+                changed: Condvar::new(),
+            }
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use serde::de::DeserializeOwned;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::BoxFuture,
+    pipeline::Step,
+    request::{try_build, Request},
+    transport::{builder_client, Transport},
+};
+
+/// Outcome of undoing a step
+#[derive(Clone, Debug)]
+pub struct Compensated {
+    /// Position of the undone step in the saga
+    pub step: usize,
+    pub result: Result<(), Error>,
+}
+
+/// Multi-step write flow with compensation
+///
+/// Steps run through one client and base URL.
+/// Those added with `step_with_undo` register a
+/// request undoing them, built from their result,
+/// e.g. deleting a created resource. Should a later
+/// step fail, the registered undo requests of all
+/// completed steps run in reverse order before
+/// its error is returned, so partial failures
+/// don't leave dangling resources.
+///
+/// Compensation is best-effort: failed undo
+/// requests are not retried beyond the client's
+/// middleware, but each outcome is recorded in
+/// `report`
+pub struct Saga<'a, C: ?Sized> {
+    client: &'a C,
+    base_url: &'a str,
+    steps: usize,
+    undo: Vec<(usize, BoxFuture<'a, Result<(), Error>>)>,
+    report: Vec<Compensated>,
+}
+
+impl<'a, C> Saga<'a, C>
+where
+    C: Transport + ?Sized,
+{
+    pub fn new(client: &'a C, base_url: &'a str) -> Self {
+        Self {
+            client,
+            base_url,
+            steps: 0,
+            undo: Vec::new(),
+            report: Vec::new(),
+        }
+    }
+
+    /// Run a step which needs no undoing
+    ///
+    /// Compensates the previous steps if it fails
+    pub async fn step<S, T>(&mut self, step: S) -> Result<T, Error>
+    where
+        S: Step<T>,
+    {
+        self.steps += 1;
+
+        match step.run(self.client, self.base_url).await {
+            Ok(output) => Ok(output),
+            Err(err) => {
+                self.compensate().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Run a step, registering the request
+    /// built by `undo` from its result
+    ///
+    /// Compensates the previous steps if it fails.
+    /// Only the status of the undo response is
+    /// checked, its body is ignored
+    pub async fn step_with_undo<S, T, F, U, V>(&mut self, step: S, undo: F) -> Result<T, Error>
+    where
+        S: Step<T>,
+        F: FnOnce(&T) -> U,
+        U: Request<V> + 'a,
+        V: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        let index = self.steps;
+        let output = self.step(step).await?;
+
+        let request = undo(&output);
+        let client = self.client;
+        let base_url = self.base_url;
+
+        self.undo.push((
+            index,
+            Box::pin(async move {
+                let built = try_build(&request, builder_client(), base_url)?.build()?;
+
+                let response = client.execute(built).await?;
+
+                request.check_response(response).await.map(|_| ())
+            }),
+        ));
+
+        Ok(output)
+    }
+
+    /// Undo all completed steps in reverse order,
+    /// returning the outcomes
+    ///
+    /// Called by failing steps. Call it yourself to
+    /// abort the saga for reasons of your own
+    pub async fn compensate(&mut self) -> &[Compensated] {
+        while let Some((step, undo)) = self.undo.pop() {
+            let result = undo.await;
+            self.report.push(Compensated { step, result });
+        }
+
+        &self.report
+    }
+
+    /// Outcomes of all undo requests run so far
+    pub fn report(&self) -> &[Compensated] {
+        &self.report
+    }
+
+    /// Finish the saga, discarding
+    /// the registered undo requests
+    pub fn commit(self) {}
+}
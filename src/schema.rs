@@ -0,0 +1,70 @@
+//! Validation of responses against JSON Schema
+//!
+//! With the `json-schema` feature, requests can attach
+//! a `Schema` with `Request::schema` to validate raw
+//! responses before deserializing them, e.g. in
+//! contract tests of wrappers against live sandboxes
+
+#[cfg(feature = "json-schema")]
+use super::error::Error;
+
+/// A violation of a JSON Schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer to the offending value
+    pub instance_path: String,
+    /// JSON pointer to the violated keyword
+    pub schema_path: String,
+    pub message: String,
+}
+
+/// A compiled JSON Schema
+///
+/// Compile it once, e.g. in a `LazyLock`,
+/// and return it from `Request::schema`
+#[cfg(feature = "json-schema")]
+pub struct Schema {
+    validator: jsonschema::Validator,
+}
+
+#[cfg(feature = "json-schema")]
+impl Schema {
+    /// Compile `schema`
+    ///
+    /// Fails with `Error::ClientDecodeError`
+    /// if it is not a valid schema
+    pub fn new(schema: &serde_json::Value) -> Result<Self, Error> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|inner| Error::ClientDecodeError(format!("invalid schema: {}", inner)))?;
+
+        Ok(Self { validator })
+    }
+
+    /// Validate `instance`, failing with
+    /// `Error::SchemaViolation` listing
+    /// all violations
+    pub fn validate(&self, instance: &serde_json::Value) -> Result<(), Error> {
+        let violations: Vec<Violation> = self
+            .validator
+            .iter_errors(instance)
+            .map(|error| Violation {
+                instance_path: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+                message: error.to_string(),
+            })
+            .collect();
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(Error::SchemaViolation(violations)),
+        }
+    }
+
+    /// Validate raw JSON
+    pub fn validate_bytes(&self, bytes: &[u8]) -> Result<(), Error> {
+        let instance = serde_json::from_slice(bytes)
+            .map_err(|inner| Error::ClientDecodeError(inner.to_string()))?;
+
+        self.validate(&instance)
+    }
+}
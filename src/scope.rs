@@ -0,0 +1,165 @@
+use http::{header::AUTHORIZATION, Extensions, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request as HttpRequest, Response, Url};
+use serde::de::DeserializeOwned;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::BoxFuture,
+    request::Request,
+    response::FullResponse,
+    transport::Transport,
+};
+
+/// A transport bound to a base URL and
+/// default headers, e.g. for authentication
+///
+/// Scopes nest, composing path prefixes and headers,
+/// so large wrappers can organize endpoints
+/// hierarchically, e.g. `client.org("acme").repos()`
+/// returning ever narrower scopes. Requests then
+/// only define their endpoint relative to a scope.
+///
+/// Scope headers are added to requests which don't
+/// set them themselves, if on the origin of the base
+/// URL, so redirects elsewhere don't receive them.
+/// As a `Transport`, a scope can also be passed to
+/// `Request::send` directly
+#[derive(Clone, Debug)]
+pub struct Scope<C> {
+    client: C,
+    base_url: String,
+    headers: HeaderMap,
+}
+
+impl<C: Transport> Scope<C> {
+    /// Root scope sending through `client`
+    pub fn new<S: Into<String>>(client: C, base_url: S) -> Self {
+        Self {
+            client,
+            base_url: base_url.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Nested scope below `path`,
+    /// inheriting all headers
+    pub fn scope<S: AsRef<str>>(&self, path: S) -> Self
+    where
+        C: Clone,
+    {
+        Self {
+            client: self.client.clone(),
+            base_url: format!(
+                "{}/{}",
+                self.base_url.trim_end_matches('/'),
+                path.as_ref().trim_matches('/')
+            ),
+            headers: self.headers.clone(),
+        }
+    }
+
+    /// Add a header to every request
+    pub fn header<K, V>(mut self, name: K, value: V) -> Result<Self, http::Error>
+    where
+        K: TryInto<HeaderName>,
+        K::Error: Into<http::Error>,
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+        let value = value.try_into().map_err(Into::into)?;
+
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Add headers to every request, replacing
+    /// existing values for the same header names
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        for (name, value) in headers {
+            if let Some(name) = name {
+                self.headers.insert(name, value);
+            }
+        }
+
+        self
+    }
+
+    /// Authenticate every request with a bearer token
+    ///
+    /// The value is marked as sensitive
+    pub fn bearer<S: AsRef<str>>(mut self, token: S) -> Result<Self, http::Error> {
+        let mut value = HeaderValue::try_from(format!("Bearer {}", token.as_ref()))?;
+        value.set_sensitive(true);
+
+        self.headers.insert(AUTHORIZATION, value);
+        Ok(self)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Send a request relative to the scope
+    pub async fn send<R, T>(&self, request: &R) -> Result<T, Error>
+    where
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        request.send(self, &self.base_url).await
+    }
+
+    /// Like `send`, but also return the
+    /// metadata of the response
+    pub async fn send_full<R, T>(&self, request: &R) -> Result<FullResponse<T>, Error>
+    where
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        request.send_full(self, &self.base_url).await
+    }
+}
+
+impl<C> Scope<C> {
+    /// Add the scope headers missing from the
+    /// request, if on the origin of the base URL
+    fn apply(&self, request: &mut HttpRequest) {
+        let same_origin = Url::parse(&self.base_url)
+            .is_ok_and(|base_url| base_url.origin() == request.url().origin());
+
+        if !same_origin {
+            return;
+        }
+
+        for name in self.headers.keys() {
+            if !request.headers().contains_key(name) {
+                for value in self.headers.get_all(name) {
+                    request.headers_mut().append(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+impl<C: Transport> Transport for Scope<C> {
+    /// Add the scope headers missing
+    /// from the request and execute it
+    fn execute(&self, mut request: HttpRequest) -> BoxFuture<'_, Result<Response, Error>> {
+        self.apply(&mut request);
+        self.client.execute(request)
+    }
+
+    fn execute_with<'a>(
+        &'a self,
+        mut request: HttpRequest,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        self.apply(&mut request);
+        self.client.execute_with(request, extensions)
+    }
+}
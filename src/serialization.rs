@@ -0,0 +1,128 @@
+//! How JSON request bodies are serialized
+//!
+//! `Request::serialization` picks compact or pretty
+//! output and whether `null` fields are sent, e.g. for
+//! APIs treating an explicit `null` as "clear this
+//! field". `Request::serialize_body` takes over entirely,
+//! e.g. to serialize with a custom `Formatter` via
+//! `Body::json_with`, or to reuse bytes serialized
+//! once with a `BodyCache` when the same request is
+//! sent repeatedly, e.g. in a retry loop. Retries of
+//! middleware and `Hedge` resend the built bytes as is
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::{
+    ser::{CompactFormatter, PrettyFormatter},
+    Value,
+};
+
+use super::{built::Body, error::Error};
+
+/// Options of JSON request bodies
+///
+/// Compact and emitting `null` fields by default,
+/// as serialized by `serde_json::to_vec`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Serialization {
+    pretty: bool,
+    skip_nulls: bool,
+}
+
+impl Serialization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indent the body, e.g. for
+    /// readable logs or recordings
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Leave out object fields that are `null`,
+    /// at any depth. Array elements are kept
+    pub fn skip_nulls(mut self, skip_nulls: bool) -> Self {
+        self.skip_nulls = skip_nulls;
+        self
+    }
+
+    /// Serialize `value` with these options
+    pub fn json<T: Serialize + ?Sized>(&self, value: &T) -> Result<Body, Error> {
+        if !self.skip_nulls {
+            return self.encode(value);
+        }
+
+        let mut value = serde_json::to_value(value).map_err(builder)?;
+        strip_nulls(&mut value);
+
+        self.encode(&value)
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Body, Error> {
+        let body = match self.pretty {
+            true => Body::json_with(value, PrettyFormatter::new()),
+            false => Body::json_with(value, CompactFormatter),
+        };
+
+        body.map_err(builder)
+    }
+}
+
+/// Body serialized once and reused by every
+/// build of a request, e.g. across retries
+///
+/// Keep it in a `#[serde(skip)]` field and return
+/// `self.cache.get_or_serialize(..)` from
+/// `Request::serialize_body`. Clones keep the
+/// cached bytes, so `clear` it after changing
+/// the body of a clone
+#[derive(Clone, Debug, Default)]
+pub struct BodyCache {
+    body: OnceLock<Option<Body>>,
+}
+
+impl BodyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached body, or the one returned by
+    /// `serialize` on the first call. Errors
+    /// aren't cached, so the next call tries again
+    pub fn get_or_serialize<F>(&self, serialize: F) -> Result<Option<Body>, Error>
+    where
+        F: FnOnce() -> Result<Option<Body>, Error>,
+    {
+        if let Some(body) = self.body.get() {
+            return Ok(body.clone());
+        }
+
+        let body = serialize()?;
+
+        Ok(self.body.get_or_init(|| body).clone())
+    }
+
+    /// Serialize again on the next call
+    pub fn clear(&mut self) {
+        self.body.take();
+    }
+}
+
+/// Serializing a body fails building the request
+fn builder(inner: serde_json::Error) -> Error {
+    Error::Builder(inner.to_string())
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|_, field| !field.is_null());
+            fields.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(elements) => elements.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
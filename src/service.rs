@@ -0,0 +1,73 @@
+use std::{
+    marker::PhantomData,
+    task::{Context, Poll},
+};
+
+use serde::de::DeserializeOwned;
+use tower_service::Service;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::BoxFuture,
+    request::Request,
+    transport::Transport,
+};
+
+/// `tower::Service` sending wrapi requests
+///
+/// Exposes request execution as a service so
+/// tower middleware (rate limiting, load shedding,
+/// timeouts, retries) can be layered around it
+///
+/// `T` is the response type requests deserialize into
+pub struct RequestService<C, T> {
+    client: C,
+    base_url: String,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<C, T> RequestService<C, T> {
+    pub fn new<S: Into<String>>(client: C, base_url: S) -> Self {
+        Self {
+            client,
+            base_url: base_url.into(),
+            _response: PhantomData,
+        }
+    }
+}
+
+impl<C, T> Clone for RequestService<C, T>
+where
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            base_url: self.base_url.clone(),
+            _response: PhantomData,
+        }
+    }
+}
+
+impl<C, T, R> Service<R> for RequestService<C, T>
+where
+    C: Transport + Clone + 'static,
+    T: DeserializeOwned + MaybeSend + MaybeSync + 'static,
+    R: Request<T> + 'static,
+{
+    type Response = T;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<T, Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let client = self.client.clone();
+        let base_url = self.base_url.clone();
+
+        Box::pin(async move { request.send(&client, &base_url).await })
+    }
+}
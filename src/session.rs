@@ -0,0 +1,236 @@
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use async_lock::Mutex as AsyncMutex;
+use http::{header::AUTHORIZATION, HeaderMap, HeaderValue, StatusCode};
+use reqwest::{Request as HttpRequest, Response};
+use serde::de::DeserializeOwned;
+
+use super::{
+    cookie::{append_cookies, CookieSession},
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+    redirect::CrossOriginRedirect,
+    request::{try_build, Request},
+    transport::builder_client,
+};
+
+/// Credentials obtained by logging in
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    /// Bearer token sent as `Authorization` header
+    pub bearer: Option<String>,
+    /// Additional headers sent with every request
+    pub headers: HeaderMap,
+}
+
+impl Credentials {
+    pub fn bearer<S: Into<String>>(token: S) -> Self {
+        Self {
+            bearer: Some(token.into()),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(bearer) = &self.bearer {
+            if let Ok(mut value) = HeaderValue::try_from(format!("Bearer {}", bearer)) {
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
+        }
+
+        headers.extend(self.headers.clone());
+    }
+}
+
+trait Login: MaybeSend + MaybeSync {
+    fn login<'a>(
+        &'a self,
+        cookies: &'a CookieSession,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Credentials, Error>>;
+}
+
+struct LoginRequest<R, T, F> {
+    request: R,
+    base_url: String,
+    extract: F,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<R, T, F> Login for LoginRequest<R, T, F>
+where
+    R: Request<T>,
+    T: DeserializeOwned + MaybeSend + MaybeSync,
+    F: Fn(T) -> Credentials + MaybeSend + MaybeSync,
+{
+    fn login<'a>(
+        &'a self,
+        cookies: &'a CookieSession,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Credentials, Error>> {
+        Box::pin(async move {
+            let request = try_build(&self.request, builder_client(), &self.base_url)?.build()?;
+
+            let response = next.run(request).await?;
+            cookies.store(response.url(), response.headers());
+
+            let body = self.request.from_response(response).await?;
+
+            Ok((self.extract)(body))
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    credentials: Option<Credentials>,
+    /// Incremented on every login, so concurrent
+    /// requests rejected with the same credentials
+    /// only trigger a single re-login
+    generation: u64,
+}
+
+/// Middleware bootstrapping a logged-in session
+///
+/// The login request is executed lazily before the
+/// first request, through the rest of the middleware
+/// stack. The credentials it yields, along with any
+/// cookies it sets, are attached to every subsequent
+/// request. When a request is rejected with
+/// `401 Unauthorized` the login is re-run and the
+/// request retried once. Redirects to other
+/// origins are sent without them.
+///
+/// Clones share the same session
+#[derive(Clone)]
+pub struct Session {
+    login: Arc<dyn Login>,
+    cookies: CookieSession,
+    state: Arc<Mutex<State>>,
+    login_lock: Arc<AsyncMutex<()>>,
+}
+
+impl Session {
+    /// Create a session logging in with `request`
+    /// sent to `base_url`, using `extract` to obtain
+    /// credentials from the login response
+    pub fn new<R, T, F, S>(request: R, base_url: S, extract: F) -> Self
+    where
+        R: Request<T> + 'static,
+        T: DeserializeOwned + MaybeSend + MaybeSync + 'static,
+        F: Fn(T) -> Credentials + MaybeSend + MaybeSync + 'static,
+        S: Into<String>,
+    {
+        Self {
+            login: Arc::new(LoginRequest {
+                request,
+                base_url: base_url.into(),
+                extract,
+                _response: PhantomData,
+            }),
+            cookies: CookieSession::new(),
+            state: Arc::new(Mutex::new(State::default())),
+            login_lock: Arc::new(AsyncMutex::new(())),
+        }
+    }
+
+    /// Current credentials, if logged in
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.state.lock().unwrap().credentials.clone()
+    }
+
+    /// Cookies captured during the session
+    pub fn cookies(&self) -> &CookieSession {
+        &self.cookies
+    }
+
+    /// Forget credentials and cookies, forcing
+    /// a login on the next request
+    pub fn logout(&self) {
+        self.state.lock().unwrap().credentials = None;
+        self.cookies.clear();
+    }
+
+    /// Credentials to use, logging in if there
+    /// are none or if `stale` is the generation
+    /// of credentials which were rejected
+    async fn ensure(
+        &self,
+        stale: Option<u64>,
+        next: &Next<'_>,
+    ) -> Result<(Credentials, u64), Error> {
+        let current = |state: &State| match (&state.credentials, stale) {
+            (Some(credentials), stale) if stale != Some(state.generation) => {
+                Some((credentials.clone(), state.generation))
+            }
+            _ => None,
+        };
+
+        if let Some(current) = current(&self.state.lock().unwrap()) {
+            return Ok(current);
+        }
+
+        let _guard = self.login_lock.lock().await;
+
+        // Another request may have logged in meanwhile
+        if let Some(current) = current(&self.state.lock().unwrap()) {
+            return Ok(current);
+        }
+
+        let credentials = self.login.login(&self.cookies, next.clone()).await?;
+
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.credentials = Some(credentials.clone());
+
+        Ok((credentials, state.generation))
+    }
+
+    fn authorize(&self, request: &mut HttpRequest, credentials: &Credentials) {
+        credentials.apply(request.headers_mut());
+
+        let cookies = self.cookies.header_for(request.url());
+        append_cookies(request.headers_mut(), &cookies);
+    }
+}
+
+impl Middleware for Session {
+    fn handle<'a>(
+        &'a self,
+        mut request: HttpRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            if next.extensions().get::<CrossOriginRedirect>().is_some() {
+                return next.run(request).await;
+            }
+
+            let (credentials, generation) = self.ensure(None, &next).await?;
+
+            // Keep a pristine copy to retry with after a re-login
+            let retry = request.try_clone();
+            self.authorize(&mut request, &credentials);
+
+            let response = next.clone().run(request).await?;
+            self.cookies.store(response.url(), response.headers());
+
+            let Some(mut retry) = retry.filter(|_| response.status() == StatusCode::UNAUTHORIZED)
+            else {
+                return Ok(response);
+            };
+
+            let (credentials, _) = self.ensure(Some(generation), &next).await?;
+            self.authorize(&mut retry, &credentials);
+
+            let response = next.run(retry).await?;
+            self.cookies.store(response.url(), response.headers());
+
+            Ok(response)
+        })
+    }
+}
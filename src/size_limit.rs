@@ -0,0 +1,86 @@
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Limit of the response body size in bytes,
+/// attached to responses by `MaxResponseSize`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug)]
+struct Limit(usize);
+
+/// Middleware limiting the size of all response
+/// bodies, protecting against hostile or
+/// unexpectedly huge payloads
+///
+/// Responses announcing a larger `Content-Length`
+/// fail with `Error::ResponseTooLarge` right away.
+/// Others are counted while being read and abort
+/// once they exceed the limit, except on `wasm32`.
+/// `Request::max_response_size` can lower the limit
+/// of individual requests
+#[derive(Clone, Copy, Debug)]
+pub struct MaxResponseSize {
+    limit: usize,
+}
+
+impl MaxResponseSize {
+    /// Limit response bodies to `limit` bytes
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl Middleware for MaxResponseSize {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            #[allow(unused_mut)]
+            let mut response = next.run(request).await?;
+
+            check_length(&response, self.limit)?;
+
+            #[cfg(not(target_arch = "wasm32"))]
+            response.extensions_mut().insert(Limit(self.limit));
+
+            Ok(response)
+        })
+    }
+}
+
+/// The stricter of `limit` and the
+/// limit attached to `response`
+pub(crate) fn effective_limit(response: &Response, limit: Option<usize>) -> Option<usize> {
+    match (limit, attached(response)) {
+        (Some(limit), Some(attached)) => Some(limit.min(attached)),
+        (limit, attached) => limit.or(attached),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn attached(response: &Response) -> Option<usize> {
+    response.extensions().get::<Limit>().map(|limit| limit.0)
+}
+
+/// Responses can't carry the limit on `wasm32`
+#[cfg(target_arch = "wasm32")]
+fn attached(_: &Response) -> Option<usize> {
+    None
+}
+
+/// Fail if `response` announces a body
+/// larger than `limit`
+pub(crate) fn check_length(response: &Response, limit: usize) -> Result<(), Error> {
+    match response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        true => Err(Error::ResponseTooLarge { limit }),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,271 @@
+//! Contract tests against live sandbox APIs
+//!
+//! APIs add, rename and retype fields without notice,
+//! which wrappers only notice when users report broken
+//! deserialization. A `Contract` sends a set of requests
+//! to a sandbox, deserializes each response strictly and
+//! runs assertions on it, reporting every endpoint whose
+//! schema drifted, e.g. from a scheduled CI job
+
+use std::{fmt, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    marker::{MaybeSend, MaybeSync},
+    middleware::BoxFuture,
+    request::Request,
+    transport::Transport,
+};
+
+type Assertion<T> = dyn Fn(&T) -> Result<(), String> + Send + Sync;
+
+type Run = dyn for<'a> Fn(&'a dyn Transport, &'a str) -> BoxFuture<'a, Outcome> + Send + Sync;
+
+/// Requests checked against a sandbox API
+///
+/// Responses are deserialized into the response type of
+/// their request rejecting unknown fields, whether or not
+/// the request is `strict`. Requests run one at a time,
+/// in the order added, sparing sandbox rate limits
+pub struct Contract {
+    base_url: String,
+    checks: Vec<Check>,
+}
+
+struct Check {
+    method: String,
+    endpoint: String,
+    run: Box<Run>,
+}
+
+impl Contract {
+    /// Contract of the API at `base_url`,
+    /// e.g. the sandbox of a vendor
+    pub fn new<S: Into<String>>(base_url: S) -> Self {
+        Self {
+            base_url: base_url.into(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// Check that the response to `request` deserializes
+    /// into `T` without unknown fields
+    pub fn check<R, T>(self, request: R) -> Self
+    where
+        R: Request<T> + 'static,
+        T: DeserializeOwned + MaybeSend + MaybeSync + 'static,
+    {
+        self.check_with(request, |_: &T| Ok(()))
+    }
+
+    /// Like `check`, also asserting `assertion` holds for
+    /// the response, e.g. that a list isn't empty
+    pub fn check_with<R, T, F>(mut self, request: R, assertion: F) -> Self
+    where
+        R: Request<T> + 'static,
+        T: DeserializeOwned + MaybeSend + MaybeSync + 'static,
+        F: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    {
+        let method = request.method().to_string();
+        let endpoint = request.endpoint();
+        let request = Arc::new(request);
+        let assertion: Arc<Assertion<T>> = Arc::new(assertion);
+
+        let run = boxed(move |client, base_url| {
+            let request = request.clone();
+            let assertion = assertion.clone();
+
+            Box::pin(async move {
+                match request.send_borrowed(client, base_url).await {
+                    Ok(body) => verify(body.bytes(), assertion.as_ref()),
+                    Err(error) => Outcome::RequestFailed {
+                        error: error.to_string(),
+                    },
+                }
+            })
+        });
+
+        self.checks.push(Check {
+            method,
+            endpoint,
+            run,
+        });
+
+        self
+    }
+
+    /// Send every request through `client`
+    /// and report how each response held up
+    pub async fn run<C: Transport>(&self, client: &C) -> ContractReport {
+        let mut results = Vec::with_capacity(self.checks.len());
+
+        for check in &self.checks {
+            let outcome = (check.run)(client, &self.base_url).await;
+
+            results.push(CheckResult {
+                method: check.method.clone(),
+                endpoint: check.endpoint.clone(),
+                outcome,
+            });
+        }
+
+        ContractReport { results }
+    }
+}
+
+impl fmt::Debug for Contract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checks: Vec<String> = self
+            .checks
+            .iter()
+            .map(|check| format!("{} {}", check.method, check.endpoint))
+            .collect();
+
+        f.debug_struct("Contract")
+            .field("base_url", &self.base_url)
+            .field("checks", &checks)
+            .finish()
+    }
+}
+
+/// Pins down the higher-ranked signature of `run`
+fn boxed<F>(run: F) -> Box<Run>
+where
+    F: for<'a> Fn(&'a dyn Transport, &'a str) -> BoxFuture<'a, Outcome> + Send + Sync + 'static,
+{
+    Box::new(run)
+}
+
+/// Deserialize `bytes` strictly and assert on them
+fn verify<T: DeserializeOwned>(bytes: &[u8], assertion: &Assertion<T>) -> Outcome {
+    // Empty bodies, e.g. of `204 No Content`, as for `send`
+    let bytes = match bytes.is_empty() {
+        true => b"null".as_slice(),
+        false => bytes,
+    };
+
+    let mut unknown = Vec::new();
+
+    let value: T =
+        match serde_ignored::deserialize(&mut serde_json::Deserializer::from_slice(bytes), |path| {
+            unknown.push(path.to_string())
+        }) {
+            Ok(value) => value,
+            Err(err) => {
+                return Outcome::Mismatch {
+                    error: err.to_string(),
+                }
+            }
+        };
+
+    if let Err(message) = assertion(&value) {
+        return Outcome::AssertionFailed { message };
+    }
+
+    match unknown.is_empty() {
+        true => Outcome::Passed,
+        false => Outcome::UnknownFields { fields: unknown },
+    }
+}
+
+/// How the response to a request held up
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    Passed,
+    /// Deserialized, but with fields unknown to the
+    /// response type, e.g. `items[0].new_field`
+    UnknownFields {
+        fields: Vec<String>,
+    },
+    /// Failed to deserialize, e.g. as a field
+    /// went missing or changed type
+    Mismatch {
+        error: String,
+    },
+    /// Deserialized, but an assertion failed
+    AssertionFailed {
+        message: String,
+    },
+    /// No response to check, e.g. an error status
+    RequestFailed {
+        error: String,
+    },
+}
+
+impl Outcome {
+    pub fn is_passed(&self) -> bool {
+        *self == Outcome::Passed
+    }
+}
+
+/// Outcome of the check of a request
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CheckResult {
+    pub method: String,
+    pub endpoint: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// Results of a `Contract`, in the order of its checks
+///
+/// Serializes to JSON for CI artifacts,
+/// and displays as a summary
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ContractReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl ContractReport {
+    /// Whether every check passed
+    pub fn is_passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.is_passed())
+    }
+
+    /// Checks that didn't pass
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.results
+            .iter()
+            .filter(|result| !result.outcome.is_passed())
+    }
+
+    /// Assert every check passed
+    ///
+    /// # Panics
+    ///
+    /// If any didn't, with the summary
+    pub fn assert_passed(&self) {
+        assert!(self.is_passed(), "contract broken\n\n{}", self);
+    }
+}
+
+impl fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self.failures().count();
+
+        writeln!(
+            f,
+            "{} of {} checks passed",
+            self.results.len() - failures,
+            self.results.len()
+        )?;
+
+        for result in self.failures() {
+            write!(f, "\n{} {}: ", result.method, result.endpoint)?;
+
+            match &result.outcome {
+                Outcome::Passed => write!(f, "passed")?,
+                Outcome::UnknownFields { fields } => {
+                    write!(f, "unknown fields {}", fields.join(", "))?
+                }
+                Outcome::Mismatch { error } => write!(f, "mismatch, {}", error)?,
+                Outcome::AssertionFailed { message } => write!(f, "assertion failed, {}", message)?,
+                Outcome::RequestFailed { error } => write!(f, "request failed, {}", error)?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Mock transport for unit-testing `Request` implementations
+//!
+//! Register canned responses for `(method, path)` pairs,
+//! send requests through the mock and assert on what
+//! it received, all without network access.
+
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{client::WrapiClient, error::Error, middleware::BoxFuture, transport::Transport};
+
+/// Base URL to send mocked requests to
+pub const MOCK_BASE_URL: &str = "http://wrapi.mock";
+
+#[derive(Clone, Debug)]
+struct Route {
+    method: Method,
+    path: String,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// A request received by the mock
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Bytes>,
+}
+
+impl ReceivedRequest {
+    /// Deserialize the received body as JSON
+    pub fn json<V: DeserializeOwned>(&self) -> Option<V> {
+        serde_json::from_slice(self.body.as_ref()?).ok()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: Vec<Route>,
+    received: Vec<ReceivedRequest>,
+}
+
+/// Transport answering requests from registered
+/// routes instead of performing them
+///
+/// Requests without a matching route are
+/// answered with `404 Not Found`
+///
+/// Clones share routes and received requests
+#[derive(Clone, Debug, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Respond to `method` requests on `path`
+    /// with `status` and a JSON `body`
+    pub fn route<B: Serialize>(
+        self,
+        method: Method,
+        path: &str,
+        status: StatusCode,
+        body: &B,
+    ) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        );
+
+        self.route_raw(
+            method,
+            path,
+            status,
+            headers,
+            serde_json::to_vec(body).expect("mock body should serialize"),
+        )
+    }
+
+    /// Respond to `method` requests on `path`
+    /// with `status`, `headers` and a raw `body`
+    pub fn route_raw<B: Into<Bytes>>(
+        self,
+        method: Method,
+        path: &str,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: B,
+    ) -> Self {
+        self.state.lock().unwrap().routes.push(Route {
+            method,
+            path: normalize(path).to_string(),
+            status,
+            headers,
+            body: body.into(),
+        });
+        self
+    }
+
+    /// Client sending all requests to the mock
+    pub fn client(&self) -> WrapiClient {
+        WrapiClient::with_transport(self.clone())
+    }
+
+    /// All requests received so far
+    pub fn received(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Requests received for `method` on `path`
+    pub fn received_on(&self, method: Method, path: &str) -> Vec<ReceivedRequest> {
+        self.received()
+            .into_iter()
+            .filter(|request| {
+                request.method == method && normalize(request.url.path()) == normalize(path)
+            })
+            .collect()
+    }
+
+    /// Assert that `method` on `path` was requested
+    /// exactly `times` times
+    ///
+    /// # Panics
+    ///
+    /// If the number of matching requests differs
+    pub fn assert_received(&self, method: Method, path: &str, times: usize) {
+        let count = self.received_on(method.clone(), path).len();
+
+        assert_eq!(
+            count, times,
+            "expected {} {} to be requested {} times, got {}",
+            method, path, times, count
+        );
+    }
+}
+
+impl Transport for MockTransport {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move {
+            let received = ReceivedRequest {
+                method: request.method().clone(),
+                url: request.url().clone(),
+                headers: request.headers().clone(),
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(Bytes::copy_from_slice),
+            };
+
+            let mut state = self.state.lock().unwrap();
+
+            let route = state
+                .routes
+                .iter()
+                .find(|route| {
+                    route.method == received.method && route.path == normalize(received.url.path())
+                })
+                .cloned();
+
+            let url = received.url.clone();
+            state.received.push(received);
+            drop(state);
+
+            let response = match route {
+                Some(route) => {
+                    let mut builder = http::Response::builder().status(route.status).url(url);
+
+                    if let Some(headers) = builder.headers_mut() {
+                        *headers = route.headers;
+                    }
+
+                    builder.body(route.body)
+                }
+                None => http::Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .url(url)
+                    .body(Bytes::new()),
+            };
+
+            Ok(Response::from(response?))
+        })
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_matches('/')
+}
@@ -0,0 +1,8 @@
+//! Utilities for testing API wrappers built with wrapi
+
+pub mod contract;
+pub mod mock;
+pub mod snapshot;
+pub mod vcr;
+#[cfg(feature = "wiremock")]
+pub mod wiremock;
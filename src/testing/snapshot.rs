@@ -0,0 +1,195 @@
+//! Snapshots of built requests for golden-file tests
+//!
+//! A `RequestSnapshot` is the wire format of a request
+//! in a canonical form: query parameters, headers and
+//! form fields sorted, JSON bodies with sorted keys and
+//! secrets redacted. Comparing it against a golden file,
+//! or with `insta::assert_json_snapshot!` or
+//! `assert_yaml_snapshot!`, catches accidental changes
+//! to what a wrapper sends
+
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::HeaderName;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::mock::MOCK_BASE_URL;
+use crate::{
+    built::{BodyFormat, BuiltRequest},
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+};
+
+/// Environment variable rewriting golden
+/// files instead of comparing against them
+pub const UPDATE_GOLDEN_ENV: &str = "WRAPI_UPDATE_GOLDEN";
+
+/// Placeholder of redacted values
+const REDACTED: &str = "[redacted]";
+
+/// Canonical form of a built request
+///
+/// Header names are lowercase and multiple values of
+/// a header joined by `, `. Bodies are `json`, `form`
+/// parameters, UTF-8 `text`, or base64 `binary`, e.g.
+/// when compressed
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RequestSnapshot {
+    method: String,
+    /// Without the query
+    url: String,
+    query: Vec<(String, String)>,
+    headers: BTreeMap<String, String>,
+    body: Option<SnapshotBody>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SnapshotBody {
+    Json(Value),
+    Form(Vec<(String, String)>),
+    Text(String),
+    Binary(String),
+}
+
+impl RequestSnapshot {
+    /// Snapshot of `built`
+    pub fn new(built: &BuiltRequest) -> Self {
+        let mut url = built.redacted_url();
+
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        query.sort();
+        url.set_query(None);
+
+        let mut headers = BTreeMap::<String, String>::new();
+        let redacted = built.redacted_headers();
+
+        for (name, value) in &redacted {
+            let value = String::from_utf8_lossy(value.as_bytes());
+
+            headers
+                .entry(name.as_str().to_string())
+                .and_modify(|values| {
+                    values.push_str(", ");
+                    values.push_str(&value);
+                })
+                .or_insert_with(|| value.into_owned());
+        }
+
+        let body = built.body.as_ref().map(|body| {
+            let text = std::str::from_utf8(&body.bytes).ok();
+
+            match (body.format, text) {
+                (BodyFormat::Json, Some(text)) => match serde_json::from_str(text) {
+                    Ok(json) => SnapshotBody::Json(json),
+                    Err(_) => SnapshotBody::Text(text.to_string()),
+                },
+                (BodyFormat::Form, Some(_)) => {
+                    let mut params: Vec<(String, String)> = form_urlencoded::parse(&body.bytes)
+                        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                        .collect();
+                    params.sort();
+
+                    SnapshotBody::Form(params)
+                }
+                (_, Some(text)) => SnapshotBody::Text(text.to_string()),
+                (_, None) => SnapshotBody::Binary(STANDARD.encode(&body.bytes)),
+            }
+        });
+
+        Self {
+            method: built.method.to_string(),
+            url: url.to_string(),
+            query,
+            headers,
+            body,
+        }
+    }
+
+    /// Snapshot of `request`, built against
+    /// `MOCK_BASE_URL`
+    ///
+    /// # Panics
+    ///
+    /// If `request` fails to build
+    pub fn of<R, T>(request: &R) -> Self
+    where
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        Self::new(
+            &request
+                .prepare(MOCK_BASE_URL)
+                .expect("snapshotted request should build"),
+        )
+    }
+
+    /// Redact header `name`, e.g. an
+    /// `Idempotency-Key` differing every run
+    pub fn redact_header(mut self, name: HeaderName) -> Self {
+        if let Some(value) = self.headers.get_mut(name.as_str()) {
+            *value = REDACTED.to_string();
+        }
+
+        self
+    }
+
+    /// Redact query parameter `name`, e.g.
+    /// a timestamp differing every run
+    pub fn redact_query(mut self, name: &str) -> Self {
+        for (key, value) in &mut self.query {
+            if key == name {
+                *value = REDACTED.to_string();
+            }
+        }
+
+        self
+    }
+
+    /// Pretty JSON, as written to golden files
+    pub fn to_json(&self) -> String {
+        let mut json =
+            serde_json::to_string_pretty(self).expect("request snapshot should serialize");
+        json.push('\n');
+
+        json
+    }
+
+    /// Assert the snapshot equals the golden file at
+    /// `path`, writing it if missing or if
+    /// `WRAPI_UPDATE_GOLDEN` is set
+    ///
+    /// # Panics
+    ///
+    /// If the snapshot differs, or the
+    /// file can't be read or written
+    pub fn assert_golden<P: AsRef<Path>>(&self, path: P) {
+        let path = path.as_ref();
+        let actual = self.to_json();
+
+        if env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("golden file directory should be created");
+            }
+
+            fs::write(path, actual).expect("golden file should be written");
+            return;
+        }
+
+        let expected = fs::read_to_string(path).expect("golden file should be read");
+
+        assert!(
+            expected == actual,
+            "request differs from golden file {}, set {} to update it\n\nexpected:\n{}\nactual:\n{}",
+            path.display(),
+            UPDATE_GOLDEN_ENV,
+            expected,
+            actual
+        );
+    }
+}
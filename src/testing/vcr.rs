@@ -0,0 +1,266 @@
+//! Record/replay ("VCR cassette") testing
+//!
+//! A `Cassette` is middleware which records real
+//! responses to disk the first time a test runs,
+//! and replays them on subsequent runs so tests
+//! are deterministic and need no network access.
+//!
+//! Recorded interactions are matched on method,
+//! URL and request body. Bodies are stored byte for
+//! byte, as text if UTF-8 and as base64 otherwise,
+//! e.g. when compressed.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{HeaderName, HeaderValue, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    middleware::{buffer, BoxFuture, Middleware, Next},
+};
+
+/// Whether a cassette records or replays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Replay if the cassette file existed
+    /// when opened, record otherwise
+    #[default]
+    Auto,
+    /// Always perform real requests, overwriting
+    /// the cassette with the new recording
+    Record,
+    /// Only replay, never touch the network
+    Replay,
+}
+
+/// A single recorded request and its response
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub body: Option<RecordedBody>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// Body of a recorded request or response
+///
+/// Serialized as a string if UTF-8, and as
+/// `{"base64": ...}` otherwise
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordedBody {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl RecordedBody {
+    pub fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Binary {
+                base64: STANDARD.encode(bytes),
+            },
+        }
+    }
+
+    /// The bytes recorded
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            RecordedBody::Text(text) => Ok(text.clone().into_bytes()),
+            RecordedBody::Binary { base64 } => STANDARD
+                .decode(base64)
+                .map_err(|inner| Error::ClientDecodeError(inner.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+/// Middleware recording and replaying interactions
+///
+/// Clones share the same cassette
+///
+/// # Panics
+///
+/// Requests panic when replaying a request without
+/// a recorded interaction, or when recording fails
+/// to write the cassette file, failing the test
+#[derive(Clone, Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    replay: bool,
+    state: Arc<Mutex<State>>,
+}
+
+impl Cassette {
+    /// Open the cassette at `path` in `Mode::Auto`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::with_mode(path, Mode::Auto)
+    }
+
+    /// Open the cassette at `path` in the given mode
+    pub fn with_mode<P: AsRef<Path>>(path: P, mode: Mode) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let replay = match mode {
+            Mode::Auto => path.exists(),
+            Mode::Record => false,
+            Mode::Replay => true,
+        };
+
+        let interactions: Vec<Interaction> = if replay {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path,
+            replay,
+            state: Arc::new(Mutex::new(State {
+                played: vec![false; interactions.len()],
+                interactions,
+            })),
+        })
+    }
+
+    /// Whether the cassette is replaying
+    /// rather than recording
+    pub fn is_replaying(&self) -> bool {
+        self.replay
+    }
+
+    /// All interactions recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// Find a recorded response for the request
+    ///
+    /// Interactions are replayed in recorded order,
+    /// the last match is reused once all matching
+    /// interactions have been played
+    fn find(&self, recorded: &RecordedRequest) -> Option<RecordedResponse> {
+        let mut state = self.state.lock().unwrap();
+
+        let matching = |interaction: &Interaction| &interaction.request == recorded;
+
+        let index = state
+            .interactions
+            .iter()
+            .zip(&state.played)
+            .position(|(interaction, played)| !played && matching(interaction))
+            .or_else(|| state.interactions.iter().rposition(matching))?;
+
+        state.played[index] = true;
+
+        Some(state.interactions[index].response.clone())
+    }
+
+    /// Append an interaction and persist the cassette
+    fn record(&self, interaction: Interaction) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        state.interactions.push(interaction);
+        state.played.push(true);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&self.path, serde_json::to_vec_pretty(&state.interactions)?)
+    }
+}
+
+impl Middleware for Cassette {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let recorded = RecordedRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(RecordedBody::new),
+            };
+
+            if self.replay {
+                let url = request.url().clone();
+
+                let replayed = self.find(&recorded).unwrap_or_else(|| {
+                    panic!(
+                        "no recorded interaction for {} {} in cassette {}",
+                        recorded.method,
+                        recorded.url,
+                        self.path.display()
+                    )
+                });
+
+                let status = StatusCode::from_u16(replayed.status)?;
+
+                let mut builder = http::Response::builder().status(status).url(url);
+
+                for (name, value) in &replayed.headers {
+                    let name = HeaderName::try_from(name)?;
+                    let value = HeaderValue::try_from(value)?;
+
+                    builder = builder.header(name, value);
+                }
+
+                let response = builder.body(replayed.body.to_bytes()?)?;
+
+                return Ok(Response::from(response));
+            }
+
+            let (response, body) = buffer(next.run(request).await?).await?;
+
+            let interaction = Interaction {
+                request: recorded,
+                response: RecordedResponse {
+                    status: response.status().as_u16(),
+                    headers: response
+                        .headers()
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((name.to_string(), value.to_str().ok()?.to_string()))
+                        })
+                        .collect(),
+                    body: RecordedBody::new(&body),
+                },
+            };
+
+            self.record(interaction).unwrap_or_else(|err| {
+                panic!("failed to write cassette {}: {}", self.path.display(), err)
+            });
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! Wiremock expectations derived from `Request`s
+//!
+//! Tests of a wrapper against a wiremock `MockServer`
+//! usually restate each request's method, path, query
+//! and body as matchers. `RequestMock` derives them from
+//! the `Request` itself and answers with a canned
+//! response, leaving the `Mock` to mount. With the
+//! `wiremock` feature
+
+use http::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use wiremock::{
+    matchers::{body_bytes, body_json, method, path, query_param},
+    Match, Mock, MockBuilder, ResponseTemplate,
+};
+
+use super::mock::MOCK_BASE_URL;
+use crate::{
+    built::{BodyFormat, BuiltRequest},
+    marker::{MaybeSend, MaybeSync},
+    request::Request,
+};
+
+/// Expectation of a `MockServer` matching
+/// the requests `Request::send` makes
+///
+/// Matches the method, path and query parameters,
+/// plus the body with `match_body`. Send the requests
+/// with the `uri` of the server as base URL
+#[derive(Clone, Debug)]
+pub struct RequestMock {
+    built: BuiltRequest,
+    match_body: bool,
+}
+
+impl RequestMock {
+    /// Expect requests like `request`
+    ///
+    /// # Panics
+    ///
+    /// If `request` fails to build
+    pub fn new<R, T>(request: &R) -> Self
+    where
+        R: Request<T>,
+        T: DeserializeOwned + MaybeSend + MaybeSync,
+    {
+        Self {
+            built: request
+                .prepare(MOCK_BASE_URL)
+                .expect("mocked request should build"),
+            match_body: false,
+        }
+    }
+
+    /// Also match the body, as JSON if it is some,
+    /// as form parameters in any order, and
+    /// byte for byte otherwise
+    pub fn match_body(mut self) -> Self {
+        self.match_body = true;
+        self
+    }
+
+    /// Respond with `status` and a JSON `body`
+    pub fn respond_json<B: Serialize>(self, status: StatusCode, body: &B) -> Mock {
+        self.respond_with(ResponseTemplate::new(status).set_body_json(body))
+    }
+
+    /// Respond with `template`
+    pub fn respond_with(self, template: ResponseTemplate) -> Mock {
+        self.given().respond_with(template)
+    }
+
+    /// Matchers of the request, to add
+    /// more before responding
+    pub fn given(self) -> MockBuilder {
+        let mut mock =
+            Mock::given(method(self.built.method.as_str())).and(path(self.built.url.path()));
+
+        for (key, value) in &self.built.query {
+            mock = mock.and(query_param(key.as_str(), value.as_str()));
+        }
+
+        let body = match self.match_body {
+            true => self.built.body,
+            false => None,
+        };
+
+        match body {
+            Some(body) => match body.format {
+                BodyFormat::Json => match serde_json::from_slice::<Value>(&body.bytes) {
+                    Ok(json) => mock.and(body_json(json)),
+                    Err(_) => mock.and(body_bytes(body.bytes.to_vec())),
+                },
+                BodyFormat::Form => mock.and(FormMatcher::new(&body.bytes)),
+                BodyFormat::Raw => mock.and(body_bytes(body.bytes.to_vec())),
+            },
+            None => mock,
+        }
+    }
+}
+
+/// Form body with the same parameters
+#[derive(Debug)]
+struct FormMatcher(Vec<(String, String)>);
+
+impl FormMatcher {
+    fn new(body: &[u8]) -> Self {
+        Self(parse_form(body))
+    }
+}
+
+impl Match for FormMatcher {
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        parse_form(&request.body) == self.0
+    }
+}
+
+/// Parameters of a form body, sorted
+fn parse_form(body: &[u8]) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = form_urlencoded::parse(body)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+
+    params
+}
@@ -0,0 +1,66 @@
+//! Plain text and HTML responses
+//!
+//! Endpoints returning ad-hoc text, such as health
+//! pages or `robots.txt`-style resources, can still
+//! go through wrapi's auth, middleware and error
+//! handling. With `Format::Text` the body is handed
+//! to `T` as a string, and `TextResponse` extracts
+//! typed values from it with `Extract`
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::error::Error;
+
+/// Typed extraction from a text body,
+/// e.g. by scraping an HTML page
+pub trait Extract: Sized {
+    fn extract(text: &str) -> Result<Self, Error>;
+}
+
+impl Extract for String {
+    fn extract(text: &str) -> Result<Self, Error> {
+        Ok(text.to_string())
+    }
+}
+
+/// Response of a `Format::Text` request,
+/// extracted from the body with `Extract`
+///
+/// The text itself by default. Failed extractions
+/// surface as `Error::ClientDecodeError`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextResponse<E = String>(pub E);
+
+impl<E> TextResponse<E> {
+    pub fn into_inner(self) -> E {
+        self.0
+    }
+}
+
+impl<'de, E: Extract> Deserialize<'de> for TextResponse<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TextVisitor<E>(PhantomData<fn() -> E>);
+
+        impl<E: Extract> Visitor<'_> for TextVisitor<E> {
+            type Value = TextResponse<E>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a text body")
+            }
+
+            fn visit_str<Err: de::Error>(self, text: &str) -> Result<Self::Value, Err> {
+                E::extract(text).map(TextResponse).map_err(|err| match err {
+                    Error::ClientDecodeError(message) => Err::custom(message),
+                    err => Err::custom(err),
+                })
+            }
+        }
+
+        deserializer.deserialize_str(TextVisitor(PhantomData))
+    }
+}
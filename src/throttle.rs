@@ -0,0 +1,115 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use super::runtime::sleep;
+
+/// Caps the bandwidth of transfers
+///
+/// Transfers acquire the bytes they move, waiting
+/// as long as needed to stay below the rate. Clones
+/// share the cap, so one `Throttle` can limit many
+/// transfers together.
+///
+/// Pass it to `Download::throttle` or
+/// `ResumableUpload::throttle`, or add it to a
+/// `WrapiClient` as middleware to throttle all
+/// request and response bodies (not on `wasm32`)
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Throttle {
+    /// Cap transfers at `bytes_per_sec`
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub async fn acquire(&self, bytes: u64) {
+        let duration = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+
+        let delay = {
+            let now = Instant::now();
+            let mut next = self.next.lock().unwrap();
+
+            let start = next.filter(|next| *next > now).unwrap_or(now);
+            *next = Some(start + duration);
+
+            start - now
+        };
+
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod middleware {
+    use futures_util::{stream, StreamExt};
+    use reqwest::{Body, Request, Response, ResponseBuilderExt};
+
+    use super::Throttle;
+    use crate::{
+        error::Error,
+        middleware::{BoxFuture, Middleware, Next},
+    };
+
+    impl Middleware for Throttle {
+        fn handle<'a>(
+            &'a self,
+            request: Request,
+            next: Next<'a>,
+        ) -> BoxFuture<'a, Result<Response, Error>> {
+            Box::pin(async move {
+                if let Some(bytes) = request.body().and_then(Body::as_bytes) {
+                    self.acquire(bytes.len() as u64).await;
+                }
+
+                let mut response = next.run(request).await?;
+
+                let mut builder = http::Response::builder()
+                    .status(response.status())
+                    .version(response.version())
+                    .url(response.url().clone());
+
+                if let Some(headers) = builder.headers_mut() {
+                    *headers = std::mem::take(response.headers_mut());
+                }
+
+                if let Some(extensions) = builder.extensions_mut() {
+                    *extensions = std::mem::take(response.extensions_mut());
+                }
+
+                let throttle = self.clone();
+
+                // Acquire every chunk before passing it on
+                let body = stream::unfold(
+                    (response.bytes_stream(), throttle),
+                    |(mut body, throttle)| async move {
+                        let chunk = body.next().await?;
+
+                        if let Ok(chunk) = &chunk {
+                            throttle.acquire(chunk.len() as u64).await;
+                        }
+
+                        Some((chunk, (body, throttle)))
+                    },
+                );
+
+                let response = builder.body(Body::wrap_stream(body))?;
+
+                Ok(Response::from(response))
+            })
+        }
+    }
+}
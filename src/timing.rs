@@ -0,0 +1,156 @@
+//! Latency of requests, split by where it was spent
+//!
+//! `Metadata::timing` of full responses tells how long
+//! a request waited in a `PriorityQueue`, until its
+//! response headers arrived and until its body was read.
+//! The `Latencies` middleware reports the same for every
+//! request of a client, e.g. to feed metrics, so slowness
+//! can be attributed to the upstream API or to local
+//! queuing and limits
+
+use std::{sync::Arc, time::Duration};
+
+use http::{Method, StatusCode};
+use reqwest::{Request, Response, Url};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Hook = dyn Fn(&Latency) + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Hook = dyn Fn(&Latency);
+
+/// Timing of a request and its response
+///
+/// Connecting is part of `first_byte`,
+/// as reqwest doesn't report it separately
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// Waiting for a slot of a `PriorityQueue`,
+    /// always zero on wasm
+    pub queued: Duration,
+    /// Until the response headers arrived,
+    /// including `queued` and any redirects
+    pub first_byte: Duration,
+    /// Until the body was read and decoded
+    pub total: Duration,
+}
+
+impl Timing {
+    /// Measured from `start`, with the queue
+    /// wait recorded on `response`
+    pub(crate) fn new(response: &Response, start: Instant) -> Self {
+        let first_byte = start.elapsed();
+
+        Self {
+            queued: QueueWait::of(response).map_or(Duration::ZERO, |wait| wait.0),
+            first_byte,
+            total: first_byte,
+        }
+    }
+
+    /// Time until the first byte, minus queuing,
+    /// i.e. spent on the network and the server
+    pub fn upstream(&self) -> Duration {
+        self.first_byte.saturating_sub(self.queued)
+    }
+}
+
+/// Time a request waited for a slot of a `PriorityQueue`,
+/// in the extensions of its response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueWait(pub Duration);
+
+// Responses have no extensions on wasm
+impl QueueWait {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn record(self, response: &mut Response) {
+        response.extensions_mut().insert(self);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn record(self, _response: &mut Response) {}
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn of(response: &Response) -> Option<Self> {
+        response.extensions().get().copied()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn of(_response: &Response) -> Option<Self> {
+        None
+    }
+}
+
+/// Latency of a request, see `Latencies`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Latency {
+    pub method: Method,
+    /// URL of the endpoint, without query
+    pub url: Url,
+    pub status: StatusCode,
+    /// Waiting for a slot of a `PriorityQueue`
+    pub queued: Duration,
+    /// Until the response headers arrived
+    pub first_byte: Duration,
+}
+
+/// Middleware reporting the latency of every
+/// response to a hook, e.g. to record metrics
+///
+/// Add it before any `PriorityQueue`, so queuing is
+/// included in `first_byte` and reported as `queued`.
+/// Reading the body is not included, see
+/// `Metadata::timing` for that
+#[derive(Clone)]
+pub struct Latencies {
+    hook: Arc<Hook>,
+}
+
+impl Latencies {
+    /// Report latencies to `hook`
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&Latency) + MaybeSend + MaybeSync + 'static,
+    {
+        Self {
+            hook: Arc::new(hook),
+        }
+    }
+}
+
+impl Middleware for Latencies {
+    fn handle<'a>(
+        &'a self,
+        request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let method = request.method().clone();
+
+            let mut url = request.url().clone();
+            url.set_query(None);
+            url.set_fragment(None);
+
+            let start = Instant::now();
+            let response = next.run(request).await?;
+            let timing = Timing::new(&response, start);
+
+            (self.hook)(&Latency {
+                method,
+                url,
+                status: response.status(),
+                queued: timing.queued,
+                first_byte: timing.first_byte,
+            });
+
+            Ok(response)
+        })
+    }
+}
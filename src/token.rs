@@ -0,0 +1,215 @@
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_lock::Mutex as AsyncMutex;
+use http::{header::AUTHORIZATION, HeaderValue};
+use reqwest::{Request, Response};
+use web_time::Instant;
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+    redirect::CrossOriginRedirect,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Refresh = dyn Fn() -> BoxFuture<'static, Result<Token, Error>> + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Refresh = dyn Fn() -> BoxFuture<'static, Result<Token, Error>>;
+
+/// An access token and when it expires
+#[derive(Clone)]
+pub struct Token {
+    pub value: String,
+    /// None if it doesn't expire
+    pub expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// Token expiring after `expires_in`,
+    /// e.g. the `expires_in` of an OAuth response.
+    /// Never expiring if that's out of range
+    pub fn new<S: Into<String>>(value: S, expires_in: Duration) -> Self {
+        Self {
+            value: value.into(),
+            expires_at: Instant::now().checked_add(expires_in),
+        }
+    }
+
+    /// Token valid until it's rejected
+    pub fn never_expiring<S: Into<String>>(value: S) -> Self {
+        Self {
+            value: value.into(),
+            expires_at: None,
+        }
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("value", &"[redacted]")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    token: Option<Token>,
+    /// Incremented on every refresh, so concurrent
+    /// callers seeing the same stale token only
+    /// trigger a single refresh
+    generation: u64,
+}
+
+/// Access token cached until shortly before it
+/// expires, refreshed by an async closure
+///
+/// Concurrent callers needing a new token wait for
+/// a single refresh instead of each running one.
+/// Return `current()` from `Request::bearer`, and
+/// `refresh()` the token in `on_unauthorized`, or
+/// add it to a `WrapiClient` as middleware to send
+/// a fresh token as bearer with every request
+/// without an `Authorization` header, short of
+/// redirects to other origins.
+///
+/// Clones share the same token
+#[derive(Clone)]
+pub struct CachedToken {
+    refresh: Arc<Refresh>,
+    margin: Duration,
+    state: Arc<Mutex<State>>,
+    refresh_lock: Arc<AsyncMutex<()>>,
+}
+
+impl CachedToken {
+    /// Cache tokens obtained with `refresh`,
+    /// e.g. a client credentials grant
+    pub fn new<F, Fut>(refresh: F) -> Self
+    where
+        F: Fn() -> Fut + MaybeSend + MaybeSync + 'static,
+        Fut: Future<Output = Result<Token, Error>> + MaybeSend + 'static,
+    {
+        Self {
+            refresh: Arc::new(move || Box::pin(refresh())),
+            margin: Duration::from_secs(30),
+            state: Arc::new(Mutex::new(State::default())),
+            refresh_lock: Arc::new(AsyncMutex::new(())),
+        }
+    }
+
+    /// Refresh tokens this long before they
+    /// expire, 30 seconds by default
+    pub fn margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// The cached token, if still valid
+    pub fn current(&self) -> Option<String> {
+        self.valid(&self.state.lock().unwrap())
+            .map(|(token, _)| token)
+    }
+
+    /// A valid token, refreshing it if
+    /// there is none or it's about to expire
+    pub async fn get(&self) -> Result<String, Error> {
+        self.ensure(None).await
+    }
+
+    /// Replace the cached token, e.g. after
+    /// it was rejected, unless another caller
+    /// refreshed it meanwhile
+    pub async fn refresh(&self) -> Result<String, Error> {
+        let generation = self.state.lock().unwrap().generation;
+
+        self.ensure(Some(generation)).await
+    }
+
+    /// Forget the token, refreshing
+    /// it on the next use
+    pub fn clear(&self) {
+        self.state.lock().unwrap().token = None;
+    }
+
+    fn valid(&self, state: &State) -> Option<(String, u64)> {
+        let token = state.token.as_ref()?;
+
+        let expiring = Instant::now().checked_add(self.margin);
+
+        match token.expires_at {
+            Some(expires_at) if expiring.is_none_or(|expiring| expiring >= expires_at) => None,
+            _ => Some((token.value.clone(), state.generation)),
+        }
+    }
+
+    /// Token to use, refreshing it if there is none
+    /// or if `stale` is the generation of the token
+    /// to replace
+    async fn ensure(&self, stale: Option<u64>) -> Result<String, Error> {
+        let current = |state: &State| {
+            self.valid(state)
+                .filter(|(_, generation)| stale != Some(*generation))
+                .map(|(token, _)| token)
+        };
+
+        if let Some(token) = current(&self.state.lock().unwrap()) {
+            return Ok(token);
+        }
+
+        let _guard = self.refresh_lock.lock().await;
+
+        // Another caller may have refreshed meanwhile
+        if let Some(token) = current(&self.state.lock().unwrap()) {
+            return Ok(token);
+        }
+
+        let token = (self.refresh)().await?;
+        let value = token.value.clone();
+
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.token = Some(token);
+
+        Ok(value)
+    }
+}
+
+impl fmt::Debug for CachedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedToken")
+            .field("margin", &self.margin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Middleware for CachedToken {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let redirected = next.extensions().get::<CrossOriginRedirect>().is_some();
+
+            if !redirected && !request.headers().contains_key(AUTHORIZATION) {
+                let token = self.get().await?;
+
+                if let Ok(mut value) = HeaderValue::try_from(format!("Bearer {}", token)) {
+                    value.set_sensitive(true);
+                    request.headers_mut().insert(AUTHORIZATION, value);
+                }
+            }
+
+            next.run(request).await
+        })
+    }
+}
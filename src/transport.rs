@@ -0,0 +1,153 @@
+use std::sync::{Arc, OnceLock};
+
+use http::Extensions;
+use reqwest::{Client, Request, Response};
+
+use super::{
+    error::Error,
+    marker::{MaybeSend, MaybeSync},
+    middleware::BoxFuture,
+};
+
+/// HTTP backend executing built requests
+///
+/// Implemented for `reqwest::Client`, which is the
+/// default, and for `WrapiClient`. Implement it to
+/// plug in other backends or mock transports
+///
+/// With the `reqwest-middleware` feature it is also
+/// implemented for `ClientWithMiddleware`, reusing
+/// an existing reqwest middleware stack
+pub trait Transport: MaybeSend + MaybeSync {
+    /// Execute the request and return its response
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>>;
+
+    /// Execute the request with the context
+    /// of its call, see `Next::extensions`
+    ///
+    /// Ignores the context by default, `WrapiClient`
+    /// passes it on to its middleware
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        let _ = extensions;
+        self.execute(request)
+    }
+}
+
+impl Transport for Client {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move { Client::execute(self, request).await.map_err(Error::from) })
+    }
+}
+
+#[cfg(feature = "reqwest-middleware")]
+impl Transport for reqwest_middleware::ClientWithMiddleware {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move {
+            reqwest_middleware::ClientWithMiddleware::execute(self, request)
+                .await
+                .map_err(from_middleware)
+        })
+    }
+
+    /// Pass a copy of `extensions` on to
+    /// the reqwest middleware
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let mut extensions = extensions.clone();
+
+            self.execute_with_extensions(request, &mut extensions)
+                .await
+                .map_err(from_middleware)
+        })
+    }
+}
+
+/// Classify errors of reqwest, other
+/// middleware errors are `Custom`
+#[cfg(feature = "reqwest-middleware")]
+fn from_middleware(error: reqwest_middleware::Error) -> Error {
+    match error {
+        reqwest_middleware::Error::Reqwest(inner) => Error::from(inner),
+        reqwest_middleware::Error::Middleware(inner) => Error::custom(inner),
+    }
+}
+
+impl<T> Transport for Arc<T>
+where
+    T: Transport + ?Sized,
+{
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        (**self).execute(request)
+    }
+
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        (**self).execute_with(request, extensions)
+    }
+}
+
+/// Transport sending every request with the
+/// call context `extensions`, see `Next::extensions`
+///
+/// What `Request::send_with_context` sends through,
+/// usable with any other method of `Request`
+pub struct WithExtensions<'a, C: ?Sized> {
+    transport: &'a C,
+    extensions: &'a Extensions,
+}
+
+impl<'a, C: ?Sized> WithExtensions<'a, C> {
+    pub fn new(transport: &'a C, extensions: &'a Extensions) -> Self {
+        Self {
+            transport,
+            extensions,
+        }
+    }
+}
+
+impl<C> Transport for WithExtensions<'_, C>
+where
+    C: Transport + ?Sized,
+{
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        self.transport.execute_with(request, self.extensions)
+    }
+
+    /// Execute with `extensions` on top of
+    /// those of the transport
+    fn execute_with<'a>(
+        &'a self,
+        request: Request,
+        extensions: &'a Extensions,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            let mut merged = self.extensions.clone();
+            merged.extend(extensions.clone());
+
+            self.transport.execute_with(request, &merged).await
+        })
+    }
+}
+
+/// Client used to construct request builders
+/// independently of the transport executing them
+///
+/// Building a request never touches the client's
+/// connection pool, so a single shared instance
+/// suffices for every transport
+pub(crate) fn builder_client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+
+    CLIENT.get_or_init(Client::new)
+}
@@ -0,0 +1,172 @@
+//! Transport to APIs served on a Unix domain socket
+//!
+//! Local daemons such as Docker or systemd expose HTTP
+//! APIs on a socket rather than a TCP port. Wrap them
+//! with the same `Request` types as remote APIs by
+//! sending through a `UnixSocket`, with a base URL of
+//! `UnixSocket::url`. With the `unix-socket` feature,
+//! on Unix targets
+
+use std::{
+    ffi::OsString,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+use http::{header::HOST, HeaderValue, Uri};
+use http_body_util::BodyExt;
+use hyper::client::conn::http1;
+use hyper_util::rt::TokioIo;
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use tokio::net::UnixStream;
+
+use super::{client::WrapiClient, error::Error, middleware::BoxFuture, transport::Transport};
+
+/// Scheme of URLs of a socket
+const SCHEME: &str = "unix";
+
+/// Transport sending requests over Unix domain sockets
+///
+/// Requests to `UnixSocket::url` base URLs go to the
+/// socket encoded in their host. `UnixSocket::at` sends
+/// every request to a single socket instead, whatever
+/// its URL. Each request opens a connection, as local
+/// daemons are cheap to connect to.
+///
+/// Requires a tokio runtime, which the connections
+/// are driven on
+#[derive(Clone, Debug, Default)]
+pub struct UnixSocket {
+    path: Option<PathBuf>,
+}
+
+impl UnixSocket {
+    /// Send requests to the socket
+    /// of their `UnixSocket::url`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send every request to the socket at `path`,
+    /// e.g. with a base URL of `http://localhost`
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Base URL of the socket at `path`, e.g.
+    /// `UnixSocket::url("/var/run/docker.sock")`
+    pub fn url<P: AsRef<Path>>(path: P) -> String {
+        let host: String = path
+            .as_ref()
+            .as_os_str()
+            .as_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        format!("{}://{}/", SCHEME, host)
+    }
+
+    /// A client sending through this transport
+    pub fn client(self) -> WrapiClient {
+        WrapiClient::with_transport(self)
+    }
+
+    /// Socket to send the request to `url` to
+    fn socket(&self, url: &Url) -> Result<PathBuf, Error> {
+        if let Some(path) = &self.path {
+            return Ok(path.clone());
+        }
+
+        if url.scheme() != SCHEME {
+            return Err(Error::Builder(format!(
+                "not a Unix socket URL, see `UnixSocket::url`: {}",
+                url
+            )));
+        }
+
+        decode(url.host_str().unwrap_or_default())
+            .ok_or_else(|| Error::Builder(format!("invalid Unix socket URL: {}", url)))
+    }
+}
+
+impl Transport for UnixSocket {
+    fn execute(&self, request: Request) -> BoxFuture<'_, Result<Response, Error>> {
+        Box::pin(async move {
+            let path = self.socket(request.url())?;
+            let url = request.url().clone();
+
+            let mut request = http::Request::<reqwest::Body>::try_from(request)?;
+
+            // The socket is the server, so only
+            // the path and query are sent
+            let target = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            *request.uri_mut() = target.parse::<Uri>().map_err(builder)?;
+
+            // Socket URLs have no host to name
+            let host = match url.scheme() {
+                SCHEME => HeaderValue::from_static("localhost"),
+                _ => HeaderValue::from_str(url.host_str().unwrap_or("localhost"))?,
+            };
+            request.headers_mut().entry(HOST).or_insert(host);
+
+            let stream = UnixStream::connect(&path)
+                .await
+                .map_err(|err| Error::Connect(format!("{}: {}", path.display(), err)))?;
+
+            let (mut sender, connection) = http1::handshake(TokioIo::new(stream))
+                .await
+                .map_err(connect)?;
+
+            // Drives the connection until the
+            // response body is read or dropped
+            tokio::spawn(connection);
+
+            let response = sender
+                .send_request(request)
+                .await
+                .map_err(|err| Error::BodyTransfer(err.to_string()))?;
+
+            let (parts, body) = response.into_parts();
+            let mut builder = http::Response::builder()
+                .status(parts.status)
+                .version(parts.version)
+                .url(url);
+
+            if let Some(headers) = builder.headers_mut() {
+                *headers = parts.headers;
+            }
+
+            let body = reqwest::Body::wrap_stream(body.into_data_stream());
+
+            Ok(Response::from(builder.body(body)?))
+        })
+    }
+}
+
+/// Socket path of a `UnixSocket::url` host
+fn decode(host: &str) -> Option<PathBuf> {
+    if host.is_empty() || !host.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = (0..host.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(host.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(OsString::from_vec(bytes).into())
+}
+
+fn builder(inner: http::uri::InvalidUri) -> Error {
+    Error::Builder(inner.to_string())
+}
+
+fn connect(inner: hyper::Error) -> Error {
+    Error::Connect(inner.to_string())
+}
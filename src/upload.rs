@@ -0,0 +1,381 @@
+use std::sync::Arc;
+
+use futures_util::io::{AsyncRead, AsyncReadExt};
+use http::{
+    header::{CONTENT_RANGE, CONTENT_TYPE, RANGE},
+    HeaderName, Method, StatusCode,
+};
+use reqwest::Response;
+
+use super::{
+    backoff::{Attempts, Backoff},
+    error::Error,
+    headers::parse_header_opt,
+    marker::{MaybeSend, MaybeSync},
+    request_id::request_id,
+    runtime::sleep,
+    throttle::Throttle,
+    transport::{builder_client, Transport},
+};
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+
+/// Protocol spoken by a resumable upload endpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// `PUT` chunks with `Content-Range`, answered with
+    /// `308` and a `Range` header until complete, as
+    /// used by Google resumable upload sessions
+    ContentRange,
+    /// tus.io 1.0: `PATCH` chunks with `Upload-Offset`
+    Tus,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Progress = dyn Fn(u64, Option<u64>) + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Progress = dyn Fn(u64, Option<u64>);
+
+/// Uploads a body in chunks to an upload session,
+/// resuming after failures
+///
+/// The session, i.e. the URL chunks are sent to, is
+/// created beforehand, typically with a regular request
+/// whose response carries it in the `Location` header.
+///
+/// After a transient error (see `Error::is_transient`)
+/// the server is asked for the confirmed offset and
+/// the upload resumes from there, waiting as long as
+/// rate limits ask to, up to the longest delay of the
+/// backoff. The current chunk
+/// is kept in memory for this, so the body is read
+/// only once. Chunks are sent sequentially, as both
+/// protocols require contiguous offsets
+#[derive(Clone)]
+pub struct ResumableUpload {
+    url: String,
+    protocol: Protocol,
+    chunk_size: usize,
+    total: Option<u64>,
+    backoff: Backoff,
+    max_retries: u32,
+    progress: Option<Arc<Progress>>,
+    throttle: Option<Throttle>,
+}
+
+/// Outcome of sending a chunk
+enum Sent {
+    /// Server confirmed everything up to the offset
+    Offset(u64),
+    /// Upload is complete
+    Complete(Response),
+}
+
+impl ResumableUpload {
+    /// Upload to the session at `url`
+    ///
+    /// Sends chunks of 8 MiB, retrying
+    /// up to 5 times per chunk by default
+    pub fn new<S: Into<String>>(url: S, protocol: Protocol) -> Self {
+        Self {
+            url: url.into(),
+            protocol,
+            chunk_size: 8 * 1024 * 1024,
+            total: None,
+            backoff: Backoff::default(),
+            max_retries: 5,
+            progress: None,
+            throttle: None,
+        }
+    }
+
+    /// Bytes per chunk
+    ///
+    /// Google requires multiples of 256 KiB
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Total size of the body, if known upfront
+    ///
+    /// Otherwise it is announced with the last chunk
+    pub fn total_size(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// Delay between retries
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up after `max_retries` consecutive
+    /// failed attempts on one chunk
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Cap the bandwidth of the transfer
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Report progress as the number of bytes
+    /// confirmed and the total size, if known
+    pub fn on_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(u64, Option<u64>) + MaybeSend + MaybeSync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Upload the whole body from `reader`
+    ///
+    /// Returns the response completing the upload
+    pub async fn upload<C, R>(&self, client: &C, reader: R) -> Result<Response, Error>
+    where
+        C: Transport + ?Sized,
+        R: AsyncRead + Unpin,
+    {
+        self.upload_from(client, reader, 0).await
+    }
+
+    /// Upload the rest of a body starting at `offset`,
+    /// e.g. as returned by `offset` after a restart
+    ///
+    /// `reader` must be positioned at `offset`
+    pub async fn upload_from<C, R>(
+        &self,
+        client: &C,
+        mut reader: R,
+        mut offset: u64,
+    ) -> Result<Response, Error>
+    where
+        C: Transport + ?Sized,
+        R: AsyncRead + Unpin,
+    {
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+
+        loop {
+            let last = read_chunk(&mut reader, &mut chunk, self.chunk_size).await?;
+
+            let start = offset;
+            let end = start + chunk.len() as u64;
+            let total = if last { Some(end) } else { self.total };
+
+            let mut failures = 0;
+            let mut attempts = Attempts::new();
+
+            loop {
+                let body = &chunk[(offset - start) as usize..];
+
+                let error = match self.send_chunk(client, body, offset, total, last).await {
+                    Ok(Sent::Complete(response)) => {
+                        self.report(total.unwrap_or(end), total);
+                        return Ok(response);
+                    }
+                    // Sending must make progress, or it never completes
+                    Ok(Sent::Offset(confirmed)) if confirmed > offset && confirmed <= end => {
+                        failures = 0;
+                        attempts = Attempts::new();
+                        offset = confirmed;
+                        self.report(offset, total);
+
+                        if offset == end && !last {
+                            break;
+                        }
+
+                        continue;
+                    }
+                    Ok(Sent::Offset(confirmed)) => return Err(offset_error(confirmed)),
+                    Err(err) => err,
+                };
+
+                if !error.is_transient() || failures >= self.max_retries {
+                    return Err(attempts.give_up(error));
+                }
+
+                let delay = self.backoff.retry_delay(failures, error.retry_after());
+                attempts.retry(error, delay);
+
+                sleep(delay).await;
+                failures += 1;
+
+                match self.query(client, total).await {
+                    Ok(Sent::Complete(response)) => {
+                        self.report(end, Some(end));
+                        return Ok(response);
+                    }
+                    Ok(Sent::Offset(confirmed)) if (start..=end).contains(&confirmed) => {
+                        offset = confirmed;
+                    }
+                    Ok(Sent::Offset(confirmed)) => return Err(offset_error(confirmed)),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Ask the server for the confirmed offset
+    pub async fn offset<C>(&self, client: &C) -> Result<u64, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        match self.query(client, self.total).await? {
+            Sent::Offset(offset) => Ok(offset),
+            Sent::Complete(_) => Ok(self.total.unwrap_or_default()),
+        }
+    }
+
+    async fn send_chunk<C>(
+        &self,
+        client: &C,
+        body: &[u8],
+        offset: u64,
+        total: Option<u64>,
+        last: bool,
+    ) -> Result<Sent, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let builder = match self.protocol {
+            Protocol::ContentRange => {
+                let total = total.map_or("*".to_string(), |total| total.to_string());
+
+                let range = match body.len() {
+                    0 => format!("bytes */{}", total),
+                    len => format!("bytes {}-{}/{}", offset, offset + len as u64 - 1, total),
+                };
+
+                builder_client().put(&self.url).header(CONTENT_RANGE, range)
+            }
+            Protocol::Tus => {
+                let mut builder = builder_client()
+                    .patch(&self.url)
+                    .header(TUS_RESUMABLE, "1.0.0")
+                    .header(UPLOAD_OFFSET, offset)
+                    .header(CONTENT_TYPE, "application/offset+octet-stream");
+
+                // Announce the length of deferred-length uploads
+                if let (true, None, Some(total)) = (last, self.total, total) {
+                    builder = builder.header(UPLOAD_LENGTH, total);
+                }
+
+                builder
+            }
+        };
+
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire(body.len() as u64).await;
+        }
+
+        let request = builder.body(body.to_vec()).build()?;
+
+        self.parse(client.execute(request).await?, total).await
+    }
+
+    async fn query<C>(&self, client: &C, total: Option<u64>) -> Result<Sent, Error>
+    where
+        C: Transport + ?Sized,
+    {
+        let builder = match self.protocol {
+            Protocol::ContentRange => {
+                let total = total.map_or("*".to_string(), |total| total.to_string());
+
+                builder_client()
+                    .put(&self.url)
+                    .header(CONTENT_RANGE, format!("bytes */{}", total))
+            }
+            Protocol::Tus => builder_client()
+                .request(Method::HEAD, &self.url)
+                .header(TUS_RESUMABLE, "1.0.0"),
+        };
+
+        let request = builder.build()?;
+
+        self.parse(client.execute(request).await?, total).await
+    }
+
+    async fn parse(&self, response: Response, total: Option<u64>) -> Result<Sent, Error> {
+        let status = response.status();
+
+        match self.protocol {
+            Protocol::ContentRange if status == StatusCode::PERMANENT_REDIRECT => {
+                // `Range: bytes=0-N` confirms N + 1 bytes
+                let range: Option<String> = parse_header_opt(response.headers(), RANGE.as_str())?;
+
+                let offset = match range.as_deref().and_then(|range| range.rsplit('-').next()) {
+                    Some(end) => end.parse::<u64>().map_err(|_| offset_error(0))? + 1,
+                    None => 0,
+                };
+
+                Ok(Sent::Offset(offset))
+            }
+            Protocol::ContentRange if status.is_success() => Ok(Sent::Complete(response)),
+            Protocol::Tus if status.is_success() => {
+                let offset: u64 = parse_header_opt(response.headers(), UPLOAD_OFFSET.as_str())?
+                    .ok_or_else(|| {
+                        Error::ClientDecodeError("missing header `upload-offset`".to_string())
+                    })?;
+
+                match total {
+                    Some(total) if offset >= total => Ok(Sent::Complete(response)),
+                    _ => Ok(Sent::Offset(offset)),
+                }
+            }
+            _ => {
+                let headers = response.headers().clone();
+                let request_id = request_id(&response);
+
+                Err(Error::from_status(
+                    status,
+                    &headers,
+                    response.json().await.ok(),
+                    request_id,
+                ))
+            }
+        }
+    }
+
+    fn report(&self, offset: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            progress(offset, total);
+        }
+    }
+}
+
+/// Fill `chunk` with up to `size` bytes,
+/// returning whether the reader is exhausted
+async fn read_chunk<R>(reader: &mut R, chunk: &mut Vec<u8>, size: usize) -> Result<bool, Error>
+where
+    R: AsyncRead + Unpin,
+{
+    chunk.clear();
+    chunk.resize(size, 0);
+
+    let mut filled = 0;
+
+    while filled < size {
+        match reader.read(&mut chunk[filled..]).await {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) => return Err(Error::ClientDecodeError(err.to_string())),
+        }
+    }
+
+    chunk.truncate(filled);
+
+    Ok(filled < size)
+}
+
+fn offset_error(offset: u64) -> Error {
+    Error::ClientDecodeError(format!("server confirmed unexpected offset {}", offset))
+}
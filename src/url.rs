@@ -0,0 +1,33 @@
+use reqwest::Url;
+
+use super::error::Error;
+
+/// Join an endpoint onto a base URL
+///
+/// The base URL is treated as a directory and the
+/// endpoint as relative to it, with or without slashes
+/// in between, so `https://api.example.com/v1` and
+/// `/users` give `https://api.example.com/v1/users`.
+/// An empty endpoint gives the base URL itself, and
+/// an absolute endpoint URL replaces the base URL,
+/// e.g. for pagination `next` links
+pub fn join(base_url: &str, endpoint: &str) -> Result<Url, Error> {
+    if let Some(url) = Url::parse(endpoint).ok().filter(Url::has_host) {
+        return Ok(url);
+    }
+
+    let mut base = Url::parse(base_url)?;
+
+    let endpoint = endpoint.trim_start_matches('/');
+
+    if endpoint.is_empty() {
+        return Ok(base);
+    }
+
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+
+    // Keep endpoints like `documents:get` from parsing as a scheme
+    Ok(base.join(&format!("./{}", endpoint))?)
+}
@@ -0,0 +1,102 @@
+use http::{header::USER_AGENT, HeaderValue};
+use reqwest::{Request, Response};
+
+use super::{
+    error::Error,
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Version of reqwest wrapi is built against
+const REQWEST: &str = "reqwest/0.12";
+
+/// Middleware sending a descriptive `User-Agent`
+/// with every request
+///
+/// Composed from the wrapper's name and version,
+/// an optional comment such as a contact URL and
+/// the versions of wrapi and reqwest, e.g.
+/// `my-wrapper/1.2.3 (+https://example.com) wrapi/0.1.0 reqwest/0.12`,
+/// as many public APIs reject requests without one.
+///
+/// Requests already carrying a `User-Agent`,
+/// e.g. from `Request::headers`, keep theirs
+#[derive(Clone, Debug)]
+pub struct UserAgent {
+    products: Vec<String>,
+    comment: Option<String>,
+    value: HeaderValue,
+}
+
+impl UserAgent {
+    /// Identify as `name/version`,
+    /// e.g. `env!("CARGO_PKG_NAME")` and
+    /// `env!("CARGO_PKG_VERSION")`
+    pub fn new<N, V>(name: N, version: V) -> Result<Self, http::Error>
+    where
+        N: AsRef<str>,
+        V: AsRef<str>,
+    {
+        Self {
+            products: vec![format!("{}/{}", name.as_ref(), version.as_ref())],
+            comment: None,
+            value: HeaderValue::from_static(""),
+        }
+        .compose()
+    }
+
+    /// Add a comment after the wrapper,
+    /// e.g. `+https://example.com` or
+    /// a contact email address
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Result<Self, http::Error> {
+        self.comment = Some(comment.into());
+        self.compose()
+    }
+
+    /// Add another product after the wrapper,
+    /// e.g. the application using it
+    pub fn product<N, V>(mut self, name: N, version: V) -> Result<Self, http::Error>
+    where
+        N: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.products
+            .push(format!("{}/{}", name.as_ref(), version.as_ref()));
+        self.compose()
+    }
+
+    /// The composed header value, e.g. for
+    /// `reqwest::ClientBuilder::user_agent`
+    pub fn value(&self) -> &HeaderValue {
+        &self.value
+    }
+
+    fn compose(mut self) -> Result<Self, http::Error> {
+        let mut parts = self.products.clone();
+
+        if let Some(comment) = &self.comment {
+            parts.insert(1, format!("({})", comment));
+        }
+
+        parts.push(format!("wrapi/{}", env!("CARGO_PKG_VERSION")));
+        parts.push(REQWEST.to_string());
+
+        self.value = HeaderValue::try_from(parts.join(" "))?;
+        Ok(self)
+    }
+}
+
+impl Middleware for UserAgent {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            if !request.headers().contains_key(USER_AGENT) {
+                request.headers_mut().insert(USER_AGENT, self.value.clone());
+            }
+
+            next.run(request).await
+        })
+    }
+}
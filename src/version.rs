@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use http::{header::ACCEPT, HeaderName, HeaderValue};
+use reqwest::{Request, Response, Url};
+
+use super::{
+    error::Error,
+    failover::{rebase, strip_base_url},
+    headers::{Deprecation, FromHeaders},
+    marker::{MaybeSend, MaybeSync},
+    middleware::{BoxFuture, Middleware, Next},
+};
+
+/// Header used for versions by `Strategy::Header` by default
+pub const API_VERSION: HeaderName = HeaderName::from_static("api-version");
+
+#[cfg(not(target_arch = "wasm32"))]
+type Hook = dyn Fn(&Url, &Deprecation) + Send + Sync;
+
+#[cfg(target_arch = "wasm32")]
+type Hook = dyn Fn(&Url, &Deprecation);
+
+/// How the API version is sent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Prefix the path of requests below the
+    /// base URL, e.g. `/v2/users` for `/users`
+    Path(String),
+    /// Set a header, e.g. `API_VERSION`
+    Header(HeaderName),
+    /// Add a `version` parameter to the
+    /// media ranges of the `Accept` header,
+    /// e.g. `application/json; version=2`
+    MediaType,
+}
+
+/// Middleware sending an API version
+/// with every request
+///
+/// Requests already carrying the version, in
+/// their path or headers, are left untouched.
+///
+/// Responses announcing a deprecation with the
+/// `Deprecation` or `Sunset` headers are reported
+/// to the `on_deprecation` hook, so wrappers learn
+/// when to move on to a newer version
+#[derive(Clone)]
+pub struct ApiVersion {
+    version: String,
+    strategy: Strategy,
+    on_deprecation: Option<Arc<Hook>>,
+}
+
+impl ApiVersion {
+    /// Send `version` as is, e.g. `v2`
+    /// for paths or `2` for headers
+    pub fn new<S: Into<String>>(version: S, strategy: Strategy) -> Self {
+        let strategy = match strategy {
+            Strategy::Path(base_url) => Strategy::Path(base_url.trim_end_matches('/').to_string()),
+            strategy => strategy,
+        };
+
+        Self {
+            version: version.into(),
+            strategy,
+            on_deprecation: None,
+        }
+    }
+
+    /// Call `hook` with the URL and deprecation
+    /// info of responses announcing one
+    pub fn on_deprecation<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Url, &Deprecation) + MaybeSend + MaybeSync + 'static,
+    {
+        self.on_deprecation = Some(Arc::new(hook));
+        self
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Add the version to `request`
+    fn apply(&self, request: &mut Request) {
+        match &self.strategy {
+            Strategy::Path(base_url) => {
+                let url = request.url().as_str();
+
+                let Some(rest) = strip_base_url(url, base_url) else {
+                    return;
+                };
+
+                let versioned = rest
+                    .strip_prefix('/')
+                    .and_then(|rest| rest.strip_prefix(self.version.as_str()))
+                    .is_some_and(|rest| {
+                        matches!(rest.chars().next(), None | Some('/' | '?' | '#'))
+                    });
+
+                if !versioned {
+                    if let Some(url) = rebase(base_url, &format!("/{}{}", self.version, rest)) {
+                        *request.url_mut() = url;
+                    }
+                }
+            }
+            Strategy::Header(name) => {
+                if !request.headers().contains_key(name) {
+                    if let Ok(value) = HeaderValue::try_from(self.version.as_str()) {
+                        request.headers_mut().insert(name.clone(), value);
+                    }
+                }
+            }
+            Strategy::MediaType => {
+                let accept = request
+                    .headers()
+                    .get(ACCEPT)
+                    .and_then(|accept| accept.to_str().ok())
+                    .unwrap_or("application/json");
+
+                let accept = accept
+                    .split(',')
+                    .map(str::trim)
+                    .map(|range| match range.contains("version=") {
+                        true => range.to_string(),
+                        false => format!("{}; version={}", range, self.version),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                if let Ok(value) = HeaderValue::try_from(accept) {
+                    request.headers_mut().insert(ACCEPT, value);
+                }
+            }
+        }
+    }
+}
+
+impl Middleware for ApiVersion {
+    fn handle<'a>(
+        &'a self,
+        mut request: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, Result<Response, Error>> {
+        Box::pin(async move {
+            self.apply(&mut request);
+
+            let response = next.run(request).await?;
+
+            if let Some(hook) = &self.on_deprecation {
+                if let Ok(deprecation) = Deprecation::from_headers(response.headers()) {
+                    hook(response.url(), &deprecation);
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,262 @@
+//! Receiving webhooks
+//!
+//! Verifies the HMAC-SHA256 signature of inbound
+//! webhook requests in constant time, checks the
+//! age of timestamped signatures and deserializes
+//! the payload, so wrappers can offer webhook
+//! handling alongside their client
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderName};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+use super::{decode, error::Error};
+
+/// How a webhook is signed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// `X-Hub-Signature-256: sha256=<hex>`
+    /// over the body
+    GitHub,
+    /// `Stripe-Signature: t=<timestamp>,v1=<hex>`
+    /// over `<timestamp>.<body>`
+    Stripe,
+    /// Hex signature over the body in a header
+    Hex(HeaderName),
+    /// Base64 signature over the body in a
+    /// header, e.g. `X-Shopify-Hmac-Sha256`
+    Base64(HeaderName),
+}
+
+/// Verifier of signed webhooks
+///
+/// Signatures are compared in constant time.
+/// Timestamped signatures older or newer than the
+/// tolerance, 5 minutes by default, are rejected
+/// to prevent replays.
+///
+/// All failures are `Error::InvalidSignature`
+#[derive(Clone)]
+pub struct Webhook {
+    secret: Vec<u8>,
+    scheme: Scheme,
+    tolerance: Duration,
+}
+
+impl Webhook {
+    /// Verify webhooks signed with `secret` as per `scheme`
+    pub fn new<S: AsRef<[u8]>>(secret: S, scheme: Scheme) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            scheme,
+            tolerance: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Verify GitHub webhooks
+    pub fn github<S: AsRef<[u8]>>(secret: S) -> Self {
+        Self::new(secret, Scheme::GitHub)
+    }
+
+    /// Verify Stripe webhooks
+    pub fn stripe<S: AsRef<[u8]>>(secret: S) -> Self {
+        Self::new(secret, Scheme::Stripe)
+    }
+
+    /// Maximum age of timestamped signatures
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verify the signature of a webhook
+    ///
+    /// Not available on `wasm32`, which has
+    /// no clock, see `verify_at`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), Error> {
+        self.verify_at(headers, body, SystemTime::now())
+    }
+
+    /// Verify the signature of a webhook
+    /// and deserialize its JSON payload
+    ///
+    /// Not available on `wasm32`, which has
+    /// no clock, see `parse_at`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn parse<T: DeserializeOwned>(&self, headers: &HeaderMap, body: &[u8]) -> Result<T, Error> {
+        self.parse_at(headers, body, SystemTime::now())
+    }
+
+    /// Like `parse`, at time `now`
+    pub fn parse_at<T: DeserializeOwned>(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<T, Error> {
+        self.verify_at(headers, body, now)?;
+
+        decode::json(body, false)
+    }
+
+    /// Like `verify`, at time `now`
+    ///
+    /// E.g. with the example of GitHub's documentation,
+    /// and a Stripe signature computed with `openssl`:
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use wrapi::{http::HeaderMap, webhook::Webhook};
+    ///
+    /// let now = UNIX_EPOCH + Duration::from_secs(1492774577);
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(
+    ///     "x-hub-signature-256",
+    ///     "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+    ///         .parse()
+    ///         .unwrap(),
+    /// );
+    ///
+    /// let github = Webhook::github("It's a Secret to Everybody");
+    /// assert!(github.verify_at(&headers, b"Hello, World!", now).is_ok());
+    /// assert!(github.verify_at(&headers, b"Hello, World?", now).is_err());
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(
+    ///     "stripe-signature",
+    ///     "t=1492774577,v1=91756ee38f5c256c6db5893fecc447d8729adb8023535a860e4c2b521391cec9"
+    ///         .parse()
+    ///         .unwrap(),
+    /// );
+    ///
+    /// let stripe = Webhook::stripe("whsec_test");
+    /// let body = br#"{"id":"evt_test"}"#;
+    /// assert!(stripe.verify_at(&headers, body, now).is_ok());
+    ///
+    /// // Replayed after the tolerance
+    /// let later = now + Duration::from_secs(10 * 60);
+    /// assert!(stripe.verify_at(&headers, body, later).is_err());
+    ///
+    /// // Timestamps out of range are rejected, not panicking
+    /// headers.insert("stripe-signature", "t=18446744073709551615,v1=00".parse().unwrap());
+    /// assert!(stripe.verify_at(&headers, body, now).is_err());
+    /// ```
+    pub fn verify_at(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| invalid(&format!("missing header `{}`", name)))
+        };
+
+        match &self.scheme {
+            Scheme::GitHub => {
+                let signature = header("x-hub-signature-256")?
+                    .strip_prefix("sha256=")
+                    .ok_or_else(|| invalid("unsupported algorithm"))?;
+
+                self.check(body, &hex(signature)?)
+            }
+            Scheme::Stripe => {
+                let value = header("stripe-signature")?;
+                let fields = || {
+                    value
+                        .split(',')
+                        .filter_map(|field| field.trim().split_once('='))
+                };
+
+                let timestamp = fields()
+                    .find(|(key, _)| *key == "t")
+                    .and_then(|(_, timestamp)| timestamp.parse::<u64>().ok())
+                    .ok_or_else(|| invalid("missing timestamp"))?;
+
+                let signed = UNIX_EPOCH
+                    .checked_add(Duration::from_secs(timestamp))
+                    .ok_or_else(|| invalid("timestamp outside of tolerance"))?;
+
+                self.check_age(signed, now)?;
+
+                let payload = [timestamp.to_string().as_bytes(), b".", body].concat();
+
+                // Any of the `v1` signatures, e.g. during secret rotation
+                let matched = fields()
+                    .filter(|(key, _)| *key == "v1")
+                    .any(|(_, signature)| {
+                        hex(signature)
+                            .is_ok_and(|signature| self.check(&payload, &signature).is_ok())
+                    });
+
+                match matched {
+                    true => Ok(()),
+                    false => Err(invalid("no matching signature")),
+                }
+            }
+            Scheme::Hex(name) => self.check(body, &hex(header(name.as_str())?)?),
+            Scheme::Base64(name) => {
+                let signature = STANDARD
+                    .decode(header(name.as_str())?.trim())
+                    .map_err(|_| invalid("invalid base64"))?;
+
+                self.check(body, &signature)
+            }
+        }
+    }
+
+    /// Check `signature` is the HMAC
+    /// of `payload` in constant time
+    fn check(&self, payload: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).map_err(|_| invalid("invalid secret"))?;
+
+        mac.update(payload);
+        mac.verify_slice(signature)
+            .map_err(|_| invalid("signature mismatch"))
+    }
+
+    fn check_age(&self, signed: SystemTime, now: SystemTime) -> Result<(), Error> {
+        let age = match now.duration_since(signed) {
+            Ok(age) => age,
+            Err(err) => err.duration(),
+        };
+
+        match age <= self.tolerance {
+            true => Ok(()),
+            false => Err(invalid("timestamp outside of tolerance")),
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidSignature(message.to_string())
+}
+
+/// Decode a hex string
+fn hex(value: &str) -> Result<Vec<u8>, Error> {
+    let value = value.trim().as_bytes();
+
+    if !value.len().is_multiple_of(2) {
+        return Err(invalid("invalid hex"));
+    }
+
+    value
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| invalid("invalid hex"))
+        })
+        .collect()
+}
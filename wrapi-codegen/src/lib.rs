@@ -0,0 +1,862 @@
+//! Code generation of wrapi wrappers from OpenAPI
+//!
+//! Reads an OpenAPI 3.x document in JSON and emits a Rust
+//! module with serde types for its schemas and a struct
+//! implementing `wrapi::request::Request` per operation,
+//! so large APIs can be wrapped mechanically and kept in
+//! sync by regenerating the module as the spec evolves.
+//!
+//! The generated module depends on `serde`, with the
+//! `derive` feature, and `serde_json` next to `wrapi`.
+//! It's not formatted, run it through `rustfmt`
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde_json::Value;
+
+/// HTTP methods of a path item, in emission order
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Keywords which can't be used as identifiers
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "box", "gen", "try",
+];
+
+/// Keywords which can't be raw identifiers either
+const RESERVED: [&str; 4] = ["self", "Self", "super", "crate"];
+
+#[derive(Debug)]
+pub enum Error {
+    /// Document is not valid JSON
+    Json(serde_json::Error),
+    /// Document is not an OpenAPI 3.x document
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(inner) => write!(f, "Invalid JSON: {}", inner),
+            Error::Invalid(inner) => write!(f, "Invalid OpenAPI document: {}", inner),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Generate a Rust module from an
+/// OpenAPI 3.x document in JSON
+pub fn generate(spec: &str) -> Result<String, Error> {
+    let spec: Value = serde_json::from_str(spec).map_err(Error::Json)?;
+
+    generate_value(&spec)
+}
+
+/// Generate a Rust module from a parsed
+/// OpenAPI 3.x document, e.g. converted
+/// from YAML by the caller
+pub fn generate_value(spec: &Value) -> Result<String, Error> {
+    match spec["openapi"].as_str() {
+        Some(version) if version.starts_with("3.") => {}
+        Some(version) => return Err(Error::Invalid(format!("unsupported version {}", version))),
+        None => return Err(Error::Invalid("missing `openapi` version".to_string())),
+    }
+
+    let mut generator = Generator {
+        spec,
+        types: BTreeMap::new(),
+        operations: Vec::new(),
+    };
+
+    if let Some(schemas) = spec
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+    {
+        for (name, schema) in schemas {
+            generator.define(&pascal(name), schema);
+        }
+    }
+
+    if let Some(paths) = spec["paths"].as_object() {
+        for (path, item) in paths {
+            let item = generator.resolve(item);
+
+            for method in METHODS {
+                if let Some(operation) = item.get(method) {
+                    generator.operation(path, method, item, operation);
+                }
+            }
+        }
+    }
+
+    Ok(generator.emit())
+}
+
+struct Generator<'a> {
+    spec: &'a Value,
+    /// Types by name, reserved
+    /// as empty while generating
+    types: BTreeMap<String, String>,
+    operations: Vec<String>,
+}
+
+/// A path, query or header parameter
+struct Parameter {
+    name: String,
+    field: String,
+    location: String,
+    ty: String,
+    required: bool,
+}
+
+impl<'a> Generator<'a> {
+    fn emit(self) -> String {
+        let info = &self.spec["info"];
+        let mut out = format!(
+            "// Generated by wrapi-codegen from {} {}, do not edit\n\n",
+            info["title"].as_str().unwrap_or("an OpenAPI document"),
+            info["version"].as_str().unwrap_or_default(),
+        );
+
+        out += "use std::collections::HashMap;\n\n";
+        out += "use serde::{Deserialize, Serialize};\n";
+        out += "use wrapi::{\n";
+        out += "    http::{HeaderMap, HeaderValue, Method},\n";
+        out += "    request::Request,\n";
+        out += "};\n\n";
+        out += "/// Format a path, query or header parameter\n";
+        out += "#[allow(dead_code)]\n";
+        out += "fn param<T: Serialize>(value: &T) -> String {\n";
+        out += "    let text = |value: &serde_json::Value| match value {\n";
+        out += "        serde_json::Value::String(value) => value.clone(),\n";
+        out += "        value => value.to_string(),\n";
+        out += "    };\n\n";
+        out += "    match serde_json::to_value(value) {\n";
+        out += "        Ok(serde_json::Value::Array(values)) => {\n";
+        out += "            values.iter().map(text).collect::<Vec<_>>().join(\",\")\n";
+        out += "        }\n";
+        out += "        Ok(value) => text(&value),\n";
+        out += "        Err(_) => String::new(),\n";
+        out += "    }\n";
+        out += "}\n";
+
+        for code in self.types.values().chain(&self.operations) {
+            out += "\n";
+            out += code;
+        }
+
+        out
+    }
+
+    /// Follow a local `$ref`, if any
+    fn resolve(&self, value: &'a Value) -> &'a Value {
+        match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+            Some(pointer) => self.spec.pointer(pointer).unwrap_or(value),
+            None => value,
+        }
+    }
+
+    /// Define the named type `name` for `schema`
+    fn define(&mut self, name: &str, schema: &'a Value) {
+        if self.types.contains_key(name) {
+            return;
+        }
+
+        if is_object(schema) {
+            self.define_struct(name, schema);
+        } else if is_string_enum(schema) {
+            self.define_enum(name, schema);
+        } else if is_union(schema) {
+            self.define_union(name, schema);
+        } else {
+            self.types.insert(name.to_string(), String::new());
+
+            let ty = self.rust_type(schema, &format!("{}Item", name));
+            let code = format!("{}pub type {} = {};\n", doc(schema, ""), name, ty);
+
+            self.types.insert(name.to_string(), code);
+        }
+    }
+
+    fn define_struct(&mut self, name: &str, schema: &'a Value) {
+        self.types.insert(name.to_string(), String::new());
+
+        let mut fields = String::new();
+        let mut members = vec![schema];
+
+        // Inline `allOf` members are merged, references flattened
+        while let Some(member) = members.pop() {
+            for part in member["allOf"].as_array().into_iter().flatten() {
+                match part["$ref"].as_str() {
+                    Some(reference) => {
+                        let ty = pascal(reference.rsplit('/').next().unwrap_or(reference));
+
+                        fields += "    #[serde(flatten)]\n";
+                        fields += &format!("    pub {}: {},\n", snake(&ty), ty);
+                    }
+                    None => members.push(part),
+                }
+            }
+
+            let required = strings(&member["required"]);
+
+            for (property, schema) in member["properties"].as_object().into_iter().flatten() {
+                let field = snake(property);
+                let context = format!("{}{}", name, pascal(property));
+                let mut ty = self.rust_type(schema, &context);
+
+                if ty == name {
+                    ty = format!("Box<{}>", ty);
+                }
+
+                let optional = !required.contains(property.as_str()) || is_nullable(schema);
+                let mut attributes = Vec::new();
+
+                if field.trim_start_matches("r#") != property {
+                    attributes.push(format!("rename = {:?}", property));
+                }
+
+                if optional {
+                    ty = format!("Option<{}>", ty);
+                    attributes.push("default".to_string());
+                    attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+                }
+
+                fields += &doc(schema, "    ");
+
+                if !attributes.is_empty() {
+                    fields += &format!("    #[serde({})]\n", attributes.join(", "));
+                }
+
+                fields += &format!("    pub {}: {},\n", field, ty);
+            }
+        }
+
+        let code = format!(
+            "{}#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\npub struct {} {{\n{}}}\n",
+            doc(schema, ""),
+            name,
+            fields
+        );
+
+        self.types.insert(name.to_string(), code);
+    }
+
+    fn define_enum(&mut self, name: &str, schema: &'a Value) {
+        let mut seen = BTreeSet::new();
+        let mut variants = String::new();
+
+        for value in schema["enum"].as_array().into_iter().flatten() {
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+
+            let variant = match value.is_empty() {
+                true => "Empty".to_string(),
+                false => pascal(value),
+            };
+
+            if !seen.insert(variant.clone()) {
+                continue;
+            }
+
+            if variant != value {
+                variants += &format!("    #[serde(rename = {:?})]\n", value);
+            }
+
+            variants += &format!("    {},\n", variant);
+        }
+
+        let code = format!(
+            "{}#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]\npub enum {} {{\n{}}}\n",
+            doc(schema, ""),
+            name,
+            variants
+        );
+
+        self.types.insert(name.to_string(), code);
+    }
+
+    /// An untagged enum of the referenced
+    /// types of `oneOf` or `anyOf`
+    fn define_union(&mut self, name: &str, schema: &'a Value) {
+        let members = schema["oneOf"]
+            .as_array()
+            .or_else(|| schema["anyOf"].as_array())
+            .into_iter()
+            .flatten();
+
+        let mut variants = String::new();
+
+        for member in members {
+            let reference = member["$ref"].as_str().unwrap_or_default();
+            let ty = pascal(reference.rsplit('/').next().unwrap_or(reference));
+
+            variants += &format!("    {}({}),\n", ty, ty);
+        }
+
+        let code = format!(
+            "{}#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n#[serde(untagged)]\npub enum {} {{\n{}}}\n",
+            doc(schema, ""),
+            name,
+            variants
+        );
+
+        self.types.insert(name.to_string(), code);
+    }
+
+    /// The Rust type of `schema`, defining
+    /// inline types as `context` if needed
+    fn rust_type(&mut self, schema: &'a Value, context: &str) -> String {
+        if let Some(reference) = schema["$ref"].as_str() {
+            return pascal(reference.rsplit('/').next().unwrap_or(reference));
+        }
+
+        if let Some([member]) = schema["allOf"].as_array().map(Vec::as_slice) {
+            return self.rust_type(member, context);
+        }
+
+        if is_object(schema) || is_string_enum(schema) || is_union(schema) {
+            self.define(context, schema);
+
+            return context.to_string();
+        }
+
+        match schema_type(schema) {
+            Some("string") => "String".to_string(),
+            Some("boolean") => "bool".to_string(),
+            Some("integer") => match schema["format"].as_str() {
+                Some("int32") => "i32".to_string(),
+                _ => "i64".to_string(),
+            },
+            Some("number") => match schema["format"].as_str() {
+                Some("float") => "f32".to_string(),
+                _ => "f64".to_string(),
+            },
+            Some("array") => {
+                let item = self.rust_type(&schema["items"], &format!("{}Item", context));
+
+                format!("Vec<{}>", item)
+            }
+            Some("object") if schema["additionalProperties"].is_object() => {
+                let value = self.rust_type(
+                    &schema["additionalProperties"],
+                    &format!("{}Value", context),
+                );
+
+                format!("HashMap<String, {}>", value)
+            }
+            _ => "serde_json::Value".to_string(),
+        }
+    }
+
+    fn operation(&mut self, path: &str, method: &str, item: &'a Value, operation: &'a Value) {
+        let mut name = match operation["operationId"].as_str() {
+            Some(id) => pascal(id),
+            None => pascal(&format!("{} {}", method, path)),
+        };
+
+        if self.types.contains_key(&name) {
+            name += "Request";
+        }
+
+        let parameters = self.parameters(&name, item, operation);
+        let (body, body_required) = self.request_body(&name, operation);
+        let response = self.response(&name, operation);
+
+        let mut code = doc_text(
+            operation["summary"]
+                .as_str()
+                .or_else(|| operation["description"].as_str()),
+            "",
+        );
+
+        if !code.is_empty() {
+            code += "///\n";
+        }
+
+        code += &format!("/// `{} {}`\n", method.to_uppercase(), path);
+
+        if response.is_none() {
+            code += "///\n/// Responds without a JSON body, send it with `exec_opt`\n";
+        }
+
+        code += &format!(
+            "#[derive(Clone, Debug, PartialEq)]\npub struct {} {{\n",
+            name
+        );
+
+        for parameter in &parameters {
+            code += &doc_text(
+                Some(&format!(
+                    "{} parameter `{}`",
+                    parameter.location, parameter.name
+                )),
+                "    ",
+            );
+
+            match parameter.required {
+                true => code += &format!("    pub {}: {},\n", parameter.field, parameter.ty),
+                false => {
+                    code += &format!("    pub {}: Option<{}>,\n", parameter.field, parameter.ty)
+                }
+            }
+        }
+
+        if let Some(body) = &body {
+            match body_required {
+                true => code += &format!("    pub body: {},\n", body),
+                false => code += &format!("    pub body: Option<{}>,\n", body),
+            }
+        }
+
+        code += "}\n\n";
+
+        // The body alone is serialized, parameters are sent separately
+        code += &format!("impl Serialize for {} {{\n", name);
+        code += "    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {\n";
+
+        match body {
+            Some(_) => code += "        self.body.serialize(serializer)\n",
+            None => code += "        serializer.serialize_unit()\n",
+        }
+
+        code += "    }\n}\n\n";
+
+        code += &format!(
+            "impl Request<{}> for {} {{\n",
+            response.as_deref().unwrap_or("serde_json::Value"),
+            name
+        );
+        code += &self.endpoint(path, &parameters);
+        code += "\n    fn method(&self) -> Method {\n";
+        code += &format!("        Method::{}\n", method.to_uppercase());
+        code += "    }\n";
+        code += &query(&parameters);
+        code += &headers(&parameters);
+
+        match (body.is_some(), body_required) {
+            (true, true) => {}
+            (true, false) => {
+                code += "\n    fn body(&self) -> Option<&Self> {\n";
+                code += "        self.body.as_ref().map(|_| self)\n";
+                code += "    }\n";
+            }
+            (false, _) => {
+                code += "\n    fn body(&self) -> Option<&Self> {\n";
+                code += "        None\n";
+                code += "    }\n";
+            }
+        }
+
+        code += "}\n";
+
+        self.operations.push(code);
+    }
+
+    /// Path, query and header parameters of
+    /// `operation`, overriding those of `item`
+    fn parameters(&mut self, name: &str, item: &'a Value, operation: &'a Value) -> Vec<Parameter> {
+        let mut declared: Vec<&'a Value> = Vec::new();
+
+        for parameter in item["parameters"]
+            .as_array()
+            .into_iter()
+            .chain(operation["parameters"].as_array())
+            .flatten()
+        {
+            let parameter = self.resolve(parameter);
+
+            declared.retain(|existing| {
+                existing["name"] != parameter["name"] || existing["in"] != parameter["in"]
+            });
+            declared.push(parameter);
+        }
+
+        let mut fields = BTreeSet::new();
+
+        declared
+            .into_iter()
+            .filter_map(|parameter| {
+                let location = parameter["in"].as_str()?;
+                let param_name = parameter["name"].as_str()?;
+
+                if !matches!(location, "path" | "query" | "header") {
+                    return None;
+                }
+
+                let mut field = snake(param_name);
+
+                if field == "body" || !fields.insert(field.clone()) {
+                    field = format!("{}_{}", location, field.trim_start_matches("r#"));
+                    fields.insert(field.clone());
+                }
+
+                let context = format!("{}{}", name, pascal(param_name));
+
+                Some(Parameter {
+                    name: param_name.to_string(),
+                    field,
+                    location: location.to_string(),
+                    ty: self.rust_type(&parameter["schema"], &context),
+                    required: location == "path" || parameter["required"] == true,
+                })
+            })
+            .collect()
+    }
+
+    /// Type of the JSON request body and whether it's required
+    fn request_body(&mut self, name: &str, operation: &'a Value) -> (Option<String>, bool) {
+        let body = self.resolve(&operation["requestBody"]);
+
+        match json_schema(&body["content"]) {
+            Some(schema) => (
+                Some(self.rust_type(schema, &format!("{}Body", name))),
+                body["required"] == true,
+            ),
+            None => (None, false),
+        }
+    }
+
+    /// Type of the first successful JSON response
+    fn response(&mut self, name: &str, operation: &'a Value) -> Option<String> {
+        let responses = operation["responses"].as_object()?;
+
+        responses
+            .iter()
+            .filter(|(status, _)| status.starts_with('2'))
+            .find_map(|(_, response)| json_schema(&self.resolve(response)["content"]))
+            .map(|schema| self.rust_type(schema, &format!("{}Response", name)))
+    }
+
+    fn endpoint(&self, path: &str, parameters: &[Parameter]) -> String {
+        let mut template = String::new();
+        let mut arguments = Vec::new();
+        let mut rest = path;
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+
+            let placeholder = &rest[start + 1..start + end];
+            let field = parameters
+                .iter()
+                .find(|parameter| parameter.location == "path" && parameter.name == placeholder)
+                .map(|parameter| parameter.field.clone());
+
+            template += &rest[..start].replace('{', "{{").replace('}', "}}");
+
+            match field {
+                Some(field) => {
+                    template += "{}";
+                    arguments.push(format!("param(&self.{})", field));
+                }
+                None => template += &format!("{{{{{}}}}}", placeholder),
+            }
+
+            rest = &rest[start + end + 1..];
+        }
+
+        template += &rest.replace('{', "{{").replace('}', "}}");
+
+        let body = match arguments.is_empty() {
+            true => format!("{:?}.to_string()", template),
+            false => format!("format!({:?}, {})", template, arguments.join(", ")),
+        };
+
+        format!(
+            "    fn endpoint(&self) -> String {{\n        {}\n    }}\n",
+            body
+        )
+    }
+}
+
+fn query(parameters: &[Parameter]) -> String {
+    let query: Vec<&Parameter> = parameters
+        .iter()
+        .filter(|parameter| parameter.location == "query")
+        .collect();
+
+    if query.is_empty() {
+        return String::new();
+    }
+
+    let mut code = String::from("\n    fn query(&self) -> Option<HashMap<String, String>> {\n");
+    code += "        let mut query = HashMap::new();\n\n";
+
+    for parameter in query {
+        let insert = format!(
+            "query.insert({:?}.to_string(), param(value));",
+            parameter.name
+        );
+
+        match parameter.required {
+            true => {
+                code += &format!(
+                    "        let value = &self.{};\n        {}\n",
+                    parameter.field, insert
+                )
+            }
+            false => {
+                code += &format!(
+                    "        if let Some(value) = &self.{} {{\n            {}\n        }}\n",
+                    parameter.field, insert
+                )
+            }
+        }
+    }
+
+    code += "\n        Some(query)\n    }\n";
+    code
+}
+
+fn headers(parameters: &[Parameter]) -> String {
+    let headers: Vec<&Parameter> = parameters
+        .iter()
+        .filter(|parameter| parameter.location == "header")
+        .collect();
+
+    if headers.is_empty() {
+        return String::new();
+    }
+
+    let mut code = String::from("\n    fn headers(&self) -> Option<HeaderMap> {\n");
+    code += "        let mut headers = HeaderMap::new();\n\n";
+
+    for parameter in headers {
+        let insert = format!(
+            "if let Ok(value) = HeaderValue::try_from(param(value)) {{\n{indent}    headers.insert({:?}, value);\n{indent}}}",
+            parameter.name.to_lowercase(),
+            indent = match parameter.required {
+                true => "        ",
+                false => "            ",
+            }
+        );
+
+        match parameter.required {
+            true => {
+                code += &format!(
+                    "        let value = &self.{};\n        {}\n",
+                    parameter.field, insert
+                )
+            }
+            false => {
+                code += &format!(
+                    "        if let Some(value) = &self.{} {{\n            {}\n        }}\n",
+                    parameter.field, insert
+                )
+            }
+        }
+    }
+
+    code += "\n        Some(headers)\n    }\n";
+    code
+}
+
+/// Schema of the JSON media type of `content`
+fn json_schema(content: &Value) -> Option<&Value> {
+    let content = content.as_object()?;
+
+    content
+        .get("application/json")
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(media_type, _)| media_type.ends_with("+json"))
+                .map(|(_, media)| media)
+        })
+        .map(|media| &media["schema"])
+        .filter(|schema| !schema.is_null())
+}
+
+/// The non-null `type` of a schema
+fn schema_type(schema: &Value) -> Option<&str> {
+    match &schema["type"] {
+        Value::String(ty) => Some(ty),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|ty| *ty != "null"),
+        _ => None,
+    }
+}
+
+fn is_nullable(schema: &Value) -> bool {
+    schema["nullable"] == true
+        || schema["type"]
+            .as_array()
+            .is_some_and(|types| types.iter().any(|ty| ty == "null"))
+}
+
+fn is_object(schema: &Value) -> bool {
+    schema["properties"].is_object()
+        || schema["allOf"]
+            .as_array()
+            .is_some_and(|members| members.len() > 1)
+}
+
+fn is_string_enum(schema: &Value) -> bool {
+    schema["enum"]
+        .as_array()
+        .is_some_and(|values| !values.is_empty() && values.iter().all(Value::is_string))
+}
+
+fn is_union(schema: &Value) -> bool {
+    schema["oneOf"]
+        .as_array()
+        .or_else(|| schema["anyOf"].as_array())
+        .is_some_and(|members| {
+            !members.is_empty() && members.iter().all(|member| member["$ref"].is_string())
+        })
+}
+
+fn strings(value: &Value) -> BTreeSet<&str> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+/// Doc comment from the description or title of `schema`
+fn doc(schema: &Value, indent: &str) -> String {
+    doc_text(
+        schema["description"]
+            .as_str()
+            .or_else(|| schema["title"].as_str()),
+        indent,
+    )
+}
+
+fn doc_text(text: Option<&str>, indent: &str) -> String {
+    let Some(text) = text.map(str::trim).filter(|text| !text.is_empty()) else {
+        return String::new();
+    };
+
+    let mut fenced = false;
+
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+
+            // Keep code blocks of the spec out of doctests
+            let line = match line.starts_with("```") {
+                true => {
+                    fenced = !fenced;
+
+                    match fenced {
+                        true => "```text",
+                        false => "```",
+                    }
+                }
+                false => line,
+            };
+
+            match line.is_empty() {
+                true => format!("{}///\n", indent),
+                false => format!("{}/// {}\n", indent, line),
+            }
+        })
+        .collect()
+}
+
+/// Split `text` into words at non-alphanumeric
+/// characters and case changes
+fn words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let boundary = i > 0
+            && c.is_uppercase()
+            && (chars[i - 1].is_lowercase()
+                || chars[i - 1].is_ascii_digit()
+                || (chars[i - 1].is_uppercase()
+                    && chars.get(i + 1).is_some_and(|next| next.is_lowercase())));
+
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+
+        word.push(c);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// `PascalCase` identifier of `text`
+fn pascal(text: &str) -> String {
+    let mut name: String = words(text)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    if name.is_empty() {
+        name = "Type".to_string();
+    }
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'V');
+    }
+
+    identifier(name)
+}
+
+/// `snake_case` identifier of `text`
+fn snake(text: &str) -> String {
+    let mut name = words(text)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    if name.is_empty() {
+        name = "value".to_string();
+    }
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    identifier(name)
+}
+
+/// Escape keywords in `name`
+fn identifier(name: String) -> String {
+    if RESERVED.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
@@ -0,0 +1,42 @@
+//! `wrapi-codegen <openapi.json> [<output.rs>]`
+//!
+//! Generates a Rust module from an OpenAPI 3.x
+//! document, printing it unless an output is given
+
+use std::{env, fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (input, output) = match args.as_slice() {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => {
+            eprintln!("usage: wrapi-codegen <openapi.json> [<output.rs>]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let module = fs::read_to_string(input)
+        .map_err(|err| format!("failed to read {}: {}", input, err))
+        .and_then(|spec| wrapi_codegen::generate(&spec).map_err(|err| err.to_string()));
+
+    let result = match (module, output) {
+        (Ok(module), Some(output)) => {
+            fs::write(output, module).map_err(|err| format!("failed to write {}: {}", output, err))
+        }
+        (Ok(module), None) => {
+            print!("{}", module);
+            Ok(())
+        }
+        (Err(err), _) => Err(err),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! Derive macros of wrapi
+//!
+//! Use them through wrapi's `derive` feature
+//! rather than depending on this crate directly
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parenthesized, parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Ident,
+    Lit, LitStr, Meta, Token, Type,
+};
+
+/// Methods of derived requests and described endpoints
+const METHODS: [&str; 8] = [
+    "GET", "PUT", "POST", "DELETE", "OPTIONS", "HEAD", "PATCH", "TRACE",
+];
+
+/// Derive `wrapi::describe::Describe`
+///
+/// See the trait for the attributes
+#[proc_macro_derive(Describe, attributes(describe))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    describe(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn describe(input: &DeriveInput) -> Result<TokenStream2, syn::Error> {
+    let mut method = None;
+    let mut path = None;
+    let mut summary = summary(&input.attrs);
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("describe"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let value: LitStr = meta.value()?.parse()?;
+
+            if meta.path.is_ident("method") {
+                let name = value.value().to_uppercase();
+
+                if !METHODS.contains(&name.as_str()) {
+                    return Err(syn::Error::new(value.span(), "unsupported method"));
+                }
+
+                method = Some(Ident::new(&name, value.span()));
+            } else if meta.path.is_ident("path") {
+                path = Some(value);
+            } else if meta.path.is_ident("summary") {
+                summary = Some(value.value());
+            } else {
+                return Err(meta.error("expected `method`, `path` or `summary`"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let method = method.ok_or_else(|| {
+        syn::Error::new(input.ident.span(), "missing `#[describe(method = \"..\")]`")
+    })?;
+    let path = path.ok_or_else(|| {
+        syn::Error::new(input.ident.span(), "missing `#[describe(path = \"..\")]`")
+    })?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            _ => Vec::new(),
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "`Describe` can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut parameters = Vec::new();
+    let mut path_parameters = Vec::new();
+    let mut body = false;
+
+    for field in fields {
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("describe"))
+        {
+            let default = field
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string().trim_start_matches("r#").to_string())
+                .unwrap_or_default();
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("body") {
+                    body = true;
+                    return Ok(());
+                }
+
+                let location = if meta.path.is_ident("path") {
+                    quote!(Path)
+                } else if meta.path.is_ident("query") {
+                    quote!(Query)
+                } else if meta.path.is_ident("header") {
+                    quote!(Header)
+                } else {
+                    return Err(meta.error("expected `path`, `query`, `header` or `body`"));
+                };
+
+                let name = match meta.input.peek(Token![=]) {
+                    true => meta.value()?.parse::<LitStr>()?.value(),
+                    false => default.clone(),
+                };
+
+                if meta.path.is_ident("path") {
+                    path_parameters.push(name.clone());
+                }
+
+                let required = !is_option(&field.ty);
+
+                parameters.push(quote! {
+                    ::wrapi::describe::Parameter {
+                        name: #name.to_string(),
+                        location: ::wrapi::describe::Location::#location,
+                        required: #required,
+                    }
+                });
+
+                Ok(())
+            })?;
+        }
+    }
+
+    // Every placeholder of the path needs a path parameter
+    for placeholder in path.value().split('{').skip(1) {
+        let placeholder = placeholder.split('}').next().unwrap_or_default();
+
+        if !path_parameters.iter().any(|name| name == placeholder) {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("no `#[describe(path)]` field for `{{{}}}`", placeholder),
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let operation = ident.to_string();
+    let summary = match summary {
+        Some(summary) => quote!(Some(#summary.to_string())),
+        None => quote!(None),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::wrapi::describe::Describe for #ident #ty_generics #where_clause {
+            fn describe() -> ::wrapi::describe::Endpoint {
+                ::wrapi::describe::Endpoint {
+                    operation: #operation.to_string(),
+                    method: ::wrapi::http::Method::#method,
+                    path: #path.to_string(),
+                    summary: #summary,
+                    parameters: vec![#(#parameters),*],
+                    body: #body,
+                }
+            }
+        }
+    })
+}
+
+/// Derive `wrapi::request::Request`
+///
+/// See the re-export in wrapi for the attributes
+#[proc_macro_derive(Request, attributes(request))]
+pub fn derive_request(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    request(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn request(input: &DeriveInput) -> Result<TokenStream2, syn::Error> {
+    let mut method = None;
+    let mut endpoint = None;
+    let mut response = None;
+    let mut headers = Vec::new();
+    let mut body = true;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("request"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("method") {
+                let value: LitStr = meta.value()?.parse()?;
+                let name = value.value().to_uppercase();
+
+                if !METHODS.contains(&name.as_str()) {
+                    return Err(syn::Error::new(value.span(), "unsupported method"));
+                }
+
+                method = Some(Ident::new(&name, value.span()));
+            } else if meta.path.is_ident("endpoint") {
+                endpoint = Some(template(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("response") {
+                response = Some(meta.value()?.parse::<Type>()?);
+            } else if meta.path.is_ident("header") {
+                let content;
+                parenthesized!(content in meta.input);
+
+                let name: LitStr = content.parse()?;
+                content.parse::<Token![=]>()?;
+                let value: LitStr = content.parse()?;
+
+                headers.push((header_name(&name)?, template(&value)?));
+            } else if meta.path.is_ident("no_body") {
+                body = false;
+            } else {
+                return Err(
+                    meta.error("expected `method`, `endpoint`, `response`, `header` or `no_body`")
+                );
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let missing = |attribute: &str| {
+        syn::Error::new(
+            input.ident.span(),
+            format!("missing `#[request({} = ..)]`", attribute),
+        )
+    };
+
+    let method = method.ok_or_else(|| missing("method"))?;
+    let endpoint = endpoint.ok_or_else(|| missing("endpoint"))?;
+    let response = response.ok_or_else(|| missing("response"))?;
+
+    let headers = match headers.is_empty() {
+        true => quote!(),
+        false => {
+            let inserts = headers.iter().map(|(name, value)| {
+                quote! {
+                    if let Ok(value) = ::wrapi::http::HeaderValue::try_from(#value) {
+                        headers.insert(::wrapi::http::HeaderName::from_static(#name), value);
+                    }
+                }
+            });
+
+            quote! {
+                fn headers(&self) -> Option<::wrapi::http::HeaderMap> {
+                    let mut headers = ::wrapi::http::HeaderMap::new();
+                    #(#inserts)*
+                    Some(headers)
+                }
+            }
+        }
+    };
+
+    let body = match body {
+        true => quote!(),
+        false => quote! {
+            fn body(&self) -> Option<&Self> {
+                None
+            }
+        },
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::wrapi::request::Request<#response> for #ident #ty_generics #where_clause {
+            fn endpoint(&self) -> String {
+                #endpoint
+            }
+
+            fn method(&self) -> ::wrapi::http::Method {
+                ::wrapi::http::Method::#method
+            }
+
+            #headers
+            #body
+        }
+    })
+}
+
+/// `format!` of a template such as
+/// `/users/{id}`, with `{field}`
+/// placeholders taken from `self`
+fn template(template: &LitStr) -> Result<TokenStream2, syn::Error> {
+    let text = template.value();
+    let mut fields: Vec<Ident> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let name: String = chars
+                    .by_ref()
+                    .take_while(|c| *c != '}')
+                    .collect::<String>()
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+
+                let field = syn::parse_str::<Ident>(&name).map_err(|_| {
+                    syn::Error::new(
+                        template.span(),
+                        format!("`{{{}}}` is not a field placeholder", name),
+                    )
+                })?;
+
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let arguments = fields.iter().map(|field| quote!(#field = self.#field));
+
+    Ok(quote!(::std::format!(#template #(, #arguments)*)))
+}
+
+/// Lowercase header name, checked to be
+/// a valid token at compile time
+fn header_name(name: &LitStr) -> Result<String, syn::Error> {
+    let lower = name.value().to_lowercase();
+
+    let valid = !lower.is_empty()
+        && lower
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+
+    match valid {
+        true => Ok(lower),
+        false => Err(syn::Error::new(name.span(), "invalid header name")),
+    }
+}
+
+/// First line of the doc comment
+fn summary(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .find_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}